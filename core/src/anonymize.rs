@@ -0,0 +1,147 @@
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+use crate::{DynamicImportWarning, ProjectFile};
+
+const BLOCK_SIZE: usize = 64;
+
+/// HMAC-SHA256 of `message` under `key` (RFC 2104).
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    let mut block = [0u8; BLOCK_SIZE];
+    if key.len() > BLOCK_SIZE {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let pad = |byte: u8| block.map(|b| b ^ byte);
+    let inner = Sha256::new().chain_update(pad(0x36)).chain_update(message).finalize();
+    Sha256::new().chain_update(pad(0x5c)).chain_update(inner).finalize().into()
+}
+
+struct Anonymizer<'a> {
+    key: &'a [u8],
+}
+
+impl Anonymizer<'_> {
+    /// `<prefix>_<first 16 hex digits of the keyed hash>`; the prefix keeps kinds of
+    /// identifiers apart.
+    fn id(&self, prefix: &str, value: &str) -> String {
+        let mac = hmac_sha256(self.key, format!("{}\0{}", prefix, value).as_bytes());
+        format!("{}_{}", prefix, hex::encode(&mac[..8]))
+    }
+
+    fn ids(&self, prefix: &str, values: &[String]) -> Vec<String> {
+        values.iter().map(|value| self.id(prefix, value)).collect()
+    }
+}
+
+/// Copy of `dependency_map` with every path and project or third-party module name
+/// replaced by an opaque identifier derived from `key`, for sharing the graph's
+/// structure without its code organization. Any export can then be run on it.
+///
+/// The same key always gives the same identifiers, so maps anonymized with it can
+/// be compared. Content hashes are re-keyed as well, so they still show which files
+/// changed without matching public files. Standard library imports are kept;
+/// modification times, commit authors and dynamic import source are dropped.
+pub fn anonymize_map(dependency_map: &HashMap<String, ProjectFile>, key: &str) -> HashMap<String, ProjectFile> {
+    let anonymizer = Anonymizer { key: key.as_bytes() };
+    dependency_map
+        .iter()
+        .map(|(path, info)| {
+            let file_id = anonymizer.id("file", path);
+            let anonymized = ProjectFile {
+                hash: anonymizer.id("hash", &info.hash),
+                project_imports: anonymizer.ids("file", &info.project_imports),
+                stdlib_imports: info.stdlib_imports.clone(),
+                third_party_imports: anonymizer.ids("module", &info.third_party_imports),
+                size: info.size,
+                mtime_ns: 0,
+                volatile: info.volatile,
+                degraded: info.degraded,
+                dynamic_imports: anonymizer.ids("file", &info.dynamic_imports),
+                observed_imports: anonymizer.ids("file", &info.observed_imports),
+                import_aliases: info
+                    .import_aliases
+                    .iter()
+                    .map(|(alias, module)| (anonymizer.id("alias", alias), anonymizer.id("module", module)))
+                    .collect(),
+                third_party_modules: anonymizer.ids("module", &info.third_party_modules),
+                unresolved_imports: anonymizer.ids("module", &info.unresolved_imports),
+                import_kinds: info
+                    .import_kinds
+                    .iter()
+                    .map(|(module, kind)| (anonymizer.id("module", module), *kind))
+                    .collect(),
+                import_statements: info
+                    .import_statements
+                    .iter()
+                    .map(|(target, count)| {
+                        let target = if info.project_imports.contains(target) {
+                            anonymizer.id("file", target)
+                        } else if info.stdlib_imports.contains(target) {
+                            target.clone()
+                        } else {
+                            anonymizer.id("module", target)
+                        };
+                        (target, *count)
+                    })
+                    .collect(),
+                dynamic_import_warnings: info
+                    .dynamic_import_warnings
+                    .iter()
+                    .map(|warning| DynamicImportWarning {
+                        path: file_id.clone(),
+                        line: warning.line,
+                        expression: String::new(),
+                    })
+                    .collect(),
+                last_commit: None,
+                imports_container: info.imports_container,
+                // The parse holds the real module names.
+                parsed: None,
+            };
+            (file_id, anonymized)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hmac_sha256_matches_rfc_4231() {
+        let mac = hmac_sha256(b"Jefe", b"what do ya want for nothing?");
+        assert_eq!(hex::encode(mac), "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843");
+    }
+
+    #[test]
+    fn test_anonymize_map_preserves_structure() {
+        let mut map = HashMap::new();
+        map.insert("/repo/app/main.py".to_string(), ProjectFile {
+            hash: "h-main".to_string(),
+            project_imports: vec!["/repo/app/secret_billing.py".to_string()],
+            stdlib_imports: vec!["os".to_string()],
+            third_party_imports: vec!["internal_sdk".to_string()],
+            mtime_ns: 42,
+            ..Default::default()
+        });
+        map.insert("/repo/app/secret_billing.py".to_string(), ProjectFile { hash: "h-billing".to_string(), ..Default::default() });
+
+        let anonymized = anonymize_map(&map, "s3cret");
+        assert_eq!(anonymized.len(), 2);
+        let main_id = Anonymizer { key: b"s3cret" }.id("file", "/repo/app/main.py");
+        let main = &anonymized[&main_id];
+        assert!(anonymized.contains_key(&main.project_imports[0]));
+        assert_eq!(main.stdlib_imports, vec!["os".to_string()]);
+        assert_eq!(main.mtime_ns, 0);
+        assert!(main.third_party_imports[0].starts_with("module_"));
+
+        let serialized = format!("{:?}", anonymized);
+        assert!(!serialized.contains("secret_billing") && !serialized.contains("internal_sdk") && !serialized.contains("h-main"));
+
+        let again = anonymize_map(&map, "s3cret");
+        assert_eq!(again[&main_id].hash, main.hash);
+        assert!(!anonymize_map(&map, "other").contains_key(&main_id));
+    }
+}
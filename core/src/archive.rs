@@ -0,0 +1,177 @@
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, DateTime, ZipArchive, ZipWriter};
+
+use crate::{closure_paths, entry_key, git, helpers, ProjectFile, Result};
+
+fn zip_error(e: zip::result::ZipError) -> io::Error {
+    io::Error::other(e)
+}
+
+/// Python sources read into memory instead of from the work tree: those of a zip
+/// archive (`.zip`, `.egg`, `.whl`, `.pyz`) used as source root, or of a git revision.
+/// Members are addressed as `<root>/<member>` (zipimport-style for archives).
+pub(crate) struct ArchiveSource {
+    root: PathBuf,
+    /// Member name -> content, for `.py` and `.pyi` members.
+    members: BTreeMap<String, Vec<u8>>,
+}
+
+impl ArchiveSource {
+    pub(crate) fn open(path: &Path) -> Result<Self> {
+        let mut archive = ZipArchive::new(fs::File::open(path)?).map_err(zip_error)?;
+        let mut members = BTreeMap::new();
+        for index in 0..archive.len() {
+            let mut member = archive.by_index(index).map_err(zip_error)?;
+            let name = member.name().trim_start_matches("./").to_string();
+            if member.is_file() && (name.ends_with(".py") || name.ends_with(".pyi")) {
+                let mut content = Vec::with_capacity(member.size() as usize);
+                member.read_to_end(&mut content)?;
+                members.insert(name, content);
+            }
+        }
+        Ok(ArchiveSource { root: path.to_path_buf(), members })
+    }
+
+    /// Sources of `revision` in the repository at `repo_path`, rooted at `repo_path`.
+    pub(crate) fn open_git_revision(repo_path: &Path, revision: &str) -> Result<Self> {
+        Ok(ArchiveSource { root: repo_path.to_path_buf(), members: git::revision_sources(repo_path, revision)? })
+    }
+
+    fn member_name(&self, path: &Path) -> Option<String> {
+        path.strip_prefix(&self.root).ok().map(|rel| helpers::relative_path_str(rel, Path::new("")))
+    }
+
+    pub(crate) fn contains(&self, path: &Path) -> bool {
+        self.member_name(path).is_some_and(|name| self.members.contains_key(&name))
+    }
+
+    pub(crate) fn read(&self, path: &Path) -> Option<&[u8]> {
+        self.members.get(&self.member_name(path)?).map(Vec::as_slice)
+    }
+
+    /// Whether any member lies below `path`; archives list no directories of their own.
+    pub(crate) fn contains_dir(&self, path: &Path) -> bool {
+        let Some(prefix) = self.member_name(path) else {
+            return false;
+        };
+        if prefix.is_empty() {
+            return !self.members.is_empty();
+        }
+        let prefix = format!("{}/", prefix);
+        self.members.range(prefix.clone()..).next().is_some_and(|(name, _)| name.starts_with(&prefix))
+    }
+
+    /// Members at or below `path`, in name order.
+    pub(crate) fn files_under(&self, path: &Path) -> Vec<PathBuf> {
+        let Some(prefix) = self.member_name(path) else {
+            return Vec::new();
+        };
+        self.members
+            .keys()
+            .filter(|name| prefix.is_empty() || **name == prefix || name.strip_prefix(&prefix).is_some_and(|rest| rest.starts_with('/')))
+            .map(|name| self.root.join(name))
+            .collect()
+    }
+}
+
+/// Writes the closure of `entry_point` to a reproducible zip and returns the archive's
+/// SHA-256.
+///
+/// Entries are named by their `/`-separated path relative to `source_root` and written
+/// in sorted order with the DOS epoch as timestamp and `0644` permissions, so the
+/// same files always produce the same bytes. Files outside `source_root` are left out.
+pub fn build_zip(
+    dependency_map: &HashMap<String, ProjectFile>,
+    entry_point: &str,
+    source_root: &Path,
+    output_path: &Path,
+) -> Result<String> {
+    let entry_key = entry_key(dependency_map, entry_point, Some(source_root))?;
+    let entries: BTreeMap<String, String> = closure_paths(dependency_map, &entry_key)
+        .into_iter()
+        .filter(|path| Path::new(path).starts_with(source_root))
+        .map(|path| (helpers::relative_path_str(Path::new(&path), source_root), path))
+        .collect();
+
+    let options = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .last_modified_time(DateTime::default())
+        .unix_permissions(0o644);
+    let mut zip = ZipWriter::new(io::Cursor::new(Vec::new()));
+    for (name, path) in &entries {
+        let content = fs::read(path)?;
+        zip.start_file(name.as_str(), options).map_err(zip_error)?;
+        zip.write_all(&content)?;
+    }
+    let bytes = zip.finish().map_err(zip_error)?.into_inner();
+
+    fs::write(output_path, &bytes)?;
+    Ok(hex::encode(Sha256::digest(&bytes)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_archive_source_members() {
+        let dir = tempdir().unwrap();
+        let archive_path = dir.path().join("bundle.zip");
+        let mut zip = ZipWriter::new(fs::File::create(&archive_path).unwrap());
+        for (name, content) in [("app/__init__.py", ""), ("app/main.py", "import os\n"), ("app/data.json", "{}"), ("apps/x.py", "")] {
+            zip.start_file(name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+
+        let source = ArchiveSource::open(&archive_path).unwrap();
+        assert!(source.contains(&archive_path.join("app/main.py")));
+        assert!(!source.contains(&archive_path.join("app/data.json")));
+        assert_eq!(source.read(&archive_path.join("app/main.py")), Some(&b"import os\n"[..]));
+        assert_eq!(
+            source.files_under(&archive_path.join("app")),
+            vec![archive_path.join("app/__init__.py"), archive_path.join("app/main.py")]
+        );
+        assert_eq!(source.files_under(&archive_path).len(), 3);
+        assert!(source.contains_dir(&archive_path.join("app")));
+        assert!(!source.contains_dir(&archive_path.join("ap")));
+        assert!(!source.contains_dir(&archive_path.join("app/main.py")));
+    }
+
+    #[test]
+    fn test_build_zip_is_reproducible() {
+        let dir = tempdir().unwrap();
+        let root = fs::canonicalize(dir.path()).unwrap();
+        fs::create_dir(root.join("app")).unwrap();
+        fs::write(root.join("app/main.py"), "from app import util\n").unwrap();
+        fs::write(root.join("app/util.py"), "X = 1\n").unwrap();
+        fs::write(root.join("app/unused.py"), "").unwrap();
+        let key = |rel: &str| root.join(rel).to_string_lossy().into_owned();
+
+        let mut map = HashMap::new();
+        map.insert(key("app/main.py"), ProjectFile { project_imports: vec![key("app/util.py")], ..Default::default() });
+        map.insert(key("app/util.py"), ProjectFile::default());
+        map.insert(key("app/unused.py"), ProjectFile::default());
+
+        let first = build_zip(&map, "app.main", &root, &root.join("first.zip")).unwrap();
+        fs::write(root.join("app/unused.py"), "changed").unwrap();
+        let second = build_zip(&map, "app.main", &root, &root.join("second.zip")).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(fs::read(root.join("first.zip")).unwrap(), fs::read(root.join("second.zip")).unwrap());
+
+        let bytes = fs::read(root.join("first.zip")).unwrap();
+        let main_at = bytes.windows(11).position(|w| w == b"app/main.py").unwrap();
+        let util_at = bytes.windows(11).position(|w| w == b"app/util.py").unwrap();
+        assert!(main_at < util_at);
+        assert!(!bytes.windows(13).any(|w| w == b"app/unused.py"));
+
+        fs::write(root.join("app/util.py"), "X = 2\n").unwrap();
+        assert_ne!(build_zip(&map, "app.main", &root, &root.join("third.zip")).unwrap(), first);
+    }
+}
@@ -0,0 +1,107 @@
+#[cfg(feature = "python")]
+use pyo3::prelude::*;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::helpers::{imports_from_source, matches_module_prefix};
+use crate::{closure_paths, entry_key, ParserOptions, ProjectFile, Result};
+
+/// Modules flagged by `audit_risky_imports` when no list is given.
+pub const DEFAULT_RISKY_MODULES: &[&str] = &[
+    "pickle",
+    "cPickle",
+    "dill",
+    "marshal",
+    "shelve",
+    "subprocess",
+    "os.system",
+    "os.popen",
+    "yaml.load",
+    "yaml.unsafe_load",
+    "builtins.eval",
+    "builtins.exec",
+    "code.interact",
+];
+
+/// One import of a risky module inside an entry point's closure.
+#[cfg_attr(feature = "python", pyclass)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RiskyImport {
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub path: String,
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub line: usize,
+    /// Module as imported, e.g. `subprocess.run` for `from subprocess import run`.
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub module: String,
+    /// Entry of the risky list it matched.
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub risky_module: String,
+}
+
+/// Lists the imports of `risky_modules` (matched on dotted segment boundaries) made by
+/// files in the closure of `entry_point`, sorted by path and line.
+///
+/// Files are re-read to locate the import statements. Only imported names are
+/// checked, so `yaml.load` is found for `from yaml import load` but not for a
+/// `yaml.load(...)` call after `import yaml`.
+pub fn audit_risky_imports(
+    dependency_map: &HashMap<String, ProjectFile>,
+    entry_point: &str,
+    source_root: Option<&Path>,
+    risky_modules: &[String],
+) -> Result<Vec<RiskyImport>> {
+    let entry_key = entry_key(dependency_map, entry_point, source_root)?;
+
+    let mut findings = Vec::new();
+    for path in closure_paths(dependency_map, &entry_key) {
+        let Ok(source) = fs::read_to_string(&path) else {
+            continue;
+        };
+        for record in imports_from_source(&source, &ParserOptions::default()).records {
+            if let Some(risky) = risky_modules.iter().find(|risky| matches_module_prefix(&record.module, risky)) {
+                findings.push(RiskyImport {
+                    path: path.clone(),
+                    line: record.line,
+                    module: record.module,
+                    risky_module: risky.clone(),
+                });
+            }
+        }
+    }
+    findings.sort_by(|a, b| (&a.path, a.line, &a.module).cmp(&(&b.path, b.line, &b.module)));
+    findings.dedup();
+    Ok(findings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_audit_risky_imports_only_covers_closure() {
+        let dir = tempdir().unwrap();
+        let root = fs::canonicalize(dir.path()).unwrap();
+        fs::create_dir(root.join("app")).unwrap();
+        fs::write(root.join("app/main.py"), "import os\nfrom app import loader\n").unwrap();
+        fs::write(root.join("app/loader.py"), "import json\n\nfrom yaml import load as yaml_load\nimport pickle\n").unwrap();
+        fs::write(root.join("app/unused.py"), "import subprocess\n").unwrap();
+        let key = |rel: &str| root.join(rel).to_string_lossy().into_owned();
+
+        let mut map = HashMap::new();
+        for (rel, imports) in [("app/main.py", vec![key("app/loader.py")]), ("app/loader.py", vec![]), ("app/unused.py", vec![])] {
+            map.insert(key(rel), ProjectFile { project_imports: imports, ..Default::default() });
+        }
+
+        let risky: Vec<String> = DEFAULT_RISKY_MODULES.iter().map(|m| m.to_string()).collect();
+        let findings = audit_risky_imports(&map, &key("app/main.py"), None, &risky).unwrap();
+        let summary: Vec<(usize, &str, &str)> =
+            findings.iter().map(|f| (f.line, f.module.as_str(), f.risky_module.as_str())).collect();
+        assert_eq!(summary, vec![(3, "yaml.load", "yaml.load"), (4, "pickle", "pickle")]);
+        assert!(findings.iter().all(|f| f.path == key("app/loader.py")));
+
+        assert!(audit_risky_imports(&map, &key("app/main.py"), None, &["subprocess".to_string()]).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,255 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::helpers::{DynamicImportSite, ImportRecord, ResourceRef, SourceImports};
+use crate::{build_dependency_map, BuildConfig, BuildResult, ParsedSource, ProjectFile, Result};
+
+/// Bumped whenever the layout changes; caches of other versions are ignored.
+const CACHE_VERSION: u32 = 4;
+
+#[derive(Serialize, Deserialize)]
+struct CacheFile {
+    version: u32,
+    /// Digest of the options the entries were built with.
+    fingerprint: String,
+    files: BTreeMap<String, CachedFile>,
+}
+
+/// A file's hash and parse. Its imports are resolved again on every run, so edges
+/// follow modules added, moved or deleted since the cache was written.
+#[derive(Serialize, Deserialize)]
+struct CachedFile {
+    hash: String,
+    size: u64,
+    mtime_ns: u64,
+    /// `BuildConfig::analysis_options` of the parse.
+    options: String,
+    volatile_pragma: bool,
+    /// `None` when the content is not UTF-8.
+    imports: Option<CachedImports>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedImports {
+    /// Module, alias, line, kind and whether it is a name imported with `from`.
+    records: Vec<(String, Option<String>, usize, String, bool)>,
+    /// Line and expression of each dynamic import.
+    dynamic_imports: Vec<(usize, String)>,
+    script_paths: Vec<String>,
+    /// Package and path of each resource.
+    resources: Vec<(Option<String>, String)>,
+    degraded: bool,
+    failed: bool,
+}
+
+impl CachedFile {
+    /// `None` for entries without a parse (e.g. resource files), which are never reused.
+    fn new(info: &ProjectFile) -> Option<Self> {
+        let parsed = info.parsed.as_ref()?;
+        Some(CachedFile {
+            hash: info.hash.clone(),
+            size: info.size,
+            mtime_ns: info.mtime_ns,
+            options: parsed.options.clone(),
+            volatile_pragma: parsed.volatile_pragma,
+            imports: parsed.imports.as_ref().map(|imports| CachedImports {
+                records: imports
+                    .records
+                    .iter()
+                    .map(|r| (r.module.clone(), r.alias.clone(), r.line, r.kind.as_str().to_string(), r.member))
+                    .collect(),
+                dynamic_imports: imports.dynamic_imports.iter().map(|site| (site.line, site.expression.clone())).collect(),
+                script_paths: imports.script_paths.clone(),
+                resources: imports.resources.iter().map(|r| (r.package.clone(), r.resource.clone())).collect(),
+                degraded: imports.degraded,
+                failed: imports.failed,
+            }),
+        })
+    }
+
+    /// `None` when a record names an unknown import kind.
+    fn into_project_file(self) -> Option<ProjectFile> {
+        let imports = match self.imports {
+            Some(imports) => Some(SourceImports {
+                records: imports
+                    .records
+                    .into_iter()
+                    .map(|(module, alias, line, kind, member)| {
+                        Some(ImportRecord { module, alias, line, kind: kind.parse().ok()?, member })
+                    })
+                    .collect::<Option<_>>()?,
+                dynamic_imports: imports
+                    .dynamic_imports
+                    .into_iter()
+                    .map(|(line, expression)| DynamicImportSite { line, expression })
+                    .collect(),
+                script_paths: imports.script_paths,
+                resources: imports.resources.into_iter().map(|(package, resource)| ResourceRef { package, resource }).collect(),
+                degraded: imports.degraded,
+                failed: imports.failed,
+            }),
+            None => None,
+        };
+        Some(ProjectFile {
+            hash: self.hash,
+            size: self.size,
+            mtime_ns: self.mtime_ns,
+            parsed: Some(ParsedSource { options: self.options, volatile_pragma: self.volatile_pragma, imports }),
+            ..Default::default()
+        })
+    }
+}
+
+/// SHA-256 of the file at `path`, or of nothing when there is none or it cannot be read.
+fn content_digest(path: Option<&PathBuf>) -> String {
+    let content = path.and_then(|path| fs::read(path).ok()).unwrap_or_default();
+    hex::encode(Sha256::digest(&content))
+}
+
+/// Digest of the options that change what a file's entry holds, including the contents
+/// of the stdlib list and extra-edges files; a cache written under other options is
+/// not reused.
+fn fingerprint(config: &BuildConfig) -> String {
+    let options = format!(
+        "{:?}",
+        (
+            (&config.source_root, &config.extra_source_roots, &config.project_module_prefixes, &config.include_paths),
+            (&config.stdlib_list_path, content_digest(config.stdlib_list_path.as_ref())),
+            (&config.extra_edges_path, content_digest(config.extra_edges_path.as_ref())),
+            (&config.volatile_patterns, config.include_stubs, config.parser),
+            (&config.site_packages_dirs, &config.deep_packages, config.deep_max_depth, config.resolution_scope),
+            (config.normalize_line_endings, config.ignore_trailing_newlines, &config.excluded_import_kinds),
+        )
+    );
+    hex::encode(Sha256::digest(options.as_bytes()))
+}
+
+/// Entries of the cache at `path`; empty when it is missing, unreadable, or was
+/// written by another version or under other options.
+fn load(path: &Path, fingerprint: &str) -> HashMap<String, ProjectFile> {
+    let Ok(content) = fs::read(path) else {
+        return HashMap::new();
+    };
+    match serde_json::from_slice::<CacheFile>(&content) {
+        Ok(cache) if cache.version == CACHE_VERSION && cache.fingerprint == fingerprint => cache
+            .files
+            .into_iter()
+            .filter_map(|(key, file)| Some((key, file.into_project_file()?)))
+            .collect(),
+        _ => HashMap::new(),
+    }
+}
+
+/// Writes the cache next to `path` and renames it into place, so an interrupted run
+/// never leaves a truncated cache behind.
+fn save(path: &Path, fingerprint: &str, files: &HashMap<String, ProjectFile>) -> Result<()> {
+    let cache = CacheFile {
+        version: CACHE_VERSION,
+        fingerprint: fingerprint.to_string(),
+        files: files.iter().filter_map(|(key, info)| Some((key.clone(), CachedFile::new(info)?))).collect(),
+    };
+    let content = serde_json::to_vec(&cache).map_err(std::io::Error::other)?;
+    if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    let mut temp_name = OsString::from(path.as_os_str());
+    temp_name.push(".tmp");
+    let temp_path = PathBuf::from(temp_name);
+    fs::write(&temp_path, content)?;
+    fs::rename(&temp_path, path)?;
+    Ok(())
+}
+
+/// `build_dependency_map` with `previous_map` kept on disk at `cache_path`: files whose
+/// size and mtime match the cache are reused without being read, and the cache is
+/// rewritten with the new map. `config.previous_map` is ignored. A cache that cannot
+/// be used (missing, corrupt, other options) only costs a full scan.
+pub fn build_dependency_map_incremental(config: &BuildConfig, cache_path: &Path) -> Result<BuildResult> {
+    let fingerprint = fingerprint(config);
+    let config = BuildConfig { previous_map: load(cache_path, &fingerprint), ..config.clone() };
+    let result = build_dependency_map(&config)?;
+    save(cache_path, &fingerprint, &result.files)?;
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_incremental_build_reuses_cached_files() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("src");
+        fs::create_dir_all(root.join("app")).unwrap();
+        fs::write(root.join("app/__init__.py"), "").unwrap();
+        fs::write(root.join("app/main.py"), "import os\nimport app.util as u\n").unwrap();
+        fs::write(root.join("app/util.py"), "x = __import__(name)\n").unwrap();
+        let cache_path = dir.path().join("cache/depmap.json");
+        let config = BuildConfig {
+            source_root: root.clone(),
+            project_module_prefixes: vec!["app".to_string()],
+            include_paths: vec!["app".to_string()],
+            ..Default::default()
+        };
+
+        let first = build_dependency_map_incremental(&config, &cache_path).unwrap();
+        assert_eq!(first.reused_files, 0);
+        let second = build_dependency_map_incremental(&config, &cache_path).unwrap();
+        assert_eq!(second.reused_files, 3);
+        let main_key = root.join("app/main.py").to_string_lossy().into_owned();
+        let util_key = root.join("app/util.py").to_string_lossy().into_owned();
+        assert_eq!(second.files[&main_key].project_imports, first.files[&main_key].project_imports);
+        assert_eq!(second.files[&main_key].import_aliases["u"], "app.util");
+        assert_eq!(second.files[&util_key].dynamic_import_warnings, first.files[&util_key].dynamic_import_warnings);
+
+        fs::write(root.join("app/util.py"), "y = 2\n").unwrap();
+        let third = build_dependency_map_incremental(&config, &cache_path).unwrap();
+        assert_eq!(third.reused_files, 2);
+        assert!(third.files[&util_key].dynamic_import_warnings.is_empty());
+
+        let other_prefixes = BuildConfig { project_module_prefixes: vec!["other".to_string()], ..config.clone() };
+        assert_eq!(build_dependency_map_incremental(&other_prefixes, &cache_path).unwrap().reused_files, 0);
+
+        fs::write(&cache_path, "{ truncated").unwrap();
+        assert_eq!(build_dependency_map_incremental(&config, &cache_path).unwrap().reused_files, 0);
+        assert_eq!(build_dependency_map_incremental(&config, &cache_path).unwrap().reused_files, 3);
+
+        // Deleting a module drops the edges of the unchanged files importing it.
+        fs::remove_file(root.join("app/util.py")).unwrap();
+        let fourth = build_dependency_map_incremental(&config, &cache_path).unwrap();
+        assert_eq!(fourth.reused_files, 2);
+        assert!(!fourth.files[&main_key].project_imports.contains(&util_key));
+        assert_eq!(fourth.files[&main_key].unresolved_imports, vec!["app.util".to_string()]);
+    }
+
+    #[test]
+    fn test_incremental_build_fingerprints_option_file_contents() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("src");
+        fs::create_dir_all(root.join("app")).unwrap();
+        fs::write(root.join("app/main.py"), "import os\n").unwrap();
+        let stdlib_list = dir.path().join("stdlib.txt");
+        fs::write(&stdlib_list, "sys").unwrap();
+        let cache_path = dir.path().join("depmap.json");
+        let config = BuildConfig {
+            source_root: root.clone(),
+            project_module_prefixes: vec!["app".to_string()],
+            include_paths: vec!["app".to_string()],
+            stdlib_list_path: Some(stdlib_list.clone()),
+            ..Default::default()
+        };
+
+        build_dependency_map_incremental(&config, &cache_path).unwrap();
+        assert_eq!(build_dependency_map_incremental(&config, &cache_path).unwrap().reused_files, 1);
+        fs::write(&stdlib_list, "sys, os").unwrap();
+        let result = build_dependency_map_incremental(&config, &cache_path).unwrap();
+        assert_eq!(result.reused_files, 0);
+        let main_key = root.join("app/main.py").to_string_lossy().into_owned();
+        assert_eq!(result.files[&main_key].stdlib_imports, vec!["os".to_string()]);
+    }
+}
@@ -0,0 +1,170 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use crate::{entry_key, ProjectFile, Result};
+
+/// Project imports of `path` that are files of the map, sorted so every walk of an
+/// unchanged map takes the same route.
+fn sorted_imports<'a>(dependency_map: &'a HashMap<String, ProjectFile>, path: &str) -> Vec<&'a str> {
+    let mut targets: Vec<&str> = dependency_map[path]
+        .project_imports
+        .iter()
+        .filter_map(|target| dependency_map.get_key_value(target).map(|(key, _)| key.as_str()))
+        .collect();
+    targets.sort_unstable();
+    targets.dedup();
+    targets
+}
+
+/// Tarjan's algorithm state, kept on the heap so deep import chains cannot overflow
+/// the stack.
+struct Tarjan<'a> {
+    dependency_map: &'a HashMap<String, ProjectFile>,
+    index: HashMap<&'a str, usize>,
+    lowlink: HashMap<&'a str, usize>,
+    stack: Vec<&'a str>,
+    on_stack: HashSet<&'a str>,
+    /// (file, its imports, next import to follow) of each file being visited.
+    frames: Vec<(&'a str, Vec<&'a str>, usize)>,
+}
+
+impl<'a> Tarjan<'a> {
+    fn open(&mut self, node: &'a str) {
+        let next_index = self.index.len();
+        self.index.insert(node, next_index);
+        self.lowlink.insert(node, next_index);
+        self.stack.push(node);
+        self.on_stack.insert(node);
+        self.frames.push((node, sorted_imports(self.dependency_map, node), 0));
+    }
+
+    fn lower(&mut self, node: &str, low: usize) {
+        let current = self.lowlink.get_mut(node).unwrap();
+        *current = (*current).min(low);
+    }
+}
+
+/// Strongly connected components of the files reachable from `roots`, dependencies
+/// first, each sorted.
+fn strongly_connected<'a>(dependency_map: &'a HashMap<String, ProjectFile>, roots: &[&'a str]) -> Vec<Vec<&'a str>> {
+    let mut tarjan = Tarjan {
+        dependency_map,
+        index: HashMap::new(),
+        lowlink: HashMap::new(),
+        stack: Vec::new(),
+        on_stack: HashSet::new(),
+        frames: Vec::new(),
+    };
+    let mut components = Vec::new();
+    for &root in roots {
+        if tarjan.index.contains_key(root) {
+            continue;
+        }
+        tarjan.open(root);
+        while let Some(frame) = tarjan.frames.last_mut() {
+            let node = frame.0;
+            let target = frame.1.get(frame.2).copied();
+            frame.2 += 1;
+            match target {
+                Some(target) => match tarjan.index.get(target).copied() {
+                    None => tarjan.open(target),
+                    Some(target_index) if tarjan.on_stack.contains(target) => tarjan.lower(node, target_index),
+                    Some(_) => {}
+                },
+                None => {
+                    tarjan.frames.pop();
+                    let low = tarjan.lowlink[node];
+                    if let Some(&(parent, _, _)) = tarjan.frames.last() {
+                        tarjan.lower(parent, low);
+                    }
+                    if low == tarjan.index[node] {
+                        let mut component = Vec::new();
+                        while let Some(member) = tarjan.stack.pop() {
+                            tarjan.on_stack.remove(member);
+                            component.push(member);
+                            if member == node {
+                                break;
+                            }
+                        }
+                        component.sort_unstable();
+                        components.push(component);
+                    }
+                }
+            }
+        }
+    }
+    components
+}
+
+/// Import cycles of the map: every group of files importing each other directly or
+/// transitively, plus files importing themselves. Each cycle is sorted, and so is the
+/// list.
+pub fn find_import_cycles(dependency_map: &HashMap<String, ProjectFile>) -> Vec<Vec<String>> {
+    let mut roots: Vec<&str> = dependency_map.keys().map(String::as_str).collect();
+    roots.sort_unstable();
+    let mut cycles: Vec<Vec<String>> = strongly_connected(dependency_map, &roots)
+        .into_iter()
+        .filter(|component| {
+            component.len() > 1 || dependency_map[component[0]].project_imports.iter().any(|target| target == component[0])
+        })
+        .map(|component| component.into_iter().map(str::to_string).collect())
+        .collect();
+    cycles.sort();
+    cycles
+}
+
+/// Files of `entry_point`'s closure, each after every file it imports, ending with the
+/// entry point. The files of an import cycle have no such order; they are listed
+/// together, sorted, after everything the cycle imports.
+pub fn topological_order(
+    dependency_map: &HashMap<String, ProjectFile>,
+    entry_point: &str,
+    source_root: Option<&Path>,
+) -> Result<Vec<String>> {
+    let entry_key = entry_key(dependency_map, entry_point, source_root)?;
+    Ok(strongly_connected(dependency_map, &[entry_key.as_str()])
+        .into_iter()
+        .flatten()
+        .map(str::to_string)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(imports: &[&str]) -> ProjectFile {
+        ProjectFile { project_imports: imports.iter().map(|i| i.to_string()).collect(), ..Default::default() }
+    }
+
+    #[test]
+    fn test_find_import_cycles() {
+        let mut map = HashMap::new();
+        map.insert("main.py".to_string(), file(&["a.py", "self.py"]));
+        map.insert("a.py".to_string(), file(&["b.py"]));
+        map.insert("b.py".to_string(), file(&["c.py", "gone.py"]));
+        map.insert("c.py".to_string(), file(&["a.py", "util.py"]));
+        map.insert("self.py".to_string(), file(&["self.py"]));
+        map.insert("util.py".to_string(), file(&[]));
+
+        assert_eq!(find_import_cycles(&map), vec![vec!["a.py", "b.py", "c.py"], vec!["self.py"]]);
+        map.insert("c.py".to_string(), file(&["util.py"]));
+        map.insert("self.py".to_string(), file(&[]));
+        assert!(find_import_cycles(&map).is_empty());
+    }
+
+    #[test]
+    fn test_topological_order_puts_imports_first() {
+        let mut map = HashMap::new();
+        map.insert("main.py".to_string(), file(&["views.py", "db.py"]));
+        map.insert("views.py".to_string(), file(&["models.py", "util.py"]));
+        map.insert("models.py".to_string(), file(&["db.py", "views.py"]));
+        map.insert("db.py".to_string(), file(&["util.py"]));
+        map.insert("util.py".to_string(), file(&[]));
+        map.insert("unused.py".to_string(), file(&["main.py"]));
+
+        let order = topological_order(&map, "main.py", None).unwrap();
+        assert_eq!(order, vec!["util.py", "db.py", "models.py", "views.py", "main.py"]);
+        assert_eq!(topological_order(&map, "db.py", None).unwrap(), vec!["util.py", "db.py"]);
+    }
+}
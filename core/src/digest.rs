@@ -0,0 +1,323 @@
+#[cfg(feature = "python")]
+use pyo3::prelude::*;
+use sha2::{Digest, Sha256};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use crate::{closure_paths, entry_key, helpers, ProjectFile, Result};
+
+/// Content hash recorded for `volatile` files, so their content never moves a digest.
+const VOLATILE_CONTENT: &str = "volatile";
+
+/// Asset digest of `entry_point`'s closure, stable across checkouts and machines.
+///
+/// SHA-256 over one `<relative path>\0<content sha256>\n` line per file, sorted by
+/// the `/`-separated path relative to `source_root`. Volatile files contribute their
+/// path only.
+pub fn asset_digest(
+    dependency_map: &HashMap<String, ProjectFile>,
+    entry_point: &str,
+    source_root: &Path,
+) -> Result<String> {
+    let entry_key = entry_key(dependency_map, entry_point, Some(source_root))?;
+    Ok(closure_digest(dependency_map, &entry_key, |path| helpers::relative_path_str(Path::new(path), source_root)))
+}
+
+/// Merkle digest of `entry_point`'s closure, for deciding whether to repackage it.
+///
+/// Each file is a leaf, SHA-256 over `0x00 <relative path>\0<content sha256>` with the
+/// `/`-separated path relative to `source_root`. Leaves sorted by path are hashed in
+/// pairs (`0x01 <left><right>`), level by level, an odd node moving up as is, until
+/// one digest is left. Identical sources give the same digest wherever they are
+/// checked out and whatever order the map was built or traversed in. Volatile files
+/// contribute their path only.
+pub fn closure_hash(
+    dependency_map: &HashMap<String, ProjectFile>,
+    entry_point: &str,
+    source_root: &Path,
+) -> Result<String> {
+    let entry_key = entry_key(dependency_map, entry_point, Some(source_root))?;
+    let mut level: Vec<Vec<u8>> =
+        closure_entries(dependency_map, &entry_key, |path| helpers::relative_path_str(Path::new(path), source_root))
+            .into_iter()
+            .map(|(path, content)| Sha256::digest(format!("\x00{}\0{}", path, content)).to_vec())
+            .collect();
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => Sha256::digest([b"\x01".as_slice(), left.as_slice(), right.as_slice()].concat()).to_vec(),
+                _ => pair[0].clone(),
+            })
+            .collect();
+    }
+    Ok(hex::encode(&level[0]))
+}
+
+/// `(path, content hash)` of each file of the closure of `entry_key`, with the path
+/// given by `path_of`, sorted; volatile files have `VOLATILE_CONTENT` as hash.
+fn closure_entries<'a>(
+    dependency_map: &'a HashMap<String, ProjectFile>,
+    entry_key: &str,
+    path_of: impl Fn(&str) -> String,
+) -> Vec<(String, &'a str)> {
+    let mut entries: Vec<(String, &str)> = closure_paths(dependency_map, entry_key)
+        .into_iter()
+        .map(|path| {
+            let info = &dependency_map[&path];
+            let content = if info.volatile { VOLATILE_CONTENT } else { info.hash.as_str() };
+            (path_of(&path), content)
+        })
+        .collect();
+    entries.sort();
+    entries
+}
+
+/// SHA-256 over one `<path>\0<content sha256>\n` line per file of the closure of
+/// `entry_key`, sorted by path as given by `path_of`.
+fn closure_digest(
+    dependency_map: &HashMap<String, ProjectFile>,
+    entry_key: &str,
+    path_of: impl Fn(&str) -> String,
+) -> String {
+    let mut hasher = Sha256::new();
+    for (path, content) in closure_entries(dependency_map, entry_key, path_of) {
+        hasher.update(path.as_bytes());
+        hasher.update(b"\0");
+        hasher.update(content.as_bytes());
+        hasher.update(b"\n");
+    }
+    hex::encode(hasher.finalize())
+}
+
+/// Merkle digest of every directory holding mapped files under `source_root`, keyed by
+/// its `/`-separated path relative to `source_root` (`""` for the root itself).
+///
+/// A directory's digest is SHA-256 over one `<kind>\0<name>\0<digest>\n` line per
+/// child, sorted: files (`f`) contribute their content hash, subdirectories (`d`) their
+/// own digest. It changes exactly when a file below it is added, removed, renamed or
+/// changed. Volatile files contribute their name only; files outside `source_root`
+/// are left out.
+pub fn directory_digests(dependency_map: &HashMap<String, ProjectFile>, source_root: &Path) -> BTreeMap<String, String> {
+    let parent_of = |dir: &str| dir.rsplit_once('/').map_or(String::new(), |(parent, _)| parent.to_string());
+    let mut children: HashMap<String, Vec<String>> = HashMap::new();
+    for (path, info) in dependency_map {
+        if !Path::new(path).starts_with(source_root) {
+            continue;
+        }
+        let relative = helpers::relative_path_str(Path::new(path), source_root);
+        let (dir, name) = relative.rsplit_once('/').unwrap_or(("", relative.as_str()));
+        let content = if info.volatile { VOLATILE_CONTENT } else { info.hash.as_str() };
+        children.entry(dir.to_string()).or_default().push(format!("f\0{}\0{}\n", name, content));
+        let mut ancestor = dir.to_string();
+        while !ancestor.is_empty() {
+            ancestor = parent_of(&ancestor);
+            children.entry(ancestor.clone()).or_default();
+        }
+    }
+
+    // Deepest directories first, so each child digest is known before its parent's.
+    let mut dirs: Vec<String> = children.keys().cloned().collect();
+    dirs.sort_by_key(|dir| Reverse(if dir.is_empty() { 0 } else { dir.matches('/').count() + 1 }));
+    let mut digests = BTreeMap::new();
+    for dir in dirs {
+        let mut lines = children.remove(&dir).unwrap_or_default();
+        lines.sort();
+        let mut hasher = Sha256::new();
+        for line in &lines {
+            hasher.update(line.as_bytes());
+        }
+        let digest = hex::encode(hasher.finalize());
+        if !dir.is_empty() {
+            let name = dir.rsplit('/').next().unwrap_or(&dir);
+            let line = format!("d\0{}\0{}\n", name, digest);
+            children.entry(parent_of(&dir)).or_default().push(line);
+        }
+        digests.insert(dir, digest);
+    }
+    digests
+}
+
+/// Entry points whose asset digest a change to `path` would invalidate.
+#[cfg_attr(feature = "python", pyclass)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlastRadius {
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub path: String,
+    /// Affected entry points, as given, in input order.
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub entry_points: Vec<String>,
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub count: usize,
+}
+
+/// Ranks the files in the closures of `entry_points` by how many of those entry
+/// points' asset digests change when the file changes, most shared first (ties by
+/// path). Volatile files never change a digest and are left out.
+pub fn blast_radius(
+    dependency_map: &HashMap<String, ProjectFile>,
+    entry_points: &[String],
+    source_root: Option<&Path>,
+) -> Result<Vec<BlastRadius>> {
+    let mut affected: HashMap<String, Vec<String>> = HashMap::new();
+    for entry_point in entry_points {
+        let entry_key = entry_key(dependency_map, entry_point, source_root)?;
+        for path in closure_paths(dependency_map, &entry_key) {
+            if dependency_map[&path].volatile {
+                continue;
+            }
+            let entries = affected.entry(path).or_default();
+            if !entries.contains(entry_point) {
+                entries.push(entry_point.clone());
+            }
+        }
+    }
+    let mut ranking: Vec<BlastRadius> = affected
+        .into_iter()
+        .map(|(path, entry_points)| BlastRadius { count: entry_points.len(), path, entry_points })
+        .collect();
+    ranking.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.path.cmp(&b.path)));
+    Ok(ranking)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{build_dependency_map, BuildConfig};
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_asset_digest_is_relative_and_ignores_volatile_content() {
+        let digest_under = |volatile_hash: &str| {
+            let dir = tempdir().unwrap();
+            let root = fs::canonicalize(dir.path()).unwrap();
+            for name in ["main.py", "util.py", "stamp.py", "other.py"] {
+                fs::write(root.join(name), "").unwrap();
+            }
+            let key = |rel: &str| root.join(rel).to_string_lossy().into_owned();
+            let mut map = HashMap::new();
+            map.insert(key("main.py"), ProjectFile {
+                hash: "h-main".to_string(),
+                project_imports: vec![key("util.py"), key("stamp.py")],
+                ..Default::default()
+            });
+            map.insert(key("util.py"), ProjectFile { hash: "h-util".to_string(), ..Default::default() });
+            map.insert(key("stamp.py"), ProjectFile { hash: volatile_hash.to_string(), volatile: true, ..Default::default() });
+            map.insert(key("other.py"), ProjectFile { hash: "h-other".to_string(), ..Default::default() });
+            let digest = asset_digest(&map, "main", &root).unwrap();
+
+            map.get_mut(&key("other.py")).unwrap().hash = "changed".to_string();
+            assert_eq!(asset_digest(&map, "main", &root).unwrap(), digest);
+            map.get_mut(&key("util.py")).unwrap().hash = "changed".to_string();
+            assert_ne!(asset_digest(&map, "main", &root).unwrap(), digest);
+            digest
+        };
+
+        // Different temp roots and volatile content, same digest.
+        assert_eq!(digest_under("stamp-1"), digest_under("stamp-2"));
+    }
+
+    #[test]
+    fn test_closure_hash_is_order_independent() {
+        let file = |hash: &str, imports: &[&str]| ProjectFile {
+            hash: hash.to_string(),
+            project_imports: imports.iter().map(|i| i.to_string()).collect(),
+            ..Default::default()
+        };
+        let root = Path::new("/repo");
+        let mut map = HashMap::new();
+        map.insert("/repo/main.py".to_string(), file("h-main", &["/repo/b.py", "/repo/a.py"]));
+        map.insert("/repo/a.py".to_string(), file("h-a", &["/repo/b.py"]));
+        map.insert("/repo/b.py".to_string(), file("h-b", &[]));
+        map.insert("/repo/other.py".to_string(), file("h-other", &[]));
+        let digest = closure_hash(&map, "/repo/main.py", root).unwrap();
+
+        map.get_mut("/repo/main.py").unwrap().project_imports.reverse();
+        map.get_mut("/repo/other.py").unwrap().hash = "changed".to_string();
+        assert_eq!(closure_hash(&map, "/repo/main.py", root).unwrap(), digest);
+        map.get_mut("/repo/b.py").unwrap().hash = "changed".to_string();
+        assert_ne!(closure_hash(&map, "/repo/main.py", root).unwrap(), digest);
+        assert!(closure_hash(&map, "/repo/missing.py", root).is_err());
+    }
+
+    #[test]
+    fn test_closure_hash_does_not_depend_on_the_checkout_location() {
+        let checkout = |sources: &[(&str, &str)]| {
+            let dir = tempdir().unwrap();
+            let root = fs::canonicalize(dir.path()).unwrap();
+            fs::create_dir(root.join("app")).unwrap();
+            for (rel, content) in sources {
+                fs::write(root.join(rel), content).unwrap();
+            }
+            let config = BuildConfig {
+                source_root: root.clone(),
+                project_module_prefixes: vec!["app".to_string()],
+                include_paths: vec!["app".to_string()],
+                ..Default::default()
+            };
+            let map = build_dependency_map(&config).unwrap().files;
+            closure_hash(&map, &root.join("app/main.py").to_string_lossy(), &root).unwrap()
+        };
+        let sources = [("app/main.py", "import app.a\nimport app.b\n"), ("app/a.py", "import app.b\n"), ("app/b.py", "")];
+        let digest = checkout(&sources);
+        assert_eq!(checkout(&sources), digest);
+        assert_ne!(checkout(&[sources[0], sources[1], ("app/b.py", "x = 1\n")]), digest);
+    }
+
+    #[test]
+    fn test_directory_digests_change_along_the_path() {
+        let root = Path::new("/repo");
+        let mut map = HashMap::new();
+        for (rel, hash) in [
+            ("services/payments/api.py", "h1"),
+            ("services/payments/db/models.py", "h2"),
+            ("services/search/index.py", "h3"),
+            ("setup.py", "h4"),
+        ] {
+            map.insert(root.join(rel).to_string_lossy().into_owned(), ProjectFile { hash: hash.to_string(), ..Default::default() });
+        }
+        map.insert("/repo/build/stamp.py".to_string(), ProjectFile { hash: "s1".to_string(), volatile: true, ..Default::default() });
+        map.insert("/elsewhere/x.py".to_string(), ProjectFile::default());
+
+        let before = directory_digests(&map, root);
+        let dirs: Vec<&str> = before.keys().map(String::as_str).collect();
+        assert_eq!(dirs, ["", "build", "services", "services/payments", "services/payments/db", "services/search"]);
+
+        map.get_mut("/repo/services/payments/db/models.py").unwrap().hash = "changed".to_string();
+        map.get_mut("/repo/build/stamp.py").unwrap().hash = "s2".to_string();
+        let after = directory_digests(&map, root);
+        for dir in ["", "services", "services/payments", "services/payments/db"] {
+            assert_ne!(before[dir], after[dir], "{}", dir);
+        }
+        assert_eq!(before["services/search"], after["services/search"]);
+        assert_eq!(before["build"], after["build"]);
+    }
+
+    #[test]
+    fn test_blast_radius_ranks_shared_files_first() {
+        let dir = tempdir().unwrap();
+        let root = fs::canonicalize(dir.path()).unwrap();
+        for name in ["a.py", "b.py", "shared.py", "only_a.py", "stamp.py"] {
+            fs::write(root.join(name), "").unwrap();
+        }
+        let key = |rel: &str| root.join(rel).to_string_lossy().into_owned();
+        let mut map = HashMap::new();
+        map.insert(key("a.py"), ProjectFile {
+            project_imports: vec![key("shared.py"), key("only_a.py"), key("stamp.py")],
+            ..Default::default()
+        });
+        map.insert(key("b.py"), ProjectFile { project_imports: vec![key("shared.py"), key("stamp.py")], ..Default::default() });
+        map.insert(key("shared.py"), ProjectFile::default());
+        map.insert(key("only_a.py"), ProjectFile::default());
+        map.insert(key("stamp.py"), ProjectFile { volatile: true, ..Default::default() });
+
+        let entry_points = vec!["a".to_string(), "b".to_string()];
+        let ranking = blast_radius(&map, &entry_points, Some(&root)).unwrap();
+        let summary: Vec<(String, usize)> = ranking.iter().map(|r| (r.path.clone(), r.count)).collect();
+        assert_eq!(summary, vec![(key("shared.py"), 2), (key("a.py"), 1), (key("b.py"), 1), (key("only_a.py"), 1)]);
+        assert_eq!(ranking[0].entry_points, entry_points);
+        assert_eq!(ranking[3].entry_points, vec!["a".to_string()]);
+    }
+}
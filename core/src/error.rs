@@ -0,0 +1,40 @@
+use std::fmt;
+use std::io;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    InvalidInput(String),
+    LimitExceeded(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "{}", e),
+            Error::InvalidInput(msg) => write!(f, "{}", msg),
+            Error::LimitExceeded(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+#[cfg(feature = "python")]
+impl From<Error> for pyo3::PyErr {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::Io(e) => e.into(),
+            Error::InvalidInput(msg) => pyo3::exceptions::PyValueError::new_err(msg),
+            Error::LimitExceeded(msg) => pyo3::exceptions::PyRuntimeError::new_err(msg),
+        }
+    }
+}
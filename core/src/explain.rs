@@ -0,0 +1,254 @@
+#[cfg(feature = "python")]
+use pyo3::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::{helpers, open_archive, source_dir_exists, source_exists, BuildConfig, Result, ResolutionScope};
+
+/// One path tried while resolving an import.
+#[cfg_attr(feature = "python", pyclass)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResolutionCandidate {
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub path: String,
+    /// `"package_init"` for an enclosing package's `__init__`, `"module"` for the
+    /// module itself, `"site_packages"` for a deep-mode lookup.
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub role: String,
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub found: bool,
+}
+
+/// How `build_dependency_map` would resolve one imported module.
+#[cfg_attr(feature = "python", pyclass)]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ResolutionExplanation {
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub module: String,
+    /// `"project"`, `"stdlib"`, `"third_party"` or `"relative"`; relative imports
+    /// resolve like project ones.
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub category: String,
+    /// Paths tried, in order.
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub candidates: Vec<ResolutionCandidate>,
+    /// Every file the import adds to `project_imports`, module last.
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub resolved: Vec<String>,
+    /// Why the import gives no `project_imports` edge to the module, if it does not.
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub reason: Option<String>,
+}
+
+fn candidates(paths: &[PathBuf], role: &str, exists: &dyn Fn(&Path) -> bool) -> Vec<ResolutionCandidate> {
+    paths
+        .iter()
+        .map(|path| ResolutionCandidate {
+            path: path.to_string_lossy().into_owned(),
+            role: role.to_string(),
+            found: exists(path),
+        })
+        .collect()
+}
+
+/// Fills in the candidates of project module `module` below the source roots.
+fn explain_project(config: &BuildConfig, module: &str, explanation: &mut ResolutionExplanation) -> Result<()> {
+    let archive = open_archive(config)?;
+    let exists = |p: &Path| source_exists(archive.as_ref(), p);
+    let dir_exists = |p: &Path| source_dir_exists(archive.as_ref(), p);
+    let roots = config.source_roots();
+    let root = helpers::module_root(module, &roots, config.include_stubs, &exists, &dir_exists);
+    for earlier in roots.iter().take_while(|earlier| earlier.as_path() != root) {
+        let tried = helpers::module_candidates(module, earlier, config.include_stubs);
+        explanation.candidates.extend(candidates(&tried, "module", &exists));
+    }
+    for dir in helpers::parent_package_dirs(module, root) {
+        let tried = candidates(&helpers::package_init_candidates(&dir), "package_init", &exists);
+        // Only the first existing init of each package is used.
+        let found = tried.iter().position(|c| c.found).map_or(tried.len(), |i| i + 1);
+        explanation.candidates.extend(tried.into_iter().take(found));
+    }
+    let module_candidates = candidates(
+        &helpers::module_candidates(module, root, config.include_stubs),
+        "module",
+        &exists,
+    );
+    let winner = module_candidates.iter().position(|c| c.found);
+    let module_file = winner.map(|i| module_candidates[i].path.clone());
+    explanation.candidates.extend(module_candidates.into_iter().take(winner.map_or(usize::MAX, |i| i + 1)));
+
+    explanation.resolved = explanation
+        .candidates
+        .iter()
+        .filter(|c| c.found)
+        .filter(|c| config.resolution_scope != ResolutionScope::Scanned || config.is_in_include_paths(Path::new(&c.path)))
+        .map(|c| c.path.clone())
+        .collect();
+    explanation.reason = match module_file {
+        None => Some(format!(
+            "no module file found under {}{}",
+            roots.iter().map(|root| root.display().to_string()).collect::<Vec<_>>().join(", "),
+            if config.include_stubs { "" } else { " (.pyi stubs are only tried with include_stubs)" }
+        )),
+        Some(file) if !explanation.resolved.contains(&file) => {
+            Some("the module file is outside the include paths and resolution_scope is \"scanned\"".to_string())
+        }
+        Some(_) => None,
+    };
+    Ok(())
+}
+
+/// Replays the resolution of `module` under `config`, listing every path tried.
+/// `importing_file` is the file containing the import; relative imports need it.
+pub fn explain_resolution(
+    config: &BuildConfig,
+    module: &str,
+    importing_file: Option<&Path>,
+) -> Result<ResolutionExplanation> {
+    let mut explanation = ResolutionExplanation { module: module.to_string(), ..Default::default() };
+    if module.starts_with('.') {
+        explanation.category = "relative".to_string();
+        let Some(file) = importing_file else {
+            explanation.reason = Some("relative imports need importing_file".to_string());
+            return Ok(explanation);
+        };
+        match helpers::absolute_module(module, file, &config.source_root) {
+            Some(absolute) => explain_project(config, &absolute, &mut explanation)?,
+            None => {
+                explanation.reason =
+                    Some(format!("'{}' names no module below source_root from {}", module, file.display()));
+            }
+        }
+        return Ok(explanation);
+    }
+
+    let base_module = module.split('.').next().unwrap_or(module);
+    if config.project_module_prefixes.iter().any(|prefix| helpers::matches_module_prefix(module, prefix)) {
+        explanation.category = "project".to_string();
+        explain_project(config, module, &mut explanation)?;
+        return Ok(explanation);
+    }
+
+    let stdlib_modules = match &config.stdlib_list_path {
+        Some(path) => helpers::load_stdlib_from_file(path)?,
+        None => HashSet::new(),
+    };
+    if stdlib_modules.contains(base_module) {
+        explanation.category = "stdlib".to_string();
+        explanation.reason = Some(format!("'{}' is in the standard library list", base_module));
+        return Ok(explanation);
+    }
+
+    explanation.category = "third_party".to_string();
+    let deep_reason = if config.site_packages_dirs.is_empty() {
+        Some(format!("'{}' matches no project_module_prefixes entry", module))
+    } else if config.deep_max_depth == Some(0) {
+        Some("deep_max_depth is 0".to_string())
+    } else if !config.deep_packages.is_empty() && !config.deep_packages.iter().any(|p| p == base_module) {
+        Some(format!("'{}' is not in deep_packages", base_module))
+    } else {
+        None
+    };
+    if deep_reason.is_some() {
+        explanation.reason = deep_reason;
+        return Ok(explanation);
+    }
+    let exists = |p: &Path| p.exists();
+    for dir in &config.site_packages_dirs {
+        let tried = candidates(&helpers::module_candidates(module, dir, config.include_stubs), "site_packages", &exists);
+        let winner = tried.iter().position(|c| c.found);
+        let module_file = winner.map(|i| tried[i].path.clone());
+        explanation.candidates.extend(tried.into_iter().take(winner.map_or(usize::MAX, |i| i + 1)));
+        if let Some(module_file) = module_file {
+            explanation.resolved = helpers::find_package_inits_in_path_seq(module, dir, &mut HashMap::new())
+                .into_iter()
+                .map(|p| p.to_string_lossy().into_owned())
+                .chain([module_file])
+                .collect();
+            break;
+        }
+    }
+    if explanation.resolved.is_empty() {
+        explanation.reason = Some("not found in any site_packages_paths entry".to_string());
+    }
+    Ok(explanation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_explain_tries_source_roots_in_order() {
+        let dir = tempdir().unwrap();
+        let root = fs::canonicalize(dir.path()).unwrap();
+        fs::create_dir_all(root.join("src/app/ns")).unwrap();
+        fs::write(root.join("src/app/__init__.py"), "").unwrap();
+        fs::write(root.join("src/app/ns/mod.py"), "").unwrap();
+        let config = BuildConfig {
+            source_root: root.clone(),
+            extra_source_roots: vec![PathBuf::from("src")],
+            project_module_prefixes: vec!["app".to_string()],
+            ..Default::default()
+        };
+
+        let explanation = explain_resolution(&config, "app.ns.mod", None).unwrap();
+        let path = |rel: &str| root.join(rel).to_string_lossy().into_owned();
+        assert_eq!(explanation.candidates[0].path, path("app/ns/mod/__init__.py"));
+        assert!(!explanation.candidates[0].found);
+        assert_eq!(explanation.resolved, vec![path("src/app/__init__.py"), path("src/app/ns/mod.py")]);
+    }
+
+    #[test]
+    fn test_explain_project_resolution() {
+        let dir = tempdir().unwrap();
+        let root = fs::canonicalize(dir.path()).unwrap();
+        fs::create_dir_all(root.join("app/core")).unwrap();
+        fs::write(root.join("app/__init__.py"), "").unwrap();
+        fs::write(root.join("app/core/models.py"), "").unwrap();
+        fs::write(root.join("app/core/types.pyi"), "").unwrap();
+        let path = |rel: &str| root.join(rel).to_string_lossy().into_owned();
+        let config = BuildConfig {
+            source_root: root.clone(),
+            project_module_prefixes: vec!["app".to_string()],
+            include_paths: vec!["app".to_string()],
+            ..Default::default()
+        };
+
+        let explanation = explain_resolution(&config, "app.core.models", None).unwrap();
+        assert_eq!(explanation.category, "project");
+        let tried: Vec<(&str, &str, bool)> =
+            explanation.candidates.iter().map(|c| (c.path.as_str(), c.role.as_str(), c.found)).collect();
+        let (app_init, core_init, core_stub, models_init, models) = (
+            path("app/__init__.py"),
+            path("app/core/__init__.py"),
+            path("app/core/__init__.pyi"),
+            path("app/core/models/__init__.py"),
+            path("app/core/models.py"),
+        );
+        assert_eq!(tried, vec![
+            (app_init.as_str(), "package_init", true),
+            (core_init.as_str(), "package_init", false),
+            (core_stub.as_str(), "package_init", false),
+            (models_init.as_str(), "module", false),
+            (models.as_str(), "module", true),
+        ]);
+        assert_eq!(explanation.resolved, vec![app_init.clone(), models.clone()]);
+        assert_eq!(explanation.reason, None);
+
+        let missing = explain_resolution(&config, "app.core.types", None).unwrap();
+        assert_eq!(missing.resolved, vec![app_init]);
+        assert!(missing.reason.unwrap().contains("include_stubs"));
+
+        let stubs = BuildConfig { include_stubs: true, ..config.clone() };
+        assert_eq!(explain_resolution(&stubs, "app.core.types", None).unwrap().resolved.last(), Some(&path("app/core/types.pyi")));
+
+        let relative = explain_resolution(&config, ".models", Some(&root.join("app/core/views.py"))).unwrap();
+        assert_eq!(relative.category, "relative");
+        assert_eq!(relative.resolved, vec![path("app/__init__.py"), models.clone()]);
+        assert!(explain_resolution(&config, ".models", None).unwrap().resolved.is_empty());
+        assert_eq!(explain_resolution(&config, "requests", None).unwrap().category, "third_party");
+    }
+}
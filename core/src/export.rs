@@ -0,0 +1,559 @@
+#[cfg(feature = "python")]
+use pyo3::prelude::*;
+use serde_json::json;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::{closure_paths, entry_key, get_dependency_graph, helpers, Error, GraphLimits, ProjectFile, Result};
+
+/// Columnar edge list of a dependency map, one row per import edge.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EdgeTable {
+    pub src: Vec<String>,
+    pub dst: Vec<String>,
+    /// `project`, `stdlib` or `third_party`.
+    pub edge_kind: Vec<&'static str>,
+    /// Import statements behind the edge; 1 for edges without one (resources, scripts,
+    /// runtime and extra edges).
+    pub weight: Vec<u32>,
+}
+
+impl EdgeTable {
+    fn push(&mut self, src: &str, dst: &str, edge_kind: &'static str, weight: u32) {
+        self.src.push(src.to_string());
+        self.dst.push(dst.to_string());
+        self.edge_kind.push(edge_kind);
+        self.weight.push(weight);
+    }
+}
+
+/// Builds the edge table with rows sorted by source path, kind and target.
+pub fn edge_table(dependency_map: &HashMap<String, ProjectFile>) -> EdgeTable {
+    let mut sources: Vec<&String> = dependency_map.keys().collect();
+    sources.sort();
+
+    let mut table = EdgeTable::default();
+    for src in sources {
+        let info = &dependency_map[src];
+        for (kind, targets) in [
+            ("project", &info.project_imports),
+            ("stdlib", &info.stdlib_imports),
+            ("third_party", &info.third_party_imports),
+        ] {
+            let mut targets: Vec<&String> = targets.iter().collect();
+            targets.sort();
+            for dst in targets {
+                let weight = info.import_statements.get(dst).copied().unwrap_or(1).max(1);
+                table.push(src, dst, kind, weight);
+            }
+        }
+    }
+    table
+}
+
+fn sorted_paths(dependency_map: &HashMap<String, ProjectFile>) -> Vec<&String> {
+    let mut paths: Vec<&String> = dependency_map.keys().collect();
+    paths.sort();
+    paths
+}
+
+fn node_label(path: &str, source_root: Option<&Path>) -> String {
+    match source_root {
+        Some(root) => helpers::relative_path_str(Path::new(path), root),
+        None => path.to_string(),
+    }
+}
+
+/// Cytoscape.js `elements` JSON: one node per file (with `package`, `size` and `hash`
+/// data) and one directed edge per project import between files of the map.
+pub fn cytoscape_json(dependency_map: &HashMap<String, ProjectFile>, source_root: Option<&Path>) -> String {
+    let paths = sorted_paths(dependency_map);
+
+    let nodes: Vec<_> = paths
+        .iter()
+        .map(|path| {
+            let info = &dependency_map[*path];
+            let package = source_root
+                .and_then(|root| helpers::module_name_for_path(Path::new(path.as_str()), root))
+                .map(|module| {
+                    let is_package = path.ends_with("__init__.py") || path.ends_with("__init__.pyi");
+                    match module.rsplit_once('.') {
+                        Some((parent, _)) if !is_package => parent.to_string(),
+                        _ => module,
+                    }
+                });
+            json!({
+                "data": {
+                    "id": path,
+                    "label": node_label(path, source_root),
+                    "package": package,
+                    "size": info.size,
+                    "hash": info.hash,
+                }
+            })
+        })
+        .collect();
+
+    let mut edges = Vec::new();
+    for src in &paths {
+        let mut targets: Vec<&String> = dependency_map[*src]
+            .project_imports
+            .iter()
+            .filter(|dst| dependency_map.contains_key(*dst))
+            .collect();
+        targets.sort();
+        for dst in targets {
+            edges.push(json!({
+                "data": {
+                    "id": format!("{}->{}", src, dst),
+                    "source": src,
+                    "target": dst,
+                }
+            }));
+        }
+    }
+
+    json!({ "nodes": nodes, "edges": edges }).to_string()
+}
+
+/// Text format of `export_graph`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GraphFormat {
+    /// Graphviz `digraph`.
+    Dot,
+    /// `{"nodes": [...], "edges": [{"source": ..., "target": ...}]}`.
+    Json,
+    /// Mermaid `flowchart`, for Markdown renderers.
+    Mermaid,
+}
+
+impl FromStr for GraphFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "dot" => Ok(GraphFormat::Dot),
+            "json" => Ok(GraphFormat::Json),
+            "mermaid" => Ok(GraphFormat::Mermaid),
+            other => Err(Error::InvalidInput(format!(
+                "unknown graph format '{}', expected 'dot', 'json' or 'mermaid'",
+                other
+            ))),
+        }
+    }
+}
+
+/// The import graph of the map, or of `entry_point`'s closure, as text: one node per
+/// file labeled by its path (relative to `source_root` when given) and one edge per
+/// project import between files of the graph. Nodes and edges are sorted by path so
+/// the output of an unchanged map is byte-identical.
+pub fn export_graph(
+    dependency_map: &HashMap<String, ProjectFile>,
+    format: GraphFormat,
+    entry_point: Option<&str>,
+    source_root: Option<&Path>,
+) -> Result<String> {
+    let nodes: Vec<&String> = match entry_point {
+        Some(entry_point) => {
+            let closure = closure_paths(dependency_map, &entry_key(dependency_map, entry_point, source_root)?);
+            sorted_paths(dependency_map).into_iter().filter(|path| closure.contains(*path)).collect()
+        }
+        None => sorted_paths(dependency_map),
+    };
+    let index: HashMap<&str, usize> = nodes.iter().enumerate().map(|(i, path)| (path.as_str(), i)).collect();
+    let labels: Vec<String> = nodes.iter().map(|path| node_label(path, source_root)).collect();
+    let mut edges = Vec::new();
+    for (src, path) in nodes.iter().enumerate() {
+        let mut targets: Vec<usize> =
+            dependency_map[*path].project_imports.iter().filter_map(|dst| index.get(dst.as_str()).copied()).collect();
+        targets.sort();
+        targets.dedup();
+        edges.extend(targets.into_iter().map(|dst| (src, dst)));
+    }
+
+    Ok(match format {
+        GraphFormat::Dot => {
+            let quote = |label: &str| format!("\"{}\"", label.replace('\\', "\\\\").replace('"', "\\\""));
+            let mut out = String::from("digraph dependencies {\n");
+            for label in &labels {
+                out.push_str(&format!("    {};\n", quote(label)));
+            }
+            for (src, dst) in edges {
+                out.push_str(&format!("    {} -> {};\n", quote(&labels[src]), quote(&labels[dst])));
+            }
+            out.push_str("}\n");
+            out
+        }
+        GraphFormat::Json => {
+            let edges: Vec<_> =
+                edges.iter().map(|&(src, dst)| json!({ "source": labels[src], "target": labels[dst] })).collect();
+            json!({ "nodes": labels, "edges": edges }).to_string()
+        }
+        GraphFormat::Mermaid => {
+            // Labels may hold characters Mermaid treats as syntax, so nodes get
+            // positional ids and quoted labels.
+            let mut out = String::from("flowchart LR\n");
+            for (i, label) in labels.iter().enumerate() {
+                out.push_str(&format!("    n{}[\"{}\"]\n", i, label.replace('"', "#quot;")));
+            }
+            for (src, dst) in edges {
+                out.push_str(&format!("    n{} --> n{}\n", src, dst));
+            }
+            out
+        }
+    })
+}
+
+/// Package a file belongs to: its dotted parent package under `source_root`, or its
+/// parent directory when no root is known.
+fn package_of(path: &str, source_root: Option<&Path>) -> String {
+    let parent = Path::new(path).parent().unwrap_or(Path::new(""));
+    match source_root {
+        Some(root) => {
+            let relative = helpers::relative_path_str(parent, root);
+            if relative.is_empty() || parent == root {
+                "<root>".to_string()
+            } else {
+                relative.replace('/', ".")
+            }
+        }
+        None => parent.to_string_lossy().into_owned(),
+    }
+}
+
+const HTML_REPORT_TEMPLATE: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>Dependency report</title>
+<style>
+body { font-family: system-ui, sans-serif; margin: 2rem; color: #222; }
+h1 { font-size: 1.4rem; }
+table { border-collapse: collapse; margin-bottom: 2rem; }
+th, td { border: 1px solid #ccc; padding: 0.3rem 0.7rem; text-align: left; }
+th { background: #f3f3f3; }
+details { margin: 0.2rem 0; }
+summary { cursor: pointer; font-weight: 600; }
+.files, .deps { margin: 0.3rem 0 0.6rem 1.5rem; font-family: monospace; font-size: 0.9rem; }
+.muted { color: #777; font-weight: normal; }
+#search { width: 30rem; padding: 0.4rem; margin-bottom: 1rem; }
+</style>
+</head>
+<body>
+<h1>Dependency report</h1>
+<h2>Entry points</h2>
+<table id="entry-points"><thead><tr><th>Entry point</th><th>Files in closure</th><th>Bytes</th><th>Packages</th></tr></thead><tbody></tbody></table>
+<h2>Packages</h2>
+<input id="search" type="search" placeholder="Filter packages or files...">
+<div id="packages"></div>
+<script id="report-data" type="application/json">__REPORT_DATA__</script>
+<script>
+const data = JSON.parse(document.getElementById("report-data").textContent);
+const el = (tag, text, cls) => { const e = document.createElement(tag); if (text !== undefined) e.textContent = text; if (cls) e.className = cls; return e; };
+
+const tbody = document.querySelector("#entry-points tbody");
+for (const ep of data.entry_points) {
+  const row = el("tr");
+  for (const v of [ep.entry_point, ep.files, ep.bytes, ep.packages]) row.appendChild(el("td", String(v)));
+  tbody.appendChild(row);
+}
+
+const container = document.getElementById("packages");
+const items = data.packages.map(pkg => {
+  const details = el("details");
+  const summary = el("summary", pkg.name + " ");
+  summary.appendChild(el("span", "(" + pkg.files.length + " files, depends on " + pkg.depends_on.length + ")", "muted"));
+  details.appendChild(summary);
+  details.appendChild(el("div", "Files", "muted"));
+  const files = el("div", undefined, "files");
+  for (const f of pkg.files) files.appendChild(el("div", f));
+  details.appendChild(files);
+  details.appendChild(el("div", "Depends on", "muted"));
+  const deps = el("div", undefined, "deps");
+  for (const d of pkg.depends_on) deps.appendChild(el("div", d));
+  details.appendChild(deps);
+  container.appendChild(details);
+  return { pkg, details, haystack: (pkg.name + "\n" + pkg.files.join("\n")).toLowerCase() };
+});
+
+document.getElementById("search").addEventListener("input", e => {
+  const q = e.target.value.trim().toLowerCase();
+  for (const item of items) item.details.style.display = !q || item.haystack.includes(q) ? "" : "none";
+});
+</script>
+</body>
+</html>
+"#;
+
+/// Writes a standalone HTML page with a searchable, collapsible package-level view of
+/// the map and the closure size of each entry point.
+pub fn export_html_report(
+    dependency_map: &HashMap<String, ProjectFile>,
+    entry_points: &[String],
+    source_root: Option<&Path>,
+    output_path: &Path,
+) -> Result<()> {
+    let mut package_files: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    let mut package_deps: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    for path in sorted_paths(dependency_map) {
+        let package = package_of(path, source_root);
+        package_files.entry(package.clone()).or_default().insert(node_label(path, source_root));
+        let deps = package_deps.entry(package.clone()).or_default();
+        for dst in &dependency_map[path].project_imports {
+            let dst_package = package_of(dst, source_root);
+            if dst_package != package {
+                deps.insert(dst_package);
+            }
+        }
+    }
+
+    let packages: Vec<_> = package_files
+        .iter()
+        .map(|(name, files)| json!({
+            "name": name,
+            "files": files,
+            "depends_on": package_deps.get(name).cloned().unwrap_or_default(),
+        }))
+        .collect();
+
+    let mut entry_rows = Vec::with_capacity(entry_points.len());
+    for entry_point in entry_points {
+        let closure = get_dependency_graph(dependency_map, entry_point, source_root, &GraphLimits::default(), false)?;
+        let bytes: u64 = closure.keys().filter_map(|p| dependency_map.get(p)).map(|f| f.size).sum();
+        let packages: BTreeSet<String> = closure.keys().map(|p| package_of(p, source_root)).collect();
+        entry_rows.push(json!({
+            "entry_point": entry_point,
+            "files": closure.len(),
+            "bytes": bytes,
+            "packages": packages.len(),
+        }));
+    }
+
+    let data = json!({ "packages": packages, "entry_points": entry_rows })
+        .to_string()
+        .replace("</", "<\\/");
+    fs::write(output_path, HTML_REPORT_TEMPLATE.replace("__REPORT_DATA__", &data))?;
+    Ok(())
+}
+
+/// Minimal Docker build context for one entry point, with `source_root` as the context.
+#[cfg_attr(feature = "python", pyclass)]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DockerContext {
+    /// Sorted `/`-separated paths relative to the context.
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub files: Vec<String>,
+    /// `.dockerignore` excluding everything but `files`.
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub dockerignore: String,
+    /// One JSON-form `COPY` instruction per directory, preserving the layout.
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub copy_manifest: String,
+}
+
+/// Files of `entry_point`'s closure under `source_root`; files outside it (e.g. deep
+/// mode site-packages) cannot be part of the context and are left out.
+pub fn docker_context(
+    dependency_map: &HashMap<String, ProjectFile>,
+    entry_point: &str,
+    source_root: &Path,
+) -> Result<DockerContext> {
+    let entry_key = entry_key(dependency_map, entry_point, Some(source_root))?;
+    let files: BTreeSet<String> = closure_paths(dependency_map, &entry_key)
+        .into_iter()
+        .filter(|path| Path::new(path).starts_with(source_root))
+        .map(|path| helpers::relative_path_str(Path::new(&path), source_root))
+        .collect();
+
+    let mut dockerignore = String::from("**\n");
+    let mut by_dir: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for file in &files {
+        dockerignore.push_str(&format!("!{}\n", file));
+        let dir = file.rsplit_once('/').map_or("", |(dir, _)| dir);
+        by_dir.entry(dir).or_default().push(file);
+    }
+    let mut copy_manifest = String::new();
+    for (dir, dir_files) in by_dir {
+        let mut args: Vec<String> = dir_files.iter().map(|f| f.to_string()).collect();
+        args.push(if dir.is_empty() { "./".to_string() } else { format!("{}/", dir) });
+        copy_manifest.push_str(&format!("COPY {}\n", serde_json::to_string(&args).unwrap_or_default()));
+    }
+
+    Ok(DockerContext { files: files.into_iter().collect(), dockerignore, copy_manifest })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(project: &[&str], stdlib: &[&str], third_party: &[&str]) -> ProjectFile {
+        let owned = |items: &[&str]| items.iter().map(|s| s.to_string()).collect();
+        ProjectFile {
+            hash: "dummy".to_string(),
+            project_imports: owned(project),
+            stdlib_imports: owned(stdlib),
+            third_party_imports: owned(third_party),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_edge_table_rows_are_sorted_and_typed() {
+        let mut map = HashMap::new();
+        map.insert("b.py".to_string(), file(&[], &["os"], &["requests"]));
+        map.insert("a.py".to_string(), file(&["c.py", "b.py"], &[], &[]));
+
+        let table = edge_table(&map);
+        assert_eq!(table.src, vec!["a.py", "a.py", "b.py", "b.py"]);
+        assert_eq!(table.dst, vec!["b.py", "c.py", "os", "requests"]);
+        assert_eq!(table.edge_kind, vec!["project", "project", "stdlib", "third_party"]);
+        assert_eq!(table.weight, vec![1, 1, 1, 1]);
+    }
+
+    #[test]
+    fn test_edge_table_weight_counts_import_statements() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = fs::canonicalize(dir.path()).unwrap();
+        fs::create_dir(root.join("app")).unwrap();
+        fs::write(root.join("app/main.py"), "import os\nfrom os import path, sep\nfrom app import util\nimport app.util\nimport requests\n").unwrap();
+        fs::write(root.join("app/util.py"), "").unwrap();
+        let config = crate::BuildConfig {
+            source_root: root.clone(),
+            project_module_prefixes: vec!["app".to_string()],
+            include_paths: vec!["app".to_string()],
+            ..Default::default()
+        };
+        let map = crate::build_dependency_map(&config).unwrap().files;
+
+        let table = edge_table(&map);
+        assert_eq!(table.dst, vec![root.join("app/util.py").to_string_lossy().into_owned(), "os".to_string(), "requests".to_string()]);
+        assert_eq!(table.weight, vec![2, 2, 1]);
+    }
+
+    #[test]
+    fn test_cytoscape_json_nodes_and_edges() {
+        let mut map = HashMap::new();
+        map.insert("/p/app/main.py".to_string(), file(&["/p/app/util.py", "/p/missing.py"], &[], &[]));
+        map.insert("/p/app/util.py".to_string(), file(&[], &[], &[]));
+        map.insert("/p/app/__init__.py".to_string(), file(&[], &[], &[]));
+
+        let value: serde_json::Value = serde_json::from_str(&cytoscape_json(&map, Some(Path::new("/p")))).unwrap();
+        let nodes = value["nodes"].as_array().unwrap();
+        assert_eq!(nodes.len(), 3);
+        assert_eq!(nodes[1]["data"]["label"], "app/main.py");
+        assert_eq!(nodes[1]["data"]["package"], "app");
+        assert_eq!(nodes[0]["data"]["package"], "app");
+
+        let edges = value["edges"].as_array().unwrap();
+        assert_eq!(edges.len(), 1, "edges to files outside the map are dropped");
+        assert_eq!(edges[0]["data"]["source"], "/p/app/main.py");
+        assert_eq!(edges[0]["data"]["target"], "/p/app/util.py");
+    }
+
+    #[test]
+    fn test_export_graph_formats_and_entry_point_subgraph() {
+        let mut map = HashMap::new();
+        map.insert("/p/app/main.py".to_string(), file(&["/p/app/util.py", "/p/missing.py"], &["os"], &[]));
+        map.insert("/p/app/util.py".to_string(), file(&["/p/app/util.py"], &[], &[]));
+        map.insert("/p/app/say \"hi\".py".to_string(), file(&["/p/app/main.py"], &[], &[]));
+        let root = Some(Path::new("/p"));
+
+        assert_eq!(
+            export_graph(&map, GraphFormat::Dot, None, root).unwrap(),
+            concat!(
+                "digraph dependencies {\n",
+                "    \"app/main.py\";\n",
+                "    \"app/say \\\"hi\\\".py\";\n",
+                "    \"app/util.py\";\n",
+                "    \"app/main.py\" -> \"app/util.py\";\n",
+                "    \"app/say \\\"hi\\\".py\" -> \"app/main.py\";\n",
+                "    \"app/util.py\" -> \"app/util.py\";\n",
+                "}\n",
+            )
+        );
+        assert_eq!(
+            export_graph(&map, GraphFormat::Mermaid, Some("/p/app/main.py"), root).unwrap(),
+            "flowchart LR\n    n0[\"app/main.py\"]\n    n1[\"app/util.py\"]\n    n0 --> n1\n    n1 --> n1\n"
+        );
+        let value: serde_json::Value =
+            serde_json::from_str(&export_graph(&map, GraphFormat::Json, Some("/p/app/util.py"), None).unwrap()).unwrap();
+        assert_eq!(value["nodes"], json!(["/p/app/util.py"]));
+        assert_eq!(value["edges"], json!([{ "source": "/p/app/util.py", "target": "/p/app/util.py" }]));
+        assert!(export_graph(&map, GraphFormat::Dot, Some("app.gone"), root).is_err());
+        assert!("svg".parse::<GraphFormat>().is_err());
+    }
+
+    #[test]
+    fn test_html_report_script_has_no_multiline_strings() {
+        // A raw line break inside a `"` or `'` literal is a syntax error that stops the
+        // whole script.
+        let script = HTML_REPORT_TEMPLATE.rsplit("<script>").next().unwrap().split("</script>").next().unwrap();
+        let (mut quote, mut escaped) = (None, false);
+        for (line, text) in script.lines().enumerate() {
+            for c in text.chars() {
+                match quote {
+                    Some(_) if escaped => escaped = false,
+                    Some(_) if c == '\\' => escaped = true,
+                    Some(q) if c == q => quote = None,
+                    Some(_) => {}
+                    None if c == '"' || c == '\'' => quote = Some(c),
+                    None => {}
+                }
+            }
+            assert!(quote.is_none(), "string literal left open on script line {}: {}", line + 1, text);
+        }
+    }
+
+    #[test]
+    fn test_export_html_report_embeds_packages_and_closures() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = fs::canonicalize(dir.path()).unwrap();
+        fs::create_dir_all(root.join("app/core")).unwrap();
+        fs::write(root.join("app/main.py"), "").unwrap();
+        let main = root.join("app/main.py").to_string_lossy().into_owned();
+        let core = root.join("app/core/db.py").to_string_lossy().into_owned();
+
+        let odd_name = root.join("app/x</script>y.py").to_string_lossy().into_owned();
+
+        let mut map = HashMap::new();
+        map.insert(main.clone(), file(&[core.as_str()], &[], &[]));
+        map.insert(core.clone(), file(&[], &[], &[]));
+        map.insert(odd_name, file(&[], &[], &[]));
+
+        let output = root.join("report.html");
+        export_html_report(&map, &[main], Some(&root), &output).unwrap();
+        let html = fs::read_to_string(output).unwrap();
+
+        assert!(html.contains(r#""name":"app.core""#));
+        assert!(html.contains(r#""depends_on":["app.core"]"#));
+        assert!(html.contains(r#""files":2"#));
+        assert_eq!(html.matches("</script>").count(), 2, "embedded data must not close the script tag");
+    }
+
+    #[test]
+    fn test_docker_context_lists_closure_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = fs::canonicalize(dir.path()).unwrap();
+        fs::create_dir_all(root.join("app/core")).unwrap();
+        fs::write(root.join("main.py"), "").unwrap();
+        let key = |rel: &str| root.join(rel).to_string_lossy().into_owned();
+
+        let mut map = HashMap::new();
+        let (core_init, core_db) = (key("app/core/__init__.py"), key("app/core/db.py"));
+        map.insert(key("main.py"), file(&[core_init.as_str(), core_db.as_str(), "/elsewhere/lib.py"], &[], &[]));
+        map.insert(core_init.clone(), file(&[], &[], &[]));
+        map.insert(core_db.clone(), file(&[], &[], &[]));
+        map.insert("/elsewhere/lib.py".to_string(), file(&[], &[], &[]));
+        map.insert(key("app/unused.py"), file(&[], &[], &[]));
+
+        let context = docker_context(&map, "main", &root).unwrap();
+        assert_eq!(context.files, vec!["app/core/__init__.py", "app/core/db.py", "main.py"]);
+        assert_eq!(context.dockerignore, "**\n!app/core/__init__.py\n!app/core/db.py\n!main.py\n");
+        assert_eq!(
+            context.copy_manifest,
+            "COPY [\"main.py\",\"./\"]\nCOPY [\"app/core/__init__.py\",\"app/core/db.py\",\"app/core/\"]\n"
+        );
+    }
+}
@@ -0,0 +1,50 @@
+#[cfg(feature = "python")]
+use pyo3::prelude::*;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::ProjectFile;
+
+/// Immutable dependency map behind an `Arc`: clones share the files, and queries can
+/// run from any number of threads at once.
+#[cfg_attr(feature = "python", pyclass(frozen))]
+#[derive(Clone, Debug, Default)]
+pub struct FrozenMap {
+    files: Arc<HashMap<String, ProjectFile>>,
+}
+
+impl FrozenMap {
+    pub fn new(files: HashMap<String, ProjectFile>) -> Self {
+        FrozenMap { files: Arc::new(files) }
+    }
+
+    pub fn files(&self) -> &HashMap<String, ProjectFile> {
+        &self.files
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{get_dependency_graph, GraphLimits};
+    use std::thread;
+
+    #[test]
+    fn test_frozen_map_shared_across_threads() {
+        let mut files = HashMap::new();
+        files.insert("main.py".to_string(), ProjectFile { project_imports: vec!["util.py".to_string()], ..Default::default() });
+        files.insert("util.py".to_string(), ProjectFile::default());
+        let frozen = FrozenMap::new(files);
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let map = frozen.clone();
+                thread::spawn(move || get_dependency_graph(map.files(), "main.py", None, &GraphLimits::default(), false).unwrap().len())
+            })
+            .collect();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), 2);
+        }
+        assert!(Arc::ptr_eq(&frozen.files, &frozen.clone().files));
+    }
+}
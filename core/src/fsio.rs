@@ -0,0 +1,178 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, UNIX_EPOCH};
+
+/// Retry and concurrency settings for reading sources, for workspaces on network
+/// filesystems (NFS, EFS) where reads fail transiently.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IoOptions {
+    /// Extra attempts after a transient failure of a stat or read.
+    pub retries: u32,
+    /// Wait before the first retry; doubled for each further one.
+    pub backoff: Duration,
+    /// Threads reading files ahead of the parser; 1 reads each file when it is parsed.
+    pub threads: usize,
+    /// Files read ahead per batch when `threads` > 1.
+    pub batch_size: usize,
+    /// Cap on file descriptors held at once, shared by the directory walk and the
+    /// readers; lowers the number of readers when needed. At least 2 are used.
+    pub max_open_files: Option<usize>,
+    /// Cap on the bytes read ahead and not parsed yet; batches end early rather than
+    /// exceed it (a single larger file is still read on its own).
+    pub max_buffered_bytes: Option<u64>,
+}
+
+impl Default for IoOptions {
+    fn default() -> Self {
+        IoOptions {
+            retries: 3,
+            backoff: Duration::from_millis(50),
+            threads: 1,
+            batch_size: 256,
+            max_open_files: None,
+            max_buffered_bytes: None,
+        }
+    }
+}
+
+/// Directory handles `walkdir` keeps open by default.
+const WALKER_OPEN_DIRS: usize = 10;
+
+impl IoOptions {
+    /// Directory handles the walk may keep open: half of `max_open_files`, at most
+    /// walkdir's default.
+    pub(crate) fn walker_open_dirs(&self) -> usize {
+        self.max_open_files.map_or(WALKER_OPEN_DIRS, |max| (max / 2).clamp(1, WALKER_OPEN_DIRS))
+    }
+
+    /// Concurrent readers, each holding one file open: `threads`, within what
+    /// `max_open_files` leaves after the walk.
+    pub(crate) fn reader_threads(&self) -> usize {
+        let threads = self.threads.max(1);
+        match self.max_open_files {
+            Some(max) => threads.min(max.saturating_sub(self.walker_open_dirs()).max(1)),
+            None => threads,
+        }
+    }
+}
+
+/// Errors worth retrying: interrupted or timed-out calls, and the I/O and stale
+/// handle errors network filesystems report while a server is failing over.
+fn is_transient(error: &io::Error) -> bool {
+    if matches!(error.kind(), io::ErrorKind::Interrupted | io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) {
+        return true;
+    }
+    // EIO and ESTALE.
+    #[cfg(target_os = "linux")]
+    const TRANSIENT_OS_ERRORS: &[i32] = &[5, 116];
+    #[cfg(target_os = "macos")]
+    const TRANSIENT_OS_ERRORS: &[i32] = &[5, 70];
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    const TRANSIENT_OS_ERRORS: &[i32] = &[];
+    error.raw_os_error().is_some_and(|code| TRANSIENT_OS_ERRORS.contains(&code))
+}
+
+fn with_retry<T>(options: &IoOptions, mut op: impl FnMut() -> io::Result<T>) -> io::Result<T> {
+    let mut delay = options.backoff;
+    let mut attempt = 0;
+    loop {
+        match op() {
+            Err(e) if attempt < options.retries && is_transient(&e) => {
+                tracing::debug!(error = %e, attempt, "retrying transient IO error");
+                thread::sleep(delay);
+                delay *= 2;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Size and modification time (ns since the epoch, 0 when unknown) of `path`.
+pub(crate) fn stat(path: &Path, options: &IoOptions) -> io::Result<(u64, u64)> {
+    let metadata = with_retry(options, || fs::metadata(path))?;
+    let mtime_ns = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_nanos() as u64);
+    Ok((metadata.len(), mtime_ns))
+}
+
+pub(crate) fn read(path: &Path, options: &IoOptions) -> io::Result<Vec<u8>> {
+    with_retry(options, || fs::read(path))
+}
+
+/// Reads `paths` on up to `IoOptions::reader_threads` threads, keeping the input order.
+pub(crate) fn read_all(paths: &[PathBuf], options: &IoOptions) -> Vec<io::Result<Vec<u8>>> {
+    let threads = options.reader_threads().min(paths.len().max(1));
+    let chunk_size = paths.len().div_ceil(threads).max(1);
+    thread::scope(|scope| {
+        let handles: Vec<_> = paths
+            .chunks(chunk_size)
+            .map(|chunk| scope.spawn(move || chunk.iter().map(|path| read(path, options)).collect::<Vec<_>>()))
+            .collect();
+        handles.into_iter().flat_map(|handle| handle.join().expect("reader thread panicked")).collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_with_retry_retries_transient_errors_only() {
+        let options = IoOptions { retries: 2, backoff: Duration::from_millis(1), ..Default::default() };
+        let calls = Cell::new(0);
+        let result = with_retry(&options, || {
+            calls.set(calls.get() + 1);
+            if calls.get() < 3 { Err(io::Error::from(io::ErrorKind::TimedOut)) } else { Ok(calls.get()) }
+        });
+        assert_eq!(result.unwrap(), 3);
+
+        calls.set(0);
+        let result: io::Result<()> = with_retry(&options, || {
+            calls.set(calls.get() + 1);
+            Err(io::Error::from(io::ErrorKind::TimedOut))
+        });
+        assert!(result.is_err());
+        assert_eq!(calls.get(), 3);
+
+        calls.set(0);
+        let result: io::Result<()> = with_retry(&options, || {
+            calls.set(calls.get() + 1);
+            Err(io::Error::from(io::ErrorKind::NotFound))
+        });
+        assert!(result.is_err());
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[test]
+    fn test_open_file_budget() {
+        let options = IoOptions { threads: 8, ..Default::default() };
+        assert_eq!((options.walker_open_dirs(), options.reader_threads()), (10, 8));
+        let options = IoOptions { threads: 8, max_open_files: Some(6), ..Default::default() };
+        assert_eq!((options.walker_open_dirs(), options.reader_threads()), (3, 3));
+        let options = IoOptions { threads: 8, max_open_files: Some(1), ..Default::default() };
+        assert_eq!((options.walker_open_dirs(), options.reader_threads()), (1, 1));
+    }
+
+    #[test]
+    fn test_read_all_keeps_order() {
+        let dir = tempdir().unwrap();
+        let paths: Vec<PathBuf> = (0..10).map(|i| dir.path().join(format!("{}.py", i))).collect();
+        for (i, path) in paths.iter().enumerate().skip(1) {
+            fs::write(path, i.to_string()).unwrap();
+        }
+        let options = IoOptions { threads: 3, ..Default::default() };
+        let contents = read_all(&paths, &options);
+        assert!(contents[0].is_err());
+        for (i, content) in contents.iter().enumerate().skip(1) {
+            assert_eq!(content.as_ref().unwrap(), i.to_string().as_bytes());
+        }
+    }
+}
@@ -0,0 +1,192 @@
+#[cfg(feature = "python")]
+use pyo3::prelude::*;
+use git2::{ObjectType, Repository, Sort, TreeWalkMode, TreeWalkResult};
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::{Error, Result};
+
+fn git_error(e: git2::Error) -> io::Error {
+    io::Error::other(e)
+}
+
+/// Last commit that touched a file.
+#[cfg_attr(feature = "python", pyclass)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GitCommit {
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub hash: String,
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub author: String,
+    /// Commit time in seconds since the Unix epoch.
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub time: i64,
+}
+
+/// For each of `paths` inside the repository containing `root`, the newest commit
+/// reachable from `HEAD` whose diff against its first parent touches it. Paths that
+/// were never committed, or lie outside the work tree, are absent.
+///
+/// Walks history newest first and stops as soon as every path has been seen.
+pub(crate) fn last_commits(root: &Path, paths: &[PathBuf]) -> Result<HashMap<PathBuf, GitCommit>> {
+    let repo = Repository::discover(root).map_err(git_error)?;
+    let Some(workdir) = repo.workdir().and_then(|dir| dir.canonicalize().ok()) else {
+        return Ok(HashMap::new());
+    };
+    // Path relative to the work tree -> path as given.
+    let mut wanted: HashMap<PathBuf, &PathBuf> = paths
+        .iter()
+        .filter_map(|path| {
+            let absolute = path.canonicalize().unwrap_or_else(|_| path.clone());
+            Some((absolute.strip_prefix(&workdir).ok()?.to_path_buf(), path))
+        })
+        .collect();
+
+    let mut found = HashMap::new();
+    let mut revwalk = repo.revwalk().map_err(git_error)?;
+    revwalk.push_head().map_err(git_error)?;
+    revwalk.set_sorting(Sort::TIME).map_err(git_error)?;
+    for oid in revwalk {
+        if wanted.is_empty() {
+            break;
+        }
+        let commit = repo.find_commit(oid.map_err(git_error)?).map_err(git_error)?;
+        let tree = commit.tree().map_err(git_error)?;
+        let parent_tree = match commit.parents().next() {
+            Some(parent) => Some(parent.tree().map_err(git_error)?),
+            None => None,
+        };
+        let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None).map_err(git_error)?;
+        for delta in diff.deltas() {
+            let Some(path) = delta.new_file().path() else { continue };
+            if let Some(original) = wanted.remove(path) {
+                found.insert(original.clone(), GitCommit {
+                    hash: commit.id().to_string(),
+                    author: commit.author().name().unwrap_or_default().to_string(),
+                    time: commit.time().seconds(),
+                });
+            }
+        }
+    }
+    Ok(found)
+}
+
+/// Python sources (`.py`, `.pyi`) of `revision`'s tree, read from the object store
+/// of the repository at `repo_path` (bare or not) without touching a work tree.
+/// Keyed by their `/`-separated path in the tree.
+pub(crate) fn revision_sources(repo_path: &Path, revision: &str) -> Result<BTreeMap<String, Vec<u8>>> {
+    let repo = Repository::open(repo_path).map_err(git_error)?;
+    let tree = repo
+        .revparse_single(revision)
+        .and_then(|object| object.peel_to_tree())
+        .map_err(|e| Error::InvalidInput(format!("git revision '{}' could not be resolved: {}", revision, e.message())))?;
+    let mut blobs = Vec::new();
+    tree.walk(TreeWalkMode::PreOrder, |dir, entry| {
+        if let (Some(ObjectType::Blob), Some(name)) = (entry.kind(), entry.name()) {
+            if name.ends_with(".py") || name.ends_with(".pyi") {
+                blobs.push((format!("{}{}", dir, name), entry.id()));
+            }
+        }
+        TreeWalkResult::Ok
+    })
+    .map_err(git_error)?;
+
+    let mut sources = BTreeMap::new();
+    for (name, id) in blobs {
+        let blob = repo.find_blob(id).map_err(git_error)?;
+        sources.insert(name, blob.content().to_vec());
+    }
+    Ok(sources)
+}
+
+/// Ignore rules of the repository containing a directory, as git applies them to its
+/// work tree: every `.gitignore`, `.git/info/exclude` and the global excludes file.
+pub(crate) struct IgnoreRules {
+    repo: Repository,
+    workdir: PathBuf,
+}
+
+impl IgnoreRules {
+    /// `None` when `root` is not inside a work tree.
+    pub(crate) fn discover(root: &Path) -> Option<Self> {
+        let repo = Repository::discover(root).ok()?;
+        let workdir = repo.workdir()?.canonicalize().ok()?;
+        Some(IgnoreRules { repo, workdir })
+    }
+
+    /// Whether git ignores `path`, itself or through an ignored directory above it.
+    /// Paths outside the work tree are never ignored.
+    pub(crate) fn is_ignored(&self, path: &Path) -> bool {
+        // Only the parent is resolved, so a symlink is judged by its own name.
+        let absolute = match (path.parent().and_then(|dir| dir.canonicalize().ok()), path.file_name()) {
+            (Some(dir), Some(name)) => dir.join(name),
+            _ => path.to_path_buf(),
+        };
+        match absolute.strip_prefix(&self.workdir) {
+            Ok(relative) if !relative.as_os_str().is_empty() => self.repo.is_path_ignored(relative).unwrap_or(false),
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::Signature;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn commit_all(repo: &Repository, author: &str, time: i64, message: &str) {
+        let mut index = repo.index().unwrap();
+        index.add_all(["*"], git2::IndexAddOption::DEFAULT, None).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = Signature::new(author, "dev@example.com", &git2::Time::new(time, 0)).unwrap();
+        let parent = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+        repo.commit(Some("HEAD"), &signature, &signature, message, &tree, &parents).unwrap();
+    }
+
+    #[test]
+    fn test_last_commits_per_file() {
+        let dir = tempdir().unwrap();
+        let root = fs::canonicalize(dir.path()).unwrap();
+        let repo = Repository::init(&root).unwrap();
+        fs::create_dir(root.join("app")).unwrap();
+        fs::write(root.join("app/main.py"), "import os\n").unwrap();
+        fs::write(root.join("app/util.py"), "").unwrap();
+        commit_all(&repo, "alice", 1_700_000_000, "initial");
+        fs::write(root.join("app/util.py"), "X = 1\n").unwrap();
+        commit_all(&repo, "bob", 1_700_000_100, "tweak util");
+        fs::write(root.join("app/new.py"), "").unwrap();
+
+        let paths = vec![root.join("app/main.py"), root.join("app/util.py"), root.join("app/new.py")];
+        let commits = last_commits(&root.join("app"), &paths).unwrap();
+        assert_eq!(commits.len(), 2);
+        assert_eq!(commits[&root.join("app/main.py")].author, "alice");
+        assert_eq!(commits[&root.join("app/util.py")].author, "bob");
+        assert_eq!(commits[&root.join("app/util.py")].time, 1_700_000_100);
+        assert_eq!(commits[&root.join("app/util.py")].hash, repo.head().unwrap().target().unwrap().to_string());
+    }
+
+    #[test]
+    fn test_revision_sources_reads_tree() {
+        let dir = tempdir().unwrap();
+        let root = fs::canonicalize(dir.path()).unwrap();
+        let repo = Repository::init(&root).unwrap();
+        fs::create_dir(root.join("app")).unwrap();
+        fs::write(root.join("app/main.py"), "import os\n").unwrap();
+        fs::write(root.join("app/data.json"), "{}").unwrap();
+        commit_all(&repo, "alice", 1_700_000_000, "initial");
+        fs::write(root.join("app/main.py"), "import sys\n").unwrap();
+        commit_all(&repo, "alice", 1_700_000_100, "switch to sys");
+        fs::write(root.join("app/main.py"), "uncommitted").unwrap();
+
+        let head = revision_sources(&root, "HEAD").unwrap();
+        assert_eq!(head.keys().collect::<Vec<_>>(), ["app/main.py"]);
+        assert_eq!(head["app/main.py"], b"import sys\n");
+        assert_eq!(revision_sources(&root, "HEAD~1").unwrap()["app/main.py"], b"import os\n");
+        assert!(matches!(revision_sources(&root, "no-such-branch"), Err(Error::InvalidInput(_))));
+    }
+}
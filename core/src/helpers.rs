@@ -0,0 +1,1093 @@
+use ruff_python_ast::visitor::{self, Visitor};
+use ruff_python_ast::{Expr, ExprCall, Operator, PythonVersion, Stmt};
+use ruff_python_parser::{Mode, ParseOptions};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use crate::{ImportKind, ParserOptions};
+
+pub(crate) fn load_stdlib_from_file(path: &Path) -> io::Result<HashSet<String>> {
+    let content = fs::read_to_string(path)?;
+    let cleaned_content = content
+        .trim()
+        .strip_prefix('[')
+        .and_then(|s| s.strip_suffix(']'))
+        .unwrap_or(&content);
+
+    let modules: HashSet<String> = cleaned_content
+        .split(',')
+        .map(|s| s.trim().trim_matches('\'').to_string())
+        .collect();
+        
+    Ok(modules)
+}
+
+pub(crate) fn find_package_inits_in_path_seq(
+    module: &str,
+    source_root: &Path,
+    cache: &mut HashMap<String, Vec<PathBuf>>,
+) -> Vec<PathBuf> {
+    find_package_inits_with(module, source_root, cache, &|p| p.exists())
+}
+
+/// `find_package_inits_in_path_seq` with the existence check supplied by the caller,
+/// e.g. for sources read from an archive.
+pub(crate) fn find_package_inits_with(
+    module: &str,
+    source_root: &Path,
+    cache: &mut HashMap<String, Vec<PathBuf>>,
+    exists: &dyn Fn(&Path) -> bool,
+) -> Vec<PathBuf> {
+    if let Some(cached) = cache.get(module) {
+        return cached.clone();
+    }
+    let inits: Vec<PathBuf> = parent_package_dirs(module, source_root)
+        .iter()
+        .filter_map(|dir| package_init_candidates(dir).into_iter().find(|candidate| exists(candidate)))
+        .collect();
+    cache.insert(module.to_string(), inits.clone());
+    inits
+}
+
+/// Directories of the packages enclosing `module` (`a` and `a/b` for `a.b.c`).
+pub(crate) fn parent_package_dirs(module: &str, source_root: &Path) -> Vec<PathBuf> {
+    let segments: Vec<&str> = module.split('.').collect();
+    let mut current_path = source_root.to_path_buf();
+    let mut dirs = Vec::new();
+    for segment in &segments[..segments.len() - 1] {
+        current_path.push(segment);
+        dirs.push(current_path.clone());
+    }
+    dirs
+}
+
+/// `__init__.py` of a package directory, then the stub-only `__init__.pyi` fallback.
+pub(crate) fn package_init_candidates(package_dir: &Path) -> [PathBuf; 2] {
+    [package_dir.join("__init__.py"), package_dir.join("__init__.pyi")]
+}
+
+/// Resolves `module` to a package `__init__.py` or a `.py` file. With `include_stubs`,
+/// stub-only packages and `.pyi` modules are accepted as targets too.
+pub(crate) fn resolve_module_in_project_seq(
+    module: &str,
+    source_root: &Path,
+    include_stubs: bool,
+    cache: &mut HashMap<String, Option<PathBuf>>,
+) -> Option<PathBuf> {
+    resolve_module_with(module, source_root, include_stubs, cache, &|p| p.exists())
+}
+
+/// `resolve_module_in_project_seq` with the existence check supplied by the caller.
+pub(crate) fn resolve_module_with(
+    module: &str,
+    source_root: &Path,
+    include_stubs: bool,
+    cache: &mut HashMap<String, Option<PathBuf>>,
+    exists: &dyn Fn(&Path) -> bool,
+) -> Option<PathBuf> {
+    if let Some(cached) = cache.get(module) {
+        return cached.clone();
+    }
+    let result = module_candidates(module, source_root, include_stubs).into_iter().find(|candidate| exists(candidate));
+    cache.insert(module.to_string(), result.clone());
+    result
+}
+
+/// Files `module` may resolve to, in the order `resolve_module_with` tries them.
+pub(crate) fn module_candidates(module: &str, source_root: &Path, include_stubs: bool) -> Vec<PathBuf> {
+    let base = source_root.join(module.replace('.', "/"));
+    let mut candidates = vec![base.join("__init__.py"), base.with_extension("py")];
+    if include_stubs {
+        candidates.extend([base.join("__init__.pyi"), base.with_extension("pyi")]);
+    }
+    candidates
+}
+
+/// Root of `roots` (searched in order, like `sys.path`) that `module` resolves under:
+/// the first holding its module file, else the first holding its top-level package
+/// directory, else the first root. Namespace packages need no `__init__.py`, so
+/// their portions may live under different roots. `exists` and `dir_exists` probe
+/// files and directories of the scanned sources.
+pub(crate) fn module_root<'r>(
+    module: &str,
+    roots: &'r [PathBuf],
+    include_stubs: bool,
+    exists: &dyn Fn(&Path) -> bool,
+    dir_exists: &dyn Fn(&Path) -> bool,
+) -> &'r Path {
+    let top_level = module.split('.').next().unwrap_or(module);
+    roots
+        .iter()
+        .find(|root| module_candidates(module, root, include_stubs).iter().any(|candidate| exists(candidate)))
+        .or_else(|| roots.iter().find(|root| dir_exists(&root.join(top_level))))
+        .unwrap_or(&roots[0])
+}
+
+/// Whether the walk should pick up `path`: `.py` sources, stub-only package inits,
+/// and any `.pyi` stub when `include_stubs` is set.
+pub(crate) fn is_source_file(path: &Path, include_stubs: bool) -> bool {
+    is_source_file_with(path, include_stubs, &|p| p.exists())
+}
+
+pub(crate) fn is_source_file_with(path: &Path, include_stubs: bool, exists: &dyn Fn(&Path) -> bool) -> bool {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("py") => true,
+        Some("pyi") => {
+            include_stubs
+                || (path.file_stem().is_some_and(|stem| stem == "__init__")
+                    && !exists(&path.with_extension("py")))
+        }
+        _ => false,
+    }
+}
+
+/// Matches a `/`-separated relative path against a glob supporting `*`, `?` and `**`.
+/// Patterns without a `/` are matched against the file name only.
+pub(crate) fn glob_match(pattern: &str, path: &str) -> bool {
+    let pattern = pattern.trim_start_matches("./").trim_end_matches('/');
+    if !pattern.contains('/') {
+        let name = path.rsplit('/').next().unwrap_or(path);
+        return wildcard_match(pattern.as_bytes(), name.as_bytes());
+    }
+    let pattern_segments: Vec<&str> = pattern.trim_start_matches('/').split('/').collect();
+    let path_segments: Vec<&str> = path.split('/').collect();
+    match_segments(&pattern_segments, &path_segments)
+}
+
+fn match_segments(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => {
+            match_segments(rest, path) || (!path.is_empty() && match_segments(pattern, &path[1..]))
+        }
+        Some((first, rest)) => {
+            !path.is_empty()
+                && wildcard_match(first.as_bytes(), path[0].as_bytes())
+                && match_segments(rest, &path[1..])
+        }
+    }
+}
+
+fn wildcard_match(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some((b'*', rest)) => (0..=text.len()).any(|i| wildcard_match(rest, &text[i..])),
+        Some((b'?', rest)) => !text.is_empty() && wildcard_match(rest, &text[1..]),
+        Some((c, rest)) => text.first() == Some(c) && wildcard_match(rest, &text[1..]),
+    }
+}
+
+/// Slash-separated path of `path` relative to `source_root`, or the full path when outside it.
+pub(crate) fn relative_path_str(path: &Path, source_root: &Path) -> String {
+    let relative = path.strip_prefix(source_root).unwrap_or(path);
+    relative
+        .iter()
+        .map(|s| s.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// `path` with `.` and `..` components folded away, without touching the filesystem.
+pub(crate) fn normalize_lexically(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                Some(Component::RootDir | Component::Prefix(_)) => {}
+                _ => normalized.push(".."),
+            },
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+/// Content as hashed: with CRLF turned into LF and/or trailing newlines removed.
+pub(crate) fn normalize_for_hash(content: &[u8], line_endings: bool, trailing_newlines: bool) -> Cow<'_, [u8]> {
+    let mut content = Cow::Borrowed(content);
+    if line_endings && content.windows(2).any(|w| w == b"\r\n") {
+        let mut normalized = Vec::with_capacity(content.len());
+        let mut bytes = content.iter().peekable();
+        while let Some(&byte) = bytes.next() {
+            if byte != b'\r' || bytes.peek() != Some(&&b'\n') {
+                normalized.push(byte);
+            }
+        }
+        content = Cow::Owned(normalized);
+    }
+    if trailing_newlines {
+        let newline_bytes: &[u8] = if line_endings { b"\n" } else { b"\r\n" };
+        let end = content.iter().rposition(|b| !newline_bytes.contains(b)).map_or(0, |i| i + 1);
+        content = match content {
+            Cow::Borrowed(slice) => Cow::Borrowed(&slice[..end]),
+            Cow::Owned(mut vec) => {
+                vec.truncate(end);
+                Cow::Owned(vec)
+            }
+        };
+    }
+    content
+}
+
+pub(crate) fn has_volatile_pragma(source: &str) -> bool {
+    source.lines().any(|line| line.trim() == "# depmap: volatile")
+}
+
+/// `app` matches `app` and `app.utils` but not `apps.utils`; a prefix ending in
+/// `.` (`app.`) only matches submodules.
+pub(crate) fn matches_module_prefix(module: &str, prefix: &str) -> bool {
+    if prefix.ends_with('.') {
+        return module.starts_with(prefix);
+    }
+    match module.strip_prefix(prefix) {
+        Some(rest) => rest.is_empty() || rest.starts_with('.'),
+        None => false,
+    }
+}
+
+pub(crate) fn is_dotted_module_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.split('.').all(|segment| {
+            !segment.is_empty()
+                && !segment.starts_with(|c: char| c.is_ascii_digit())
+                && segment.chars().all(|c| c.is_alphanumeric() || c == '_')
+        })
+}
+
+/// Dotted module name of a file below `source_root` (`pkg/__init__.py` -> `pkg`).
+pub(crate) fn module_name_for_path(path: &Path, source_root: &Path) -> Option<String> {
+    let relative = path.strip_prefix(source_root).ok()?;
+    let stem = relative.file_stem()?.to_str()?;
+    if !matches!(relative.extension().and_then(|e| e.to_str()), Some("py") | Some("pyi")) {
+        return None;
+    }
+    let mut segments: Vec<&str> = relative
+        .parent()
+        .map(|p| p.iter().filter_map(|s| s.to_str()).collect())
+        .unwrap_or_default();
+    if stem != "__init__" {
+        segments.push(stem);
+    }
+    if segments.is_empty() {
+        return None;
+    }
+    Some(segments.join("."))
+}
+
+/// Absolute name of the relative import `module` (`..pkg.mod`) made by `importing_file`
+/// below `root`: one leading dot stands for the file's directory, each further dot
+/// for one directory up. `None` when it climbs above `root` or names `root` itself.
+pub(crate) fn absolute_module(module: &str, importing_file: &Path, root: &Path) -> Option<String> {
+    let rest = module.trim_start_matches('.');
+    let level = module.len() - rest.len();
+    let package = importing_file.parent()?.strip_prefix(root).ok()?;
+    let mut segments: Vec<&str> = package.iter().map(|s| s.to_str()).collect::<Option<_>>()?;
+    segments.truncate(segments.len().checked_sub(level.checked_sub(1)?)?);
+    segments.extend(rest.split('.').filter(|s| !s.is_empty()));
+    (!segments.is_empty()).then(|| segments.join("."))
+}
+
+/// One imported module as seen by the import visitor.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct ImportRecord {
+    pub(crate) module: String,
+    /// Local name bound with `as` (`import numpy as np`, `from x import y as z`).
+    pub(crate) alias: Option<String>,
+    /// 1-based line of the import statement.
+    pub(crate) line: usize,
+    pub(crate) kind: ImportKind,
+    /// Name imported with `from`, which may be an attribute of the module rather than
+    /// a submodule.
+    pub(crate) member: bool,
+}
+
+impl ImportRecord {
+    fn new(module: String, alias: Option<String>, line: usize, kind: ImportKind) -> Self {
+        ImportRecord { module, alias, line, kind, member: false }
+    }
+}
+
+/// `__import__(...)` / `import_module(...)` call whose module is not a string literal.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct DynamicImportSite {
+    pub(crate) line: usize,
+    /// Source of the call, whitespace-collapsed and truncated.
+    pub(crate) expression: String,
+}
+
+/// Data file read through `importlib.resources`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct ResourceRef {
+    /// Dotted package holding the resource; `None` for `__package__` / `__name__`,
+    /// i.e. the package of the file itself.
+    pub(crate) package: Option<String>,
+    /// `/`-separated path of the resource inside the package.
+    pub(crate) resource: String,
+}
+
+/// Imports found in one source file.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct SourceImports {
+    pub(crate) records: Vec<ImportRecord>,
+    pub(crate) dynamic_imports: Vec<DynamicImportSite>,
+    /// Literal paths of scripts run through `subprocess`, `os.system` or `runpy.run_path`.
+    pub(crate) script_paths: Vec<String>,
+    pub(crate) resources: Vec<ResourceRef>,
+    /// The file had syntax errors and `records` comes from the recovered AST.
+    pub(crate) degraded: bool,
+    /// The file was rejected by the parser options and nothing was extracted.
+    pub(crate) failed: bool,
+}
+
+impl SourceImports {
+    fn failed() -> Self {
+        SourceImports { failed: true, ..Default::default() }
+    }
+}
+
+const MAX_SNIPPET_CHARS: usize = 120;
+
+fn snippet(source: &str) -> String {
+    let collapsed = source.split_whitespace().collect::<Vec<_>>().join(" ");
+    if collapsed.chars().count() <= MAX_SNIPPET_CHARS {
+        return collapsed;
+    }
+    let mut truncated: String = collapsed.chars().take(MAX_SNIPPET_CHARS).collect();
+    truncated.push('…');
+    truncated
+}
+
+/// Module argument of an `__import__` / `importlib.import_module` call.
+fn dynamic_import_argument(call: &ExprCall) -> Option<&Expr> {
+    let is_import_call = match call.func.as_ref() {
+        Expr::Name(name) => matches!(name.id.as_str(), "__import__" | "import_module"),
+        Expr::Attribute(attr) => attr.attr.as_str() == "import_module",
+        _ => false,
+    };
+    if !is_import_call {
+        return None;
+    }
+    call.arguments.args.first().or_else(|| {
+        call.arguments
+            .keywords
+            .iter()
+            .find(|k| k.arg.as_ref().is_some_and(|arg| arg.as_str() == "name"))
+            .map(|k| &k.value)
+    })
+}
+
+/// `TYPE_CHECKING` or `typing.TYPE_CHECKING` as an `if` test.
+fn is_type_checking_test(test: &Expr) -> bool {
+    match test {
+        Expr::Name(name) => name.id.as_str() == "TYPE_CHECKING",
+        Expr::Attribute(attr) => attr.attr.as_str() == "TYPE_CHECKING",
+        _ => false,
+    }
+}
+
+/// `module.attr` callee of a call, e.g. `("subprocess", "run")`.
+fn qualified_callee(call: &ExprCall) -> Option<(&str, &str)> {
+    match call.func.as_ref() {
+        Expr::Attribute(attr) => match attr.value.as_ref() {
+            Expr::Name(name) => Some((name.id.as_str(), attr.attr.as_str())),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// `.py` paths named by string literals in the command of a `subprocess.*` /
+/// `os.system` / `os.popen` call, either as argv elements or inside a shell string.
+fn subprocess_script_paths(call: &ExprCall) -> Vec<String> {
+    let is_command_call = matches!(
+        qualified_callee(call),
+        Some(("subprocess", "run" | "call" | "check_call" | "check_output" | "Popen") | ("os", "system" | "popen"))
+    );
+    let Some(command) = call.arguments.args.first().filter(|_| is_command_call) else {
+        return Vec::new();
+    };
+    let literals: Vec<&str> = match command {
+        Expr::StringLiteral(s) => s.value.to_str().split_whitespace().collect(),
+        Expr::List(list) => list.elts.iter().filter_map(|e| e.as_string_literal_expr()).map(|s| s.value.to_str()).collect(),
+        Expr::Tuple(tuple) => tuple.elts.iter().filter_map(|e| e.as_string_literal_expr()).map(|s| s.value.to_str()).collect(),
+        _ => Vec::new(),
+    };
+    literals
+        .into_iter()
+        .map(|token| token.trim_matches(|c| c == '"' || c == '\''))
+        .filter(|token| token.ends_with(".py"))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Dotted name of a callee made of names and attributes (`importlib.resources.files`).
+fn dotted_callee(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Name(name) => Some(name.id.to_string()),
+        Expr::Attribute(attr) => Some(format!("{}.{}", dotted_callee(&attr.value)?, attr.attr)),
+        _ => None,
+    }
+}
+
+fn is_resources_function(callee: &str, function: &str) -> bool {
+    callee.strip_suffix(function).is_some_and(|prefix| prefix.ends_with("resources."))
+}
+
+/// Package argument of an `importlib.resources` call: a literal package name, or
+/// `None` for `__package__` / `__name__`.
+fn resource_package(argument: &Expr) -> Option<Option<String>> {
+    match argument {
+        Expr::StringLiteral(s) => Some(Some(s.value.to_str().to_string())),
+        Expr::Name(name) if matches!(name.id.as_str(), "__package__" | "__name__") => Some(None),
+        _ => None,
+    }
+}
+
+/// Package and path segments of `files(pkg) / "a" / "b"` or `files(pkg).joinpath("a", "b")`.
+fn resource_traversal(expr: &Expr) -> Option<(Option<String>, Vec<&str>)> {
+    match expr {
+        Expr::BinOp(binop) if binop.op == Operator::Div => {
+            let (package, mut segments) = resource_traversal(&binop.left)?;
+            segments.push(binop.right.as_string_literal_expr()?.value.to_str());
+            Some((package, segments))
+        }
+        Expr::Call(call) => match call.func.as_ref() {
+            Expr::Attribute(attr) if attr.attr.as_str() == "joinpath" => {
+                let (package, mut segments) = resource_traversal(&attr.value)?;
+                for argument in call.arguments.args.iter() {
+                    segments.push(argument.as_string_literal_expr()?.value.to_str());
+                }
+                Some((package, segments))
+            }
+            func => {
+                let callee = dotted_callee(func)?;
+                if callee != "files" && !is_resources_function(&callee, "files") {
+                    return None;
+                }
+                Some((resource_package(call.arguments.args.first()?)?, Vec::new()))
+            }
+        },
+        _ => None,
+    }
+}
+
+/// Data file named by an `importlib.resources` expression with literal arguments.
+fn resource_ref(expr: &Expr) -> Option<ResourceRef> {
+    if let Some((package, segments)) = resource_traversal(expr) {
+        return (!segments.is_empty()).then(|| ResourceRef { package, resource: segments.join("/") });
+    }
+    // Legacy API: `resources.read_text(package, "resource")` and friends.
+    let Expr::Call(call) = expr else { return None };
+    let callee = dotted_callee(&call.func)?;
+    let is_legacy = ["read_text", "read_binary", "open_text", "open_binary", "path", "is_resource"]
+        .iter()
+        .any(|function| is_resources_function(&callee, function));
+    match (is_legacy, call.arguments.args.as_ref()) {
+        (true, [package, resource, ..]) => Some(ResourceRef {
+            package: resource_package(package)?,
+            resource: resource.as_string_literal_expr()?.value.to_str().to_string(),
+        }),
+        _ => None,
+    }
+}
+
+/// What a `runpy.run_module` / `runpy.run_path` call with a literal argument runs.
+enum RunpyTarget<'a> {
+    Module(&'a str),
+    Path(&'a str),
+}
+
+fn runpy_target(call: &ExprCall) -> Option<RunpyTarget<'_>> {
+    let function = match call.func.as_ref() {
+        Expr::Name(name) => name.id.as_str(),
+        _ => match qualified_callee(call)? {
+            ("runpy", function) => function,
+            _ => return None,
+        },
+    };
+    let argument = call.arguments.args.first().or_else(|| {
+        let keyword = if function == "run_module" { "mod_name" } else { "path_name" };
+        call.arguments
+            .keywords
+            .iter()
+            .find(|k| k.arg.as_ref().is_some_and(|arg| arg.as_str() == keyword))
+            .map(|k| &k.value)
+    })?;
+    let literal = argument.as_string_literal_expr()?.value.to_str();
+    match function {
+        "run_module" => Some(RunpyTarget::Module(literal)),
+        "run_path" => Some(RunpyTarget::Path(literal)),
+        _ => None,
+    }
+}
+
+pub(crate) fn imports_from_source(source: &str, options: &ParserOptions) -> SourceImports {
+    let mut parse_options = ParseOptions::from(Mode::Module);
+    if let Some((major, minor)) = options.target_version {
+        parse_options = parse_options.with_target_version(PythonVersion { major, minor });
+    }
+    let parsed = ruff_python_parser::parse_unchecked(source, parse_options);
+    let degraded = !parsed.errors().is_empty();
+    if degraded && !options.error_recovery {
+        return SourceImports::failed();
+    }
+    if options.reject_unsupported_syntax && !parsed.unsupported_syntax_errors().is_empty() {
+        return SourceImports::failed();
+    }
+    let Some(parsed) = parsed.try_into_module() else {
+        return SourceImports::failed();
+    };
+    struct ImportVisitor<'s> {
+        source: &'s str,
+        imports: Vec<ImportRecord>,
+        dynamic_imports: Vec<DynamicImportSite>,
+        script_paths: Vec<String>,
+        resources: Vec<ResourceRef>,
+        line_starts: Vec<usize>,
+        /// Enclosing function definitions.
+        function_depth: usize,
+        /// Enclosing `if TYPE_CHECKING:` bodies.
+        type_checking_depth: usize,
+    }
+    impl ImportVisitor<'_> {
+        fn line_at(&self, offset: u32) -> usize {
+            let offset = offset as usize;
+            self.line_starts.partition_point(|&start| start <= offset)
+        }
+
+        fn kind(&self, dynamic: bool) -> ImportKind {
+            if self.type_checking_depth > 0 {
+                ImportKind::TypeChecking
+            } else if dynamic {
+                ImportKind::Dynamic
+            } else if self.function_depth > 0 {
+                ImportKind::Deferred
+            } else {
+                ImportKind::TopLevel
+            }
+        }
+    }
+    impl<'ast> Visitor<'ast> for ImportVisitor<'_> {
+        fn visit_expr(&mut self, expr: &'ast Expr) {
+            // The sub-expressions of a matched traversal are its shorter prefixes.
+            if let Some(resource) = resource_ref(expr) {
+                self.resources.push(resource);
+                return;
+            }
+            if let Expr::Call(call) = expr {
+                match dynamic_import_argument(call) {
+                    Some(Expr::StringLiteral(literal)) => {
+                        let module = literal.value.to_str();
+                        if is_dotted_module_name(module) {
+                            let line = self.line_at(call.range.start().into());
+                            self.imports.push(ImportRecord::new(module.to_string(), None, line, self.kind(true)));
+                        }
+                    }
+                    Some(_) => {
+                        let (start, end): (u32, u32) = (call.range.start().into(), call.range.end().into());
+                        self.dynamic_imports.push(DynamicImportSite {
+                            line: self.line_at(start),
+                            expression: snippet(self.source.get(start as usize..end as usize).unwrap_or_default()),
+                        });
+                    }
+                    None => {}
+                }
+                self.script_paths.extend(subprocess_script_paths(call));
+                match runpy_target(call) {
+                    Some(RunpyTarget::Module(module)) if is_dotted_module_name(module) => {
+                        let line = self.line_at(call.range.start().into());
+                        self.imports.push(ImportRecord::new(module.to_string(), None, line, self.kind(true)));
+                    }
+                    Some(RunpyTarget::Path(path)) => self.script_paths.push(path.to_string()),
+                    _ => {}
+                }
+            }
+            visitor::walk_expr(self, expr);
+        }
+
+        fn visit_stmt(&mut self, stmt: &'ast Stmt) {
+            match stmt {
+                Stmt::FunctionDef(_) => {
+                    self.function_depth += 1;
+                    visitor::walk_stmt(self, stmt);
+                    self.function_depth -= 1;
+                    return;
+                }
+                Stmt::If(if_stmt) if is_type_checking_test(&if_stmt.test) => {
+                    self.type_checking_depth += 1;
+                    self.visit_body(&if_stmt.body);
+                    self.type_checking_depth -= 1;
+                    for clause in &if_stmt.elif_else_clauses {
+                        self.visit_elif_else_clause(clause);
+                    }
+                    return;
+                }
+                Stmt::Import(i) => {
+                    let line = self.line_at(i.range.start().into());
+                    // Recovered statements can carry empty placeholder names.
+                    for a in i.names.iter().filter(|a| !a.name.as_str().is_empty()) {
+                        let alias = a.asname.as_ref().map(|n| n.to_string());
+                        self.imports.push(ImportRecord::new(a.name.to_string(), alias, line, self.kind(false)));
+                    }
+                }
+                Stmt::ImportFrom(i) => {
+                    // Relative imports keep their leading dots (`..pkg`); the scanner
+                    // resolves them against the importing file.
+                    let dots = ".".repeat(i.level as usize);
+                    let m = format!("{}{}", dots, i.module.as_ref().map_or("", |m| m.as_str()));
+                    if !m.is_empty() {
+                        let line = self.line_at(i.range.start().into());
+                        let kind = self.kind(false);
+                        self.imports.push(ImportRecord::new(m.clone(), None, line, kind));
+                        let separator = if m.ends_with('.') { "" } else { "." };
+                        for a in &i.names {
+                            if !a.name.as_str().is_empty() && a.name.as_str() != "*" {
+                                let alias = a.asname.as_ref().map(|n| n.to_string());
+                                let name = format!("{}{}{}", m, separator, a.name);
+                                self.imports.push(ImportRecord { member: true, ..ImportRecord::new(name, alias, line, kind) });
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+            visitor::walk_stmt(self, stmt);
+        }
+    }
+    let line_starts = std::iter::once(0)
+        .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+        .collect();
+    let mut visitor = ImportVisitor {
+        source,
+        imports: Vec::new(),
+        dynamic_imports: Vec::new(),
+        script_paths: Vec::new(),
+        resources: Vec::new(),
+        line_starts,
+        function_depth: 0,
+        type_checking_depth: 0,
+    };
+    let module = parsed.into_syntax();
+    visitor.visit_body(&module.body);
+    SourceImports {
+        records: visitor.imports,
+        dynamic_imports: visitor.dynamic_imports,
+        script_paths: visitor.script_paths,
+        resources: visitor.resources,
+        degraded,
+        failed: false,
+    }
+}
+
+
+
+
+
+
+
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{self, File};
+    use tempfile::tempdir;
+
+    fn modules(source: &str) -> Vec<String> {
+        modules_with(source, &ParserOptions::default())
+    }
+
+    fn modules_with(source: &str, options: &ParserOptions) -> Vec<String> {
+        imports_from_source(source, options).records.into_iter().map(|r| r.module).collect()
+    }
+
+    #[test]
+    fn test_imports_from_source_basic() {
+        let source_code = r#"
+import os
+import sys as system
+from collections import namedtuple
+import my_package.module
+        "#;
+        
+        let imports = modules(source_code);
+        let imports_set: HashSet<_> = imports.into_iter().collect();
+
+        assert!(imports_set.contains("os"));
+        assert!(imports_set.contains("sys"));
+        assert!(imports_set.contains("collections"));
+        assert!(imports_set.contains("collections.namedtuple"));
+        assert!(imports_set.contains("my_package.module")); 
+    }
+
+    #[test]
+    fn test_imports_from_source_records_aliases() {
+        let source_code = r#"
+import numpy as np
+import os.path as osp
+from x import y as z, w
+"#;
+        let aliases: Vec<(String, Option<String>)> = imports_from_source(source_code, &ParserOptions::default())
+            .records
+            .into_iter()
+            .map(|r| (r.module, r.alias))
+            .collect();
+
+        assert!(aliases.contains(&("numpy".to_string(), Some("np".to_string()))));
+        assert!(aliases.contains(&("os.path".to_string(), Some("osp".to_string()))));
+        assert!(aliases.contains(&("x.y".to_string(), Some("z".to_string()))));
+        assert!(aliases.contains(&("x.w".to_string(), None)));
+        assert!(aliases.contains(&("x".to_string(), None)));
+    }
+
+    #[test]
+    fn test_imports_from_source_edge_cases() {
+        let source_relative = "from . import sibling\nfrom ..pkg import mod as m\nfrom .. import *";
+        let imports = modules(source_relative);
+        assert_eq!(imports, vec![".", ".sibling", "..pkg", "..pkg.mod", ".."]);
+
+        let source_wildcard = "from os import *";
+        let imports = modules(source_wildcard);
+        assert!(imports.contains(&"os".to_string()));
+        assert!(!imports.iter().any(|s| s.contains('*')));
+
+        let source_invalid = "import "; 
+        let imports = modules(source_invalid);
+        assert!(imports.is_empty(), "Should return empty list on syntax error");
+    }
+
+    #[test]
+    fn test_imports_from_source_import_kinds() {
+        let source = r#"
+from typing import TYPE_CHECKING
+import importlib
+
+if TYPE_CHECKING:
+    from app.models import Model
+else:
+    import app.fallback
+
+class Handler:
+    import app.settings
+
+    def handle(self):
+        import app.slow
+        if typing.TYPE_CHECKING:
+            import app.types
+        return importlib.import_module("app.plugins.fixed")
+
+runpy.run_module("app.tool")
+"#;
+        let kinds: Vec<(String, ImportKind)> = imports_from_source(source, &ParserOptions::default())
+            .records
+            .into_iter()
+            .map(|r| (r.module, r.kind))
+            .collect();
+        let kind_of = |module: &str| kinds.iter().find(|(m, _)| m == module).map(|(_, kind)| *kind);
+        assert_eq!(kind_of("typing"), Some(ImportKind::TopLevel));
+        assert_eq!(kind_of("app.models.Model"), Some(ImportKind::TypeChecking));
+        assert_eq!(kind_of("app.fallback"), Some(ImportKind::TopLevel));
+        assert_eq!(kind_of("app.settings"), Some(ImportKind::TopLevel));
+        assert_eq!(kind_of("app.slow"), Some(ImportKind::Deferred));
+        assert_eq!(kind_of("app.types"), Some(ImportKind::TypeChecking));
+        assert_eq!(kind_of("app.plugins.fixed"), Some(ImportKind::Dynamic));
+        assert_eq!(kind_of("app.tool"), Some(ImportKind::Dynamic));
+    }
+
+    #[test]
+    fn test_imports_from_source_parser_options() {
+        let newer_syntax = "import os\nmatch os.name:\n    case 'nt':\n        pass\n";
+        assert_eq!(modules(newer_syntax), vec!["os".to_string()]);
+
+        let py39_strict = ParserOptions {
+            target_version: Some((3, 9)),
+            reject_unsupported_syntax: true,
+            ..Default::default()
+        };
+        assert!(modules_with(newer_syntax, &py39_strict).is_empty());
+
+        let py312_strict = ParserOptions { target_version: Some((3, 12)), ..py39_strict };
+        assert_eq!(modules_with(newer_syntax, &py312_strict), vec!["os".to_string()]);
+
+        let broken = "import os\ndef broken(:\n";
+        let strict = ParserOptions { error_recovery: false, ..Default::default() };
+        assert!(modules_with(broken, &strict).is_empty());
+        assert!(imports_from_source(broken, &strict).failed);
+        assert!(modules(broken).contains(&"os".to_string()));
+        assert!(!imports_from_source(broken, &ParserOptions::default()).failed);
+    }
+
+    #[test]
+    fn test_imports_from_source_reports_non_literal_dynamic_imports() {
+        let source = "import importlib\n\nplugin = importlib.import_module(\"app.plugins.fixed\")\n\ndef load(name):\n    return importlib.import_module(\n        f\"app.plugins.{name}\"\n    )\n\nmod = __import__(name=settings.BACKEND)\n";
+        let sites = imports_from_source(source, &ParserOptions::default()).dynamic_imports;
+        assert_eq!(sites, vec![
+            DynamicImportSite { line: 6, expression: "importlib.import_module( f\"app.plugins.{name}\" )".to_string() },
+            DynamicImportSite { line: 10, expression: "__import__(name=settings.BACKEND)".to_string() },
+        ]);
+
+        let long = format!("__import__({})\n", "x".repeat(200));
+        let site = &imports_from_source(&long, &ParserOptions::default()).dynamic_imports[0];
+        assert!(site.expression.ends_with('…'));
+        assert_eq!(site.expression.chars().count(), MAX_SNIPPET_CHARS + 1);
+    }
+
+    #[test]
+    fn test_imports_from_source_finds_subprocess_scripts() {
+        let source = r#"
+import os
+import subprocess
+
+subprocess.run([sys.executable, "scripts/migrate.py", "--all"], check=True)
+subprocess.check_call(("python", "tools/seed.py"))
+os.system("python tools/cleanup.py --force")
+subprocess.run(["ls", "-l"])
+subprocess.run([sys.executable, script])
+print("not_a_command.py")
+"#;
+        let result = imports_from_source(source, &ParserOptions::default());
+        assert_eq!(result.script_paths, vec!["scripts/migrate.py", "tools/seed.py", "tools/cleanup.py"]);
+    }
+
+    #[test]
+    fn test_imports_from_source_treats_runpy_literals_as_imports() {
+        let source = "import runpy\nfrom runpy import run_path\nrunpy.run_module('app.tools.seed', run_name='__main__')\nrunpy.run_module(mod_name='app.tools.migrate')\nrun_path('tools/cleanup.py')\nrunpy.run_module(name)\n";
+        let result = imports_from_source(source, &ParserOptions::default());
+        let modules: Vec<(String, usize)> = result.records.into_iter().map(|r| (r.module, r.line)).collect();
+        assert!(modules.contains(&("app.tools.seed".to_string(), 3)));
+        assert!(modules.contains(&("app.tools.migrate".to_string(), 4)));
+        assert_eq!(result.script_paths, vec!["tools/cleanup.py"]);
+    }
+
+    #[test]
+    fn test_imports_from_source_finds_importlib_resources() {
+        let source = r#"
+from importlib import resources
+from importlib.resources import files
+import importlib.resources
+
+schema = (files("app.data") / "schemas" / "order.json").read_text()
+config = importlib.resources.files(__package__).joinpath("defaults.toml")
+query = resources.read_text("app.sql", "report.sql")
+dynamic = files("app.data") / name
+"#;
+        let resources = imports_from_source(source, &ParserOptions::default()).resources;
+        let resource = |package: Option<&str>, resource: &str| ResourceRef {
+            package: package.map(str::to_string),
+            resource: resource.to_string(),
+        };
+        assert_eq!(resources, vec![
+            resource(Some("app.data"), "schemas/order.json"),
+            resource(None, "defaults.toml"),
+            resource(Some("app.sql"), "report.sql"),
+        ]);
+    }
+
+    #[test]
+    fn test_normalize_for_hash() {
+        let content = b"a\r\nb\rc\r\n\r\n";
+        assert_eq!(&*normalize_for_hash(content, false, false), content);
+        assert_eq!(&*normalize_for_hash(content, true, false), b"a\nb\rc\n\n");
+        assert_eq!(&*normalize_for_hash(content, true, true), b"a\nb\rc");
+        assert_eq!(&*normalize_for_hash(b"a\n\n", false, true), b"a");
+        assert_eq!(&*normalize_for_hash(b"\n", false, true), b"");
+    }
+
+    #[test]
+    fn test_imports_from_source_partial_file() {
+        let source = "import os\nfrom app import models\n\ndef broken(:\n    pass\n\nimport json\n";
+        let result = imports_from_source(source, &ParserOptions::default());
+        assert!(result.degraded);
+        let modules: Vec<String> = result.records.into_iter().map(|r| r.module).collect();
+        assert!(modules.contains(&"os".to_string()));
+        assert!(modules.contains(&"app.models".to_string()));
+        assert!(modules.iter().all(|m| !m.is_empty() && !m.ends_with('.')));
+
+        assert!(!imports_from_source("import os\n", &ParserOptions::default()).degraded);
+    }
+
+    #[test]
+    fn test_find_package_inits() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+
+        let pkg_dir = root.join("pkg");
+        fs::create_dir(&pkg_dir).unwrap();
+        File::create(pkg_dir.join("__init__.py")).unwrap();
+
+        let mut cache = HashMap::new();
+        
+        let inits = find_package_inits_in_path_seq("pkg.submodule", root, &mut cache);
+        
+        assert_eq!(inits.len(), 1);
+        assert_eq!(inits[0], pkg_dir.join("__init__.py"));
+
+        let inits_cached = find_package_inits_in_path_seq("pkg.submodule", root, &mut cache);
+        assert_eq!(inits_cached.len(), 1);
+    }
+
+    #[test]
+    fn test_absolute_module() {
+        let root = Path::new("/repo");
+        let file = root.join("app/core/views.py");
+        assert_eq!(absolute_module(".models", &file, root), Some("app.core.models".to_string()));
+        assert_eq!(absolute_module(".", &file, root), Some("app.core".to_string()));
+        assert_eq!(absolute_module("..utils.text", &file, root), Some("app.utils.text".to_string()));
+        assert_eq!(absolute_module("...", &file, root), None);
+        assert_eq!(absolute_module("....x", &file, root), None);
+        let init = root.join("app/__init__.py");
+        assert_eq!(absolute_module(".views", &init, root), Some("app.views".to_string()));
+        assert_eq!(absolute_module(".x", Path::new("/elsewhere/a.py"), root), None);
+    }
+
+    #[test]
+    fn test_module_root_searches_roots_in_order() {
+        let dir = tempdir().unwrap();
+        let (first, second) = (dir.path().join("first"), dir.path().join("src"));
+        fs::create_dir_all(first.join("ns/a")).unwrap();
+        fs::create_dir_all(second.join("ns/b")).unwrap();
+        fs::create_dir_all(second.join("pkg")).unwrap();
+        File::create(first.join("ns/a/mod.py")).unwrap();
+        File::create(second.join("ns/b/mod.py")).unwrap();
+        File::create(second.join("pkg/__init__.py")).unwrap();
+        File::create(second.join("pkg/typed.pyi")).unwrap();
+        let roots = vec![first.clone(), second.clone()];
+        let exists = |p: &Path| p.exists();
+        let dir_exists = |p: &Path| p.is_dir();
+
+        assert_eq!(module_root("ns.a.mod", &roots, false, &exists, &dir_exists), first);
+        assert_eq!(module_root("ns.b.mod", &roots, false, &exists, &dir_exists), second);
+        assert_eq!(module_root("pkg.CONSTANT", &roots, false, &exists, &dir_exists), second);
+        assert_eq!(module_root("pkg.typed", &roots, true, &exists, &dir_exists), second);
+        assert_eq!(module_root("missing", &roots, false, &exists, &dir_exists), first);
+        // Directories are probed through `dir_exists` too, as in archives.
+        let only_second = |p: &Path| p == second.join("ns");
+        assert_eq!(module_root("ns.c", &roots, false, &exists, &only_second), second);
+    }
+
+    #[test]
+    fn test_resolve_module_file() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+
+        let utils_path = root.join("utils.py");
+        File::create(&utils_path).unwrap();
+
+        let mut cache = HashMap::new();
+        
+        let result = resolve_module_in_project_seq("utils", root, false, &mut cache);
+        assert_eq!(result, Some(utils_path));
+        
+        let result_none = resolve_module_in_project_seq("missing", root, false, &mut cache);
+        assert_eq!(result_none, None);
+    }
+
+    #[test]
+    fn test_resolve_package_dir() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        
+        let pkg_dir = root.join("mypkg");
+        fs::create_dir(&pkg_dir).unwrap();
+        let init_path = pkg_dir.join("__init__.py");
+        File::create(&init_path).unwrap();
+
+        let mut cache = HashMap::new();
+        
+        let result = resolve_module_in_project_seq("mypkg", root, false, &mut cache);
+        assert_eq!(result, Some(init_path));
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*_pb2.py", "app/proto/user_pb2.py"));
+        assert!(glob_match("app/generated/**", "app/generated/a/b.py"));
+        assert!(glob_match("app/*/version.py", "app/core/version.py"));
+        assert!(!glob_match("app/*/version.py", "app/core/sub/version.py"));
+        assert!(glob_match("**/build_info.py", "build_info.py"));
+        assert!(glob_match("app/v?.py", "app/v1.py"));
+        assert!(!glob_match("app/*.py", "lib/x.py"));
+    }
+
+    #[test]
+    fn test_normalize_lexically() {
+        assert_eq!(normalize_lexically(Path::new("/repo/src/../build/./gen")), PathBuf::from("/repo/build/gen"));
+        assert_eq!(normalize_lexically(Path::new("/../x")), PathBuf::from("/x"));
+        assert_eq!(normalize_lexically(Path::new("../a/../../b")), PathBuf::from("../../b"));
+    }
+
+    #[test]
+    fn test_has_volatile_pragma() {
+        assert!(has_volatile_pragma("BUILD = 42\n# depmap: volatile\n"));
+        assert!(!has_volatile_pragma("BUILD = 42  # depmap volatile\n"));
+    }
+
+    #[test]
+    fn test_matches_module_prefix_on_segment_boundaries() {
+        assert!(matches_module_prefix("app", "app"));
+        assert!(matches_module_prefix("app.utils", "app"));
+        assert!(!matches_module_prefix("apps.utils", "app"));
+        assert!(!matches_module_prefix("appengine_sdk", "app"));
+        assert!(matches_module_prefix("app.utils", "app."));
+        assert!(!matches_module_prefix("app", "app."));
+        assert!(matches_module_prefix("app.handlers.x", "app.handlers"));
+    }
+
+    #[test]
+    fn test_module_name_for_path() {
+        let root = Path::new("/project");
+        assert_eq!(module_name_for_path(Path::new("/project/app/utils.py"), root), Some("app.utils".to_string()));
+        assert_eq!(module_name_for_path(Path::new("/project/app/__init__.py"), root), Some("app".to_string()));
+        assert_eq!(module_name_for_path(Path::new("/project/__init__.py"), root), None);
+        assert_eq!(module_name_for_path(Path::new("/elsewhere/app.py"), root), None);
+        assert_eq!(module_name_for_path(Path::new("/project/app/data.json"), root), None);
+    }
+
+    #[test]
+    fn test_stub_only_packages() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+
+        let pkg_dir = root.join("stubbed");
+        fs::create_dir(&pkg_dir).unwrap();
+        File::create(pkg_dir.join("__init__.pyi")).unwrap();
+        File::create(pkg_dir.join("types.pyi")).unwrap();
+
+        let mut init_cache = HashMap::new();
+        let inits = find_package_inits_in_path_seq("stubbed.types", root, &mut init_cache);
+        assert_eq!(inits, vec![pkg_dir.join("__init__.pyi")]);
+
+        let mut cache = HashMap::new();
+        assert_eq!(resolve_module_in_project_seq("stubbed.types", root, false, &mut cache), None);
+
+        let mut cache = HashMap::new();
+        assert_eq!(resolve_module_in_project_seq("stubbed", root, true, &mut cache), Some(pkg_dir.join("__init__.pyi")));
+        assert_eq!(resolve_module_in_project_seq("stubbed.types", root, true, &mut cache), Some(pkg_dir.join("types.pyi")));
+
+        assert!(is_source_file(&pkg_dir.join("__init__.pyi"), false));
+        assert!(!is_source_file(&pkg_dir.join("types.pyi"), false));
+        assert!(is_source_file(&pkg_dir.join("types.pyi"), true));
+    }
+
+    #[test]
+    fn test_load_stdlib_from_file() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("stdlib.txt");
+        fs::write(&file_path, "['os', 'sys', 're']").unwrap();
+        
+        let stdlib = load_stdlib_from_file(&file_path).unwrap();
+        
+        assert!(stdlib.contains("os"));
+        assert!(stdlib.contains("sys"));
+        assert!(stdlib.contains("re"));
+        assert!(!stdlib.contains("requests"));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,116 @@
+#[cfg(feature = "python")]
+use pyo3::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+use crate::{closure_paths, ProjectFile};
+
+/// A project import edge seen for the first time in snapshot `snapshot`.
+#[cfg_attr(feature = "python", pyclass)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NewEdge {
+    /// Index of the snapshot that introduced the edge (never 0).
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub snapshot: usize,
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub source: String,
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub target: String,
+}
+
+/// How a sequence of dependency maps evolved.
+#[cfg_attr(feature = "python", pyclass)]
+#[derive(Clone, Debug, Default)]
+pub struct ChurnReport {
+    /// Path -> number of snapshot transitions in which the file was added, removed or
+    /// changed content. Files that never changed are left out.
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub change_counts: HashMap<String, usize>,
+    /// Entry point -> closure size in each snapshot, `None` where it is not in the map.
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub closure_sizes: HashMap<String, Vec<Option<usize>>>,
+    /// Edges absent from the previous snapshot, by snapshot, source and target.
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub new_edges: Vec<NewEdge>,
+}
+
+/// Compares consecutive `snapshots` (oldest first). Entry points are map keys, as
+/// the files of older snapshots may no longer exist on disk, and the snapshots must
+/// have been built from the same `source_root` for paths to line up.
+pub fn churn_report(snapshots: &[HashMap<String, ProjectFile>], entry_points: &[String]) -> ChurnReport {
+    let mut report = ChurnReport::default();
+
+    for entry_point in entry_points {
+        let sizes = snapshots
+            .iter()
+            .map(|map| map.contains_key(entry_point).then(|| closure_paths(map, entry_point).len()))
+            .collect();
+        report.closure_sizes.insert(entry_point.clone(), sizes);
+    }
+
+    for (index, pair) in snapshots.windows(2).enumerate() {
+        let (before, after) = (&pair[0], &pair[1]);
+        let paths: HashSet<&String> = before.keys().chain(after.keys()).collect();
+        for path in paths {
+            let changed = match (before.get(path), after.get(path)) {
+                (Some(old), Some(new)) => old.hash != new.hash,
+                _ => true,
+            };
+            if changed {
+                *report.change_counts.entry(path.clone()).or_default() += 1;
+            }
+        }
+
+        for (source, info) in after {
+            let previous: HashSet<&String> =
+                before.get(source).map(|old| old.project_imports.iter().collect()).unwrap_or_default();
+            for target in &info.project_imports {
+                if !previous.contains(target) {
+                    report.new_edges.push(NewEdge { snapshot: index + 1, source: source.clone(), target: target.clone() });
+                }
+            }
+        }
+    }
+    report.new_edges.sort_by(|a, b| (a.snapshot, &a.source, &a.target).cmp(&(b.snapshot, &b.source, &b.target)));
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(hash: &str, imports: &[&str]) -> ProjectFile {
+        ProjectFile {
+            hash: hash.to_string(),
+            project_imports: imports.iter().map(|i| i.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_churn_report_across_snapshots() {
+        let first = HashMap::from([
+            ("main.py".to_string(), file("m1", &["util.py"])),
+            ("util.py".to_string(), file("u1", &[])),
+        ]);
+        let second = HashMap::from([
+            ("main.py".to_string(), file("m2", &["util.py", "db.py"])),
+            ("util.py".to_string(), file("u1", &[])),
+            ("db.py".to_string(), file("d1", &["util.py"])),
+        ]);
+        let third = HashMap::from([
+            ("main.py".to_string(), file("m3", &["db.py"])),
+            ("db.py".to_string(), file("d1", &[])),
+        ]);
+
+        let report = churn_report(&[first, second, third], &["main.py".to_string(), "db.py".to_string()]);
+        assert_eq!(report.change_counts["main.py"], 2);
+        assert_eq!(report.change_counts["db.py"], 1);
+        assert_eq!(report.change_counts["util.py"], 1);
+        assert_eq!(report.closure_sizes["main.py"], vec![Some(2), Some(3), Some(2)]);
+        assert_eq!(report.closure_sizes["db.py"], vec![None, Some(2), Some(1)]);
+
+        let edges: Vec<(usize, &str, &str)> =
+            report.new_edges.iter().map(|e| (e.snapshot, e.source.as_str(), e.target.as_str())).collect();
+        assert_eq!(edges, vec![(1, "db.py", "util.py"), (1, "main.py", "db.py")]);
+    }
+}
@@ -0,0 +1,2685 @@
+//! Scanning, import resolution and graph queries behind `py_dependency_mapper`.
+//!
+//! This crate has no Python dependency; the `python` feature only adds the
+//! PyO3 class derives used by the bindings crate.
+
+#[cfg(feature = "python")]
+use pyo3::prelude::*;
+use rayon::prelude::*;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use tracing::debug_span;
+use walkdir::WalkDir;
+mod anonymize;
+mod archive;
+mod audit;
+mod cache;
+mod cycles;
+mod digest;
+mod error;
+mod explain;
+mod export;
+mod frozen;
+mod fsio;
+mod git;
+mod helpers;
+mod history;
+mod license;
+mod metrics;
+mod owners;
+mod pip;
+#[cfg(feature = "python")]
+mod python;
+mod reverse;
+mod runtime;
+mod sbom;
+use helpers::imports_from_source;
+
+pub use anonymize::anonymize_map;
+pub use archive::build_zip;
+pub use audit::{audit_risky_imports, RiskyImport, DEFAULT_RISKY_MODULES};
+pub use cache::build_dependency_map_incremental;
+pub use cycles::{find_import_cycles, topological_order};
+pub use digest::{asset_digest, blast_radius, closure_hash, directory_digests, BlastRadius};
+pub use error::{Error, Result};
+pub use explain::{explain_resolution, ResolutionCandidate, ResolutionExplanation};
+pub use export::{
+    cytoscape_json, docker_context, edge_table, export_graph, export_html_report, DockerContext, EdgeTable, GraphFormat,
+};
+pub use frozen::FrozenMap;
+pub use fsio::IoOptions;
+pub use git::GitCommit;
+pub use history::{churn_report, ChurnReport, NewEdge};
+pub use license::{license_report, LicenseReport};
+pub use metrics::{metrics, Metrics};
+pub use owners::{codeowners_report, OwnershipReport};
+pub use pip::{build_environment_metadata, build_pip_metadata, resolve_package_set, PipMetadata, PipPackageInfo};
+pub use reverse::{get_reverse_dependencies, ReverseIndex};
+pub use runtime::{load_coverage_json, merge_runtime_dependencies};
+pub use sbom::cyclonedx_sbom;
+
+/// Which files project imports may resolve to.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ResolutionScope {
+    /// Anything under `source_root`, even files outside the include paths (which then
+    /// have no map entry of their own).
+    #[default]
+    SourceRoot,
+    /// Only files under the include paths; edges to anything else are dropped.
+    Scanned,
+    /// Anything under `source_root`; resolved files outside the include paths are
+    /// scanned as well, so every edge target has a map entry.
+    Follow,
+}
+
+impl FromStr for ResolutionScope {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "source_root" => Ok(ResolutionScope::SourceRoot),
+            "scanned" => Ok(ResolutionScope::Scanned),
+            "follow" => Ok(ResolutionScope::Follow),
+            other => Err(Error::InvalidInput(format!(
+                "unknown resolution scope '{}', expected 'source_root', 'scanned' or 'follow'",
+                other
+            ))),
+        }
+    }
+}
+
+/// Python container used to expose the import lists of `ProjectFile` and `GraphFileResult`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ImportsContainer {
+    #[default]
+    List,
+    Tuple,
+    FrozenSet,
+}
+
+impl FromStr for ImportsContainer {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "list" => Ok(ImportsContainer::List),
+            "tuple" => Ok(ImportsContainer::Tuple),
+            "frozenset" => Ok(ImportsContainer::FrozenSet),
+            other => Err(Error::InvalidInput(format!(
+                "unknown imports container '{}', expected 'list', 'tuple' or 'frozenset'",
+                other
+            ))),
+        }
+    }
+}
+
+/// How an import runs, from most to least eager.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ImportKind {
+    /// Runs when the importing module is imported.
+    TopLevel,
+    /// Inside a function body; runs when the function is called.
+    Deferred,
+    /// `importlib.import_module`, `__import__` or `runpy.run_module` with a literal name.
+    Dynamic,
+    /// Under `if TYPE_CHECKING:`; only type checkers follow it.
+    TypeChecking,
+}
+
+impl ImportKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ImportKind::TopLevel => "top_level",
+            ImportKind::Deferred => "deferred",
+            ImportKind::Dynamic => "dynamic",
+            ImportKind::TypeChecking => "type_checking",
+        }
+    }
+}
+
+impl FromStr for ImportKind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "top_level" => Ok(ImportKind::TopLevel),
+            "deferred" => Ok(ImportKind::Deferred),
+            "dynamic" => Ok(ImportKind::Dynamic),
+            "type_checking" => Ok(ImportKind::TypeChecking),
+            other => Err(Error::InvalidInput(format!(
+                "unknown import kind '{}', expected 'top_level', 'deferred', 'dynamic' or 'type_checking'",
+                other
+            ))),
+        }
+    }
+}
+
+// The import lists are exposed through getters in `python.rs` so they honour `imports_container`.
+#[cfg_attr(feature = "python", pyclass)]
+#[derive(Clone, Debug, Default)]
+pub struct ProjectFile {
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub hash: String,
+    pub project_imports: Vec<String>,
+    pub stdlib_imports: Vec<String>,
+    pub third_party_imports: Vec<String>,
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub size: u64,
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub mtime_ns: u64,
+    /// Matched `volatile_patterns` or carries a `# depmap: volatile` pragma; aggregate
+    /// digests should not depend on this file's content.
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub volatile: bool,
+    /// The file has syntax errors; its imports come from the parser's error recovery
+    /// and may be incomplete.
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub degraded: bool,
+    /// Files only seen at runtime (see `merge_runtime_dependencies`); also in `project_imports`.
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub dynamic_imports: Vec<String>,
+    /// Statically found `project_imports` that were also exercised at runtime.
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub observed_imports: Vec<String>,
+    /// Local alias -> imported module (`np` -> `numpy`, `z` -> `x.y` for `from x import y as z`).
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub import_aliases: HashMap<String, String>,
+    /// Full dotted names of the third-party imports (`google.cloud.storage`), used to
+    /// tell apart distributions sharing a namespace package.
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub third_party_modules: Vec<String>,
+    /// Project imports that resolve to no file: typos, deleted modules, or relative
+    /// imports above the top-level package. Names imported with `from` are left out,
+    /// as they may be attributes.
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub unresolved_imports: Vec<String>,
+    /// Imported module -> how it is imported, the most eager kind when imported several
+    /// ways. Exposed with string values through a getter in `python.rs`.
+    pub import_kinds: HashMap<String, ImportKind>,
+    /// Import target (project path or top-level module) -> number of import statements
+    /// naming it; the `weight` of `edge_table`.
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub import_statements: HashMap<String, u32>,
+    /// Imports whose module is computed at runtime; see `dynamic_import_warnings`.
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub dynamic_import_warnings: Vec<DynamicImportWarning>,
+    /// Last commit touching the file, with `BuildConfig::git_metadata`.
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub last_commit: Option<GitCommit>,
+    pub imports_container: ImportsContainer,
+    /// The parse the import fields were resolved from; `None` for files not parsed as
+    /// Python source.
+    pub parsed: Option<ParsedSource>,
+}
+
+/// What reading and parsing a file gave, independent of where its imports resolve.
+/// Entries reused from `previous_map` keep it and resolve its imports again, so
+/// they follow modules added, moved or deleted since, and the current options.
+#[derive(Clone, Debug, Default)]
+pub struct ParsedSource {
+    /// `BuildConfig::analysis_options` of the build that parsed the file.
+    options: String,
+    volatile_pragma: bool,
+    /// `None` when the content is not UTF-8.
+    imports: Option<helpers::SourceImports>,
+}
+
+impl ProjectFile {
+    /// Third-party modules for distribution lookup; maps built without module detail
+    /// fall back to the top-level names.
+    pub(crate) fn third_party_module_names(&self) -> &[String] {
+        if self.third_party_modules.is_empty() {
+            &self.third_party_imports
+        } else {
+            &self.third_party_modules
+        }
+    }
+}
+
+#[cfg_attr(feature = "python", pyclass)]
+#[derive(Clone, Debug)]
+pub struct GraphFileResult {
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub hash: String,
+    pub stdlib_imports: Vec<String>,
+    pub third_party_imports: Vec<String>,
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub volatile: bool,
+    /// Minimum number of import hops from the entry point (0 for the entry point itself).
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub depth: u32,
+    /// With provenance: the importer that first pulled the file in, along a shortest
+    /// import chain from the entry point (`None` for the entry point itself).
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub parent: Option<String>,
+    /// With provenance: every file of the closure importing this one directly, sorted.
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub imported_by: Vec<String>,
+    pub imports_container: ImportsContainer,
+}
+
+/// Entry of a map keyed by dotted module name instead of file path.
+#[cfg_attr(feature = "python", pyclass)]
+#[derive(Clone, Debug)]
+pub struct ModuleFile {
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub path: String,
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub hash: String,
+    /// Dotted names of the project modules this module imports.
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub project_imports: Vec<String>,
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub stdlib_imports: Vec<String>,
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub third_party_imports: Vec<String>,
+}
+
+/// Approximate in-Rust footprint of a dependency map.
+#[cfg_attr(feature = "python", pyclass)]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MemoryStats {
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub files: usize,
+    /// Resolved project import edges.
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub edges: usize,
+    /// Heap bytes held by path, hash and import strings.
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub string_bytes: usize,
+    /// Bytes of the entry structs and the vectors holding import strings.
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub struct_bytes: usize,
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub total_bytes: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParserOptions {
+    /// Python version the parser targets, e.g. `(3, 9)`. Defaults to the parser's own default.
+    pub target_version: Option<(u8, u8)>,
+    /// Treat files using syntax newer than `target_version` as unparseable.
+    pub reject_unsupported_syntax: bool,
+    /// Keep the imports of the recovered AST when a file has syntax errors; such files
+    /// are flagged `degraded`. On by default.
+    pub error_recovery: bool,
+}
+
+impl Default for ParserOptions {
+    fn default() -> Self {
+        ParserOptions { target_version: None, reject_unsupported_syntax: false, error_recovery: true }
+    }
+}
+
+impl ParserOptions {
+    /// Parses a `"3.9"`-style version string.
+    pub fn parse_target_version(version: &str) -> Result<(u8, u8)> {
+        let invalid = || Error::InvalidInput(format!("invalid Python version '{}', expected e.g. '3.12'", version));
+        let (major, minor) = version.trim().split_once('.').ok_or_else(invalid)?;
+        Ok((major.parse().map_err(|_| invalid())?, minor.parse().map_err(|_| invalid())?))
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct BuildConfig {
+    pub source_root: PathBuf,
+    /// Further roots project imports resolve against after `source_root`, in order, like
+    /// `sys.path` entries: a `src/` layout or in-repo packages on `PYTHONPATH`. Relative
+    /// ones are relative to `source_root`.
+    pub extra_source_roots: Vec<PathBuf>,
+    pub project_module_prefixes: Vec<String>,
+    /// Named alternatives to `project_module_prefixes`. The scan resolves imports for all
+    /// of them at once and `BuildResult::views` holds one map per set.
+    pub prefix_sets: BTreeMap<String, Vec<String>>,
+    /// Directories or files to scan, relative to `source_root` or absolute; they may
+    /// lie outside `source_root`. Imports always resolve against `source_root`.
+    pub include_paths: Vec<String>,
+    /// Globs (relative to `source_root`) of files and directories the walk of the
+    /// include paths leaves out; a matching directory is not descended into.
+    pub exclude: Vec<String>,
+    /// Also leave out of the walk what git ignores in the repository containing the
+    /// include path, if any.
+    pub respect_gitignore: bool,
+    pub stdlib_list_path: Option<PathBuf>,
+    /// Map from an earlier run; entries whose size and mtime are unchanged keep their hash
+    /// and parse, and have their imports resolved again.
+    pub previous_map: HashMap<String, ProjectFile>,
+    pub imports_container: ImportsContainer,
+    /// Globs (relative to `source_root`) of files marked `volatile`, e.g. generated build info.
+    pub volatile_patterns: Vec<String>,
+    /// TOML or JSON file with an `extra_edges` table merged into the map after scanning.
+    pub extra_edges_path: Option<PathBuf>,
+    /// Scan `.pyi` stubs and let imports resolve to them when no `.py` exists.
+    /// Stub-only `__init__.pyi` package markers are always recognised.
+    pub include_stubs: bool,
+    pub parser: ParserOptions,
+    /// Deep mode: third-party imports resolving into these directories are scanned too,
+    /// and the resolved files become `project_imports` edges.
+    pub site_packages_dirs: Vec<PathBuf>,
+    /// Top-level packages deep mode may follow; empty follows every package.
+    pub deep_packages: Vec<String>,
+    /// Maximum number of hops from a first-party file that deep mode follows.
+    pub deep_max_depth: Option<u32>,
+    pub resolution_scope: ResolutionScope,
+    /// Annotate each file with its last commit in the git repository containing
+    /// `source_root`.
+    pub git_metadata: bool,
+    /// Read sources from this revision (branch, tag, commit) of the git repository at
+    /// `source_root`, which may be bare, instead of from its work tree.
+    pub git_revision: Option<String>,
+    /// Hash content with CRLF line endings converted to LF, so checkouts with
+    /// different line endings get the same hashes.
+    pub normalize_line_endings: bool,
+    /// Hash content without its trailing newlines.
+    pub ignore_trailing_newlines: bool,
+    pub io: IoOptions,
+    /// Imports of these kinds are left out of the map entirely.
+    pub excluded_import_kinds: Vec<ImportKind>,
+    /// Threads hashing and parsing files, one batch at a time; `None` uses one per
+    /// CPU and 1 scans sequentially. Imports are still resolved one file at a time
+    /// in scan order, so the map does not depend on it.
+    pub max_workers: Option<usize>,
+    /// Files larger than this many bytes are skipped without being read, e.g. generated
+    /// modules or vendored data too big to be worth parsing.
+    pub max_file_size: Option<u64>,
+}
+
+impl BuildConfig {
+    /// Whether a file of `size` bytes is over `max_file_size`.
+    fn exceeds_max_file_size(&self, size: u64) -> bool {
+        self.max_file_size.is_some_and(|max| size > max)
+    }
+
+    /// Directory or file an include path stands for: relative to `source_root`, or
+    /// absolute, anywhere on disk.
+    fn include_root(&self, include_path: &str) -> PathBuf {
+        helpers::normalize_lexically(&self.source_root.join(include_path))
+    }
+
+    /// `source_root` followed by `extra_source_roots`.
+    fn source_roots(&self) -> Vec<PathBuf> {
+        std::iter::once(self.source_root.clone())
+            .chain(self.extra_source_roots.iter().map(|root| helpers::normalize_lexically(&self.source_root.join(root))))
+            .collect()
+    }
+
+    fn is_in_include_paths(&self, path: &Path) -> bool {
+        self.include_paths.iter().any(|include| path.starts_with(self.include_root(include)))
+    }
+
+    /// The options hashing and parsing a file depend on; `previous_map` entries
+    /// parsed under others are not reused.
+    fn analysis_options(&self) -> String {
+        format!("{:?}", (self.normalize_line_endings, self.ignore_trailing_newlines, self.parser))
+    }
+
+    /// SHA-256 of `content` after the line ending and trailing newline normalisation.
+    fn content_hash(&self, content: &[u8]) -> String {
+        let content = helpers::normalize_for_hash(content, self.normalize_line_endings, self.ignore_trailing_newlines);
+        hex::encode(Sha256::digest(&content))
+    }
+
+    /// Hash and parse of the `previous_map` entry reusable for a file of this size and
+    /// mtime: one parsed under the same `analysis_options`. Only these are reused; the
+    /// imports are resolved again.
+    fn reusable_entry(&self, key: &str, size: u64, mtime_ns: u64) -> Option<(&str, &ParsedSource)> {
+        let previous = self
+            .previous_map
+            .get(key)
+            .filter(|previous| previous.size == size && previous.mtime_ns == mtime_ns && mtime_ns != 0)?;
+        let parsed = previous.parsed.as_ref().filter(|parsed| parsed.options == self.analysis_options())?;
+        Some((&previous.hash, parsed))
+    }
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct ExtraEdges {
+    #[serde(default)]
+    extra_edges: BTreeMap<String, Vec<String>>,
+}
+
+fn load_extra_edges(path: &Path) -> Result<BTreeMap<String, Vec<String>>> {
+    let content = fs::read_to_string(path)?;
+    let parsed: ExtraEdges = if path.extension().is_some_and(|ext| ext == "json") {
+        serde_json::from_str(&content).map_err(|e| Error::InvalidInput(e.to_string()))?
+    } else {
+        toml::from_str(&content).map_err(|e| Error::InvalidInput(e.to_string()))?
+    };
+    Ok(parsed.extra_edges)
+}
+
+/// Caps applied while collecting a closure; exceeding any of them aborts the query.
+#[derive(Clone, Debug, Default)]
+pub struct GraphLimits {
+    pub max_nodes: Option<usize>,
+    pub max_edges: Option<usize>,
+    pub max_duration: Option<Duration>,
+}
+
+impl GraphLimits {
+    fn check(&self, nodes: usize, edges: usize, started: Instant) -> Result<()> {
+        if let Some(max) = self.max_nodes {
+            if nodes > max {
+                return Err(Error::LimitExceeded(format!(
+                    "dependency graph exceeded max_nodes={} (check the module prefix filters)", max
+                )));
+            }
+        }
+        if let Some(max) = self.max_edges {
+            if edges > max {
+                return Err(Error::LimitExceeded(format!(
+                    "dependency graph exceeded max_edges={} (check the module prefix filters)", max
+                )));
+            }
+        }
+        if let Some(max) = self.max_duration {
+            if started.elapsed() > max {
+                return Err(Error::LimitExceeded(format!(
+                    "dependency graph traversal exceeded {:.3}s", max.as_secs_f64()
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An `__import__` / `importlib.import_module` call whose module is computed at
+/// runtime, so the static graph cannot follow it.
+#[cfg_attr(feature = "python", pyclass)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DynamicImportWarning {
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub path: String,
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub line: usize,
+    /// Source of the call, whitespace-collapsed and truncated.
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub expression: String,
+}
+
+/// Why a file met during the scan was left out of the map, or kept without imports.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SkipReason {
+    /// Not a `.py` source (or a `.pyi` stub while stubs are not scanned).
+    WrongExtension,
+    /// Could not be stat'ed or read.
+    Unreadable,
+    /// Not valid UTF-8; kept in the map without imports.
+    NotUtf8,
+    /// Rejected by the parser options; kept in the map without imports.
+    ParseFailure,
+    /// Matched `exclude` or is git-ignored. For a directory, nothing below it was walked.
+    Excluded,
+    /// Larger than `max_file_size`; not read.
+    TooLarge,
+}
+
+impl SkipReason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SkipReason::WrongExtension => "wrong_extension",
+            SkipReason::Unreadable => "unreadable",
+            SkipReason::NotUtf8 => "not_utf8",
+            SkipReason::ParseFailure => "parse_failure",
+            SkipReason::Excluded => "excluded",
+            SkipReason::TooLarge => "too_large",
+        }
+    }
+}
+
+// `reason` is exposed as a string through a getter in `python.rs`.
+#[cfg_attr(feature = "python", pyclass)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SkippedFile {
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub path: String,
+    pub reason: SkipReason,
+}
+
+/// Files one include path contributes to a scan.
+#[cfg_attr(feature = "python", pyclass)]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct IncludePathPlan {
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub include_path: String,
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub files: usize,
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub bytes: u64,
+}
+
+/// What a build would scan, from the walk and file metadata alone.
+#[cfg_attr(feature = "python", pyclass)]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BuildPlan {
+    /// One entry per include path, in the order given.
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub include_paths: Vec<IncludePathPlan>,
+    /// Files the walk meets but would not scan, sorted by path.
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub excluded: Vec<SkippedFile>,
+    /// Distinct files to scan, overlapping include paths counted once.
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub files: usize,
+    /// Of `files`, those whose `previous_map` entry would be reused.
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub reusable_files: usize,
+    /// Bytes that would be read and hashed: the size of every file not reused.
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub bytes_to_hash: u64,
+}
+
+/// What `build_dependency_map` returns with `detailed=True` in Python.
+#[cfg_attr(feature = "python", pyclass)]
+#[derive(Clone, Debug, Default)]
+pub struct BuildResult {
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub files: HashMap<String, ProjectFile>,
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub reused_files: usize,
+    /// Unresolvable dynamic imports of all files, sorted by path and line.
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub warnings: Vec<DynamicImportWarning>,
+    /// Files met during the scan whose imports are not in the map, sorted by path.
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub skipped: Vec<SkippedFile>,
+    /// Prefix set name -> the map as a build with that set as `project_module_prefixes`
+    /// would give it.
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub views: BTreeMap<String, HashMap<String, ProjectFile>>,
+}
+
+/// Unresolvable dynamic imports recorded in a map, sorted by path and line.
+pub fn dynamic_import_warnings(dependency_map: &HashMap<String, ProjectFile>) -> Vec<DynamicImportWarning> {
+    let mut warnings: Vec<DynamicImportWarning> =
+        dependency_map.values().flat_map(|info| info.dynamic_import_warnings.iter().cloned()).collect();
+    warnings.sort_by(|a, b| (&a.path, a.line).cmp(&(&b.path, b.line)));
+    warnings
+}
+
+struct Scanner<'a> {
+    config: &'a BuildConfig,
+    stdlib_modules: HashSet<String>,
+    project_file_map: HashMap<String, ProjectFile>,
+    module_resolution_cache: HashMap<String, Option<PathBuf>>,
+    package_init_cache: HashMap<String, Vec<PathBuf>>,
+    source_roots: Vec<PathBuf>,
+    /// Module -> source root it resolves under, with `extra_source_roots`.
+    module_root_cache: HashMap<String, PathBuf>,
+    /// Module -> files it resolves to across `site_packages_dirs`.
+    deep_resolution_cache: HashMap<String, Vec<PathBuf>>,
+    /// Files found through imports that still need scanning, with their deep-mode hop
+    /// count: third-party files in deep mode and, under `ResolutionScope::Follow`,
+    /// project files outside the include paths.
+    scan_queue: VecDeque<(PathBuf, u32)>,
+    /// Set when `source_root` is a zip archive or `git_revision` is given; sources are
+    /// then read from it.
+    archive: Option<archive::ArchiveSource>,
+    reused_files: usize,
+    skipped: Vec<SkippedFile>,
+    /// Files of the current batch read ahead by `IoOptions::threads` readers and
+    /// analyzed by the workers, consumed when their imports are resolved.
+    prefetched: HashMap<PathBuf, io::Result<Analysis>>,
+    /// Pool sized by `max_workers`; rayon's global pool when `None`.
+    pool: Option<rayon::ThreadPool>,
+    /// `project_module_prefixes` and every prefix set.
+    project_prefixes: Vec<String>,
+    /// With prefix sets: file -> each project module it imports, with the edges the
+    /// module added.
+    module_edges: HashMap<String, Vec<(String, Vec<String>)>>,
+}
+
+/// A file's hash and parsed imports, computed off the resolving thread.
+struct Analysis {
+    hash: String,
+    parsed: ParsedSource,
+}
+
+fn analyze(config: &BuildConfig, content: &[u8]) -> Analysis {
+    let hash = debug_span!("hash").in_scope(|| config.content_hash(content));
+    let content_str = std::str::from_utf8(content).ok();
+    let parsed = ParsedSource {
+        options: config.analysis_options(),
+        volatile_pragma: content_str.is_some_and(helpers::has_volatile_pragma),
+        imports: content_str
+            .map(|content_str| debug_span!("parse").in_scope(|| imports_from_source(content_str, &config.parser))),
+    };
+    Analysis { hash, parsed }
+}
+
+fn worker_pool(config: &BuildConfig) -> Result<Option<rayon::ThreadPool>> {
+    let Some(workers) = config.max_workers else {
+        return Ok(None);
+    };
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(workers.max(1)).build().map_err(io::Error::other)?;
+    Ok(Some(pool))
+}
+
+/// Whether `path` exists in the scanned sources (the archive, when scanning one).
+fn source_exists(archive: Option<&archive::ArchiveSource>, path: &Path) -> bool {
+    match archive {
+        Some(archive) => archive.contains(path),
+        None => path.exists(),
+    }
+}
+
+/// Whether `path` is a directory of the scanned sources.
+fn source_dir_exists(archive: Option<&archive::ArchiveSource>, path: &Path) -> bool {
+    match archive {
+        Some(archive) => archive.contains_dir(path),
+        None => path.is_dir(),
+    }
+}
+
+/// In-memory sources of `config`: its `git_revision` or its zip archive `source_root`.
+fn open_archive(config: &BuildConfig) -> Result<Option<archive::ArchiveSource>> {
+    if let Some(revision) = &config.git_revision {
+        Ok(Some(archive::ArchiveSource::open_git_revision(&config.source_root, revision)?))
+    } else if config.source_root.is_file() {
+        Ok(Some(archive::ArchiveSource::open(&config.source_root)?))
+    } else {
+        Ok(None)
+    }
+}
+
+fn skipped_file(path: &Path, reason: SkipReason) -> SkippedFile {
+    SkippedFile { path: path.to_string_lossy().into_owned(), reason }
+}
+
+/// Files of one include path the scan starts from, in walk order. Everything else the
+/// walk meets goes to `skipped`. A file named directly is scanned whatever its
+/// extension; symlinked files are followed, symlinked directories are not. Excluded
+/// directories are pruned, and the include path itself is never excluded.
+fn walk_include_path(
+    config: &BuildConfig,
+    archive: Option<&archive::ArchiveSource>,
+    include_path: &str,
+    skipped: &mut Vec<SkippedFile>,
+) -> Vec<PathBuf> {
+    let full_path = config.include_root(include_path);
+    // Archives and git revisions hold no untracked files for git to ignore.
+    let ignore_rules = match archive {
+        None if config.respect_gitignore => git::IgnoreRules::discover(&full_path),
+        _ => None,
+    };
+    let is_excluded = |path: &Path| {
+        let relative = helpers::relative_path_str(path, &config.source_root);
+        config.exclude.iter().any(|pattern| helpers::glob_match(pattern, &relative))
+            || ignore_rules.as_ref().is_some_and(|rules| rules.is_ignored(path))
+    };
+    if let Some(archive) = archive {
+        let exists = |p: &Path| archive.contains(p);
+        let mut files = Vec::new();
+        for path in archive.files_under(&full_path) {
+            // Archives list no directories, so each file is checked with the
+            // directories above it.
+            if path.ancestors().take_while(|dir| *dir != full_path).any(is_excluded) {
+                skipped.push(skipped_file(&path, SkipReason::Excluded));
+            } else if helpers::is_source_file_with(&path, config.include_stubs, &exists) {
+                files.push(path);
+            } else {
+                skipped.push(skipped_file(&path, SkipReason::WrongExtension));
+            }
+        }
+        files
+    } else if full_path.is_dir() {
+        let mut files = Vec::new();
+        let mut excluded = Vec::new();
+        let walker = WalkDir::new(full_path).max_open(config.io.walker_open_dirs()).into_iter().filter_entry(|entry| {
+            let keep = entry.depth() == 0 || !is_excluded(entry.path());
+            if !keep {
+                excluded.push(skipped_file(entry.path(), SkipReason::Excluded));
+            }
+            keep
+        });
+        for entry in walker {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    if let Some(path) = e.path() {
+                        skipped.push(skipped_file(path, SkipReason::Unreadable));
+                    }
+                    continue;
+                }
+            };
+            let path = entry.path();
+            if !path.is_file() {
+                continue;
+            }
+            if helpers::is_source_file(path, config.include_stubs) {
+                files.push(path.to_path_buf());
+            } else {
+                skipped.push(skipped_file(path, SkipReason::WrongExtension));
+            }
+        }
+        skipped.append(&mut excluded);
+        files
+    } else if full_path.is_file() {
+        vec![full_path]
+    } else {
+        Vec::new()
+    }
+}
+
+/// The files `build_dependency_map` starts scanning from under `config`'s include
+/// paths, sorted, applying the same walk rules. Files reached only through imports
+/// (`ResolutionScope::Follow`, deep mode, `extra_edges`) are not part of it.
+pub fn find_source_files(config: &BuildConfig) -> Result<Vec<String>> {
+    let archive = open_archive(config)?;
+    let mut skipped = Vec::new();
+    let mut files: Vec<String> = config
+        .include_paths
+        .iter()
+        .flat_map(|include_path| walk_include_path(config, archive.as_ref(), include_path, &mut skipped))
+        .map(|path| path.to_string_lossy().into_owned())
+        .collect();
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+/// Dry run of `build_dependency_map`: walks the include paths and stats the files
+/// found, without reading or parsing them. Files reached only through imports are
+/// not counted.
+pub fn plan_dependency_map(config: &BuildConfig) -> Result<BuildPlan> {
+    let archive = open_archive(config)?;
+    let mut plan = BuildPlan::default();
+    let mut planned = HashSet::new();
+    for include_path in &config.include_paths {
+        let mut include_plan = IncludePathPlan { include_path: include_path.clone(), ..Default::default() };
+        for path in walk_include_path(config, archive.as_ref(), include_path, &mut plan.excluded) {
+            let stat = match &archive {
+                Some(archive) => archive.read(&path).map(|content| (content.len() as u64, 0)),
+                None => fsio::stat(&path, &config.io).ok(),
+            };
+            let Some((size, mtime_ns)) = stat else {
+                plan.excluded.push(skipped_file(&path, SkipReason::Unreadable));
+                continue;
+            };
+            if config.exceeds_max_file_size(size) {
+                plan.excluded.push(skipped_file(&path, SkipReason::TooLarge));
+                continue;
+            }
+            include_plan.files += 1;
+            include_plan.bytes += size;
+            let key = path.to_string_lossy().into_owned();
+            if config.reusable_entry(&key, size, mtime_ns).is_some() {
+                plan.reusable_files += usize::from(planned.insert(key));
+            } else if planned.insert(key) {
+                plan.bytes_to_hash += size;
+            }
+        }
+        plan.include_paths.push(include_plan);
+    }
+    plan.files = planned.len();
+    plan.excluded.sort_by(|a, b| a.path.cmp(&b.path));
+    plan.excluded.dedup();
+    Ok(plan)
+}
+
+/// Scans the include paths. `source_root` may also be a zip archive (`.zip`, `.egg`,
+/// `.whl`, `.pyz`), whose members are read without extraction, or a git repository
+/// read at `git_revision`. Emits `tracing` spans (`walk`, `prefetch`, `file`, `read`,
+/// `hash`, `parse`, `resolve`) that callers can record with any subscriber; with
+/// `max_workers` other than 1, `hash` and `parse` run on the worker threads.
+pub fn build_dependency_map(config: &BuildConfig) -> Result<BuildResult> {
+    let _build_span = debug_span!("build_dependency_map").entered();
+    let started = Instant::now();
+    let stdlib_modules = if let Some(path) = &config.stdlib_list_path {
+        helpers::load_stdlib_from_file(path)?
+    } else {
+        HashSet::new()
+    };
+    let extra_edges = match &config.extra_edges_path {
+        Some(path) => load_extra_edges(path)?,
+        None => BTreeMap::new(),
+    };
+
+    let mut scanner = Scanner {
+        config,
+        stdlib_modules,
+        project_file_map: HashMap::with_capacity(4096),
+        module_resolution_cache: HashMap::with_capacity(1024),
+        package_init_cache: HashMap::with_capacity(1024),
+        source_roots: config.source_roots(),
+        module_root_cache: HashMap::new(),
+        deep_resolution_cache: HashMap::new(),
+        scan_queue: VecDeque::new(),
+        archive: open_archive(config)?,
+        reused_files: 0,
+        skipped: Vec::new(),
+        prefetched: HashMap::new(),
+        pool: worker_pool(config)?,
+        project_prefixes: config
+            .project_module_prefixes
+            .iter()
+            .chain(config.prefix_sets.values().flatten())
+            .cloned()
+            .collect(),
+        module_edges: HashMap::new(),
+    };
+
+    for path_str in &config.include_paths {
+        let _walk_span = debug_span!("walk", include_path = %path_str).entered();
+        let files = walk_include_path(config, scanner.archive.as_ref(), path_str, &mut scanner.skipped);
+        scanner.scan_files(&files.into_iter().map(|path| (path, 0)).collect::<Vec<_>>());
+    }
+    scanner.merge_extra_edges(&extra_edges)?;
+    while !scanner.scan_queue.is_empty() {
+        let queued: Vec<(PathBuf, u32)> = scanner.scan_queue.drain(..).collect();
+        let _queued_span = debug_span!("queued", files = queued.len()).entered();
+        scanner.scan_files(&queued);
+    }
+
+    if config.git_metadata && scanner.archive.is_none() {
+        let _git_span = debug_span!("git").entered();
+        let paths: Vec<PathBuf> = scanner.project_file_map.keys().map(PathBuf::from).collect();
+        let mut commits = git::last_commits(&config.source_root, &paths)?;
+        for (path, info) in scanner.project_file_map.iter_mut() {
+            info.last_commit = commits.remove(Path::new(path));
+        }
+    }
+
+    let warnings = dynamic_import_warnings(&scanner.project_file_map);
+    let mut skipped = scanner.skipped;
+    // Files skipped by the walk can still join the map later, e.g. as resources.
+    skipped.retain(|s| {
+        matches!(s.reason, SkipReason::NotUtf8 | SkipReason::ParseFailure) || !scanner.project_file_map.contains_key(&s.path)
+    });
+    skipped.sort_by(|a, b| a.path.cmp(&b.path));
+    skipped.dedup();
+    let mut views = BTreeMap::new();
+    if !config.prefix_sets.is_empty() {
+        let _views_span = debug_span!("views").entered();
+        for (name, prefixes) in &config.prefix_sets {
+            views.insert(name.clone(), scanner.prefix_view(prefixes));
+        }
+        scanner.project_file_map = scanner.prefix_view(&config.project_module_prefixes);
+    }
+    metrics::record_build(started.elapsed(), scanner.project_file_map.len(), scanner.reused_files);
+    Ok(BuildResult {
+        files: scanner.project_file_map,
+        reused_files: scanner.reused_files,
+        warnings,
+        skipped,
+        views,
+    })
+}
+
+/// Resolves an entry point given either as a file path or as a dotted module name
+/// (`app.handlers.checkout`), the latter looked up under `source_root` like an import.
+pub fn resolve_entry_point(entry_point: &str, source_root: Option<&Path>) -> Result<String> {
+    match source_root {
+        Some(root) => resolve_entry_point_in(&BuildConfig { source_root: root.to_path_buf(), ..Default::default() }, entry_point),
+        None => Ok(fs::canonicalize(entry_point)?.to_string_lossy().into_owned()),
+    }
+}
+
+/// `resolve_entry_point` with the resolution `build_dependency_map` gives imports
+/// under `config`: its source roots, `include_stubs`, and archive or `git_revision`
+/// sources, whose keys are returned as the build makes them.
+pub fn resolve_entry_point_in(config: &BuildConfig, entry_point: &str) -> Result<String> {
+    let archive = open_archive(config)?;
+    if let Some(archive) = &archive {
+        let path = helpers::normalize_lexically(&config.source_root.join(entry_point));
+        if archive.contains(&path) {
+            return Ok(path.to_string_lossy().into_owned());
+        }
+    }
+    if helpers::is_dotted_module_name(entry_point) && (archive.is_some() || !Path::new(entry_point).exists()) {
+        let exists = |p: &Path| source_exists(archive.as_ref(), p);
+        let dir_exists = |p: &Path| source_dir_exists(archive.as_ref(), p);
+        let roots = config.source_roots();
+        let root = helpers::module_root(entry_point, &roots, config.include_stubs, &exists, &dir_exists);
+        return match helpers::resolve_module_with(entry_point, root, config.include_stubs, &mut HashMap::new(), &exists) {
+            Some(path) if archive.is_some() => Ok(path.to_string_lossy().into_owned()),
+            Some(path) => Ok(fs::canonicalize(path)?.to_string_lossy().into_owned()),
+            None => Err(Error::InvalidInput(format!(
+                "module '{}' could not be resolved under {}",
+                entry_point,
+                roots.iter().map(|root| root.display().to_string()).collect::<Vec<_>>().join(", ")
+            ))),
+        };
+    }
+    Ok(fs::canonicalize(entry_point)?.to_string_lossy().into_owned())
+}
+
+/// Map key of the dotted `module`, resolved like an import with the map's files as the
+/// sources: under `source_root` first, then under any other root holding one of its
+/// files (extra source roots, site-packages), in path order. Works for maps of
+/// archives and git revisions, whose files are not on disk. The map's `paths` are
+/// only listed when the module is not under `source_root`.
+fn module_entry_key(
+    module: &str,
+    source_root: &Path,
+    contains: &dyn Fn(&str) -> bool,
+    paths: &dyn Fn() -> Vec<String>,
+) -> Option<String> {
+    let exists = |p: &Path| contains(p.to_string_lossy().as_ref());
+    let canonical_root = fs::canonicalize(source_root).unwrap_or_else(|_| source_root.to_path_buf());
+    let mut roots = vec![source_root.to_path_buf(), canonical_root];
+    if !roots.iter().any(|root| helpers::module_candidates(module, root, true).iter().any(|c| exists(c))) {
+        let suffixes: Vec<String> = helpers::module_candidates(module, Path::new(""), true)
+            .iter()
+            .map(|candidate| format!("/{}", helpers::relative_path_str(candidate, Path::new(""))))
+            .collect();
+        roots = paths()
+            .iter()
+            .filter_map(|key| suffixes.iter().find_map(|suffix| key.strip_suffix(suffix.as_str())))
+            .map(PathBuf::from)
+            .collect();
+        if roots.is_empty() {
+            return None;
+        }
+        roots.sort();
+        roots.dedup();
+    }
+    let root = helpers::module_root(module, &roots, true, &exists, &|_: &Path| false);
+    helpers::resolve_module_with(module, root, true, &mut HashMap::new(), &exists).map(|path| path.to_string_lossy().into_owned())
+}
+
+/// Key `entry_point` names: itself when in the map, else the module or path it
+/// resolves to, which need not be in the map.
+fn lookup_entry_point(
+    dependency_map: &HashMap<String, ProjectFile>,
+    entry_point: &str,
+    source_root: Option<&Path>,
+) -> Result<String> {
+    lookup_entry_point_with(
+        entry_point,
+        source_root,
+        &|key| dependency_map.contains_key(key),
+        &|| dependency_map.keys().cloned().collect(),
+    )
+}
+
+/// `lookup_entry_point` for a map held elsewhere (e.g. a Python dict), seen through
+/// `contains` and the listing of its `paths`, so callers can find the entry point
+/// before copying out its closure.
+pub fn lookup_entry_point_with(
+    entry_point: &str,
+    source_root: Option<&Path>,
+    contains: &dyn Fn(&str) -> bool,
+    paths: &dyn Fn() -> Vec<String>,
+) -> Result<String> {
+    if contains(entry_point) {
+        return Ok(entry_point.to_string());
+    }
+    if let Some(root) = source_root.filter(|_| helpers::is_dotted_module_name(entry_point)) {
+        if let Some(key) = module_entry_key(entry_point, root, contains, paths) {
+            return Ok(key);
+        }
+    }
+    resolve_entry_point(entry_point, source_root)
+}
+
+/// Closure of `entry_point` with each file's import depth. With `provenance`, each file
+/// also records which files imported it, so the chain that pulled it in can be read
+/// back through `parent`.
+pub fn get_dependency_graph(
+    dependency_map: &HashMap<String, ProjectFile>,
+    entry_point: &str,
+    source_root: Option<&Path>,
+    limits: &GraphLimits,
+    provenance: bool,
+) -> Result<HashMap<String, GraphFileResult>> {
+    let started = Instant::now();
+    let entry_point_path = lookup_entry_point(dependency_map, entry_point, source_root)?;
+    let mut edges = 0;
+
+    // Breadth-first, so the first visit of a file is along a shortest import chain.
+    let mut resolved_file_map = HashMap::with_capacity(64);
+    let mut queue: VecDeque<(String, u32)> = VecDeque::from([(entry_point_path, 0)]);
+    let mut seen: HashSet<String> = HashSet::with_capacity(128);
+    let mut importers: HashMap<String, Vec<String>> = HashMap::new();
+
+    while let Some((current_path, depth)) = queue.pop_front() {
+        if !seen.insert(current_path.clone()) {
+            continue;
+        }
+        if let Some(info) = dependency_map.get(&current_path) {
+            let result = GraphFileResult {
+                hash: info.hash.clone(),
+                stdlib_imports: info.stdlib_imports.clone(),
+                third_party_imports: info.third_party_imports.clone(),
+                volatile: info.volatile,
+                depth,
+                parent: None,
+                imported_by: Vec::new(),
+                imports_container: info.imports_container,
+            };
+            edges += info.project_imports.len();
+            for import_path in &info.project_imports {
+                if provenance {
+                    importers.entry(import_path.clone()).or_default().push(current_path.clone());
+                }
+                queue.push_back((import_path.clone(), depth + 1));
+            }
+            resolved_file_map.insert(current_path, result);
+            limits.check(resolved_file_map.len(), edges, started)?;
+        }
+    }
+    for (path, mut sources) in importers {
+        if let Some(result) = resolved_file_map.get_mut(&path) {
+            // Files are visited breadth-first, so the first importer is on a shortest chain.
+            if result.depth > 0 {
+                result.parent = sources.first().cloned();
+            }
+            sources.sort();
+            sources.dedup();
+            result.imported_by = sources;
+        }
+    }
+    Ok(resolved_file_map)
+}
+
+/// Map key of `entry_point`: the key itself (e.g. an archive member) or the result of
+/// `resolve_entry_point`, which must be part of the map.
+pub(crate) fn entry_key(
+    dependency_map: &HashMap<String, ProjectFile>,
+    entry_point: &str,
+    source_root: Option<&Path>,
+) -> Result<String> {
+    let key = lookup_entry_point(dependency_map, entry_point, source_root)?;
+    if !dependency_map.contains_key(&key) {
+        return Err(Error::InvalidInput(format!(
+            "entry point '{}' is not part of the dependency map",
+            entry_point
+        )));
+    }
+    Ok(key)
+}
+
+/// Paths reachable from `start` (itself included) through `project_imports`.
+pub(crate) fn closure_paths(dependency_map: &HashMap<String, ProjectFile>, start: &str) -> HashSet<String> {
+    let mut seen = HashSet::new();
+    let mut stack = vec![start.to_string()];
+    while let Some(current) = stack.pop() {
+        if let Some(info) = dependency_map.get(&current) {
+            if seen.insert(current) {
+                stack.extend(info.project_imports.iter().cloned());
+            }
+        }
+    }
+    seen
+}
+
+/// Top-level third-party packages imported by the files of `entry_point`'s closure,
+/// sorted: what its deployment needs installed besides the project's own files.
+pub fn get_external_dependencies(
+    dependency_map: &HashMap<String, ProjectFile>,
+    entry_point: &str,
+    source_root: Option<&Path>,
+) -> Result<Vec<String>> {
+    let entry_key = entry_key(dependency_map, entry_point, source_root)?;
+    let packages: BTreeSet<&String> = closure_paths(dependency_map, &entry_key)
+        .iter()
+        .flat_map(|path| &dependency_map[path].third_party_imports)
+        .collect();
+    Ok(packages.into_iter().cloned().collect())
+}
+
+pub fn find_dependents(
+    dependency_map: &HashMap<String, ProjectFile>,
+    changed_file_paths: Vec<String>,
+) -> HashSet<String> {
+
+    let mut reverse_graph: HashMap<&str, Vec<&str>> = HashMap::new();
+
+    for (importer, project_file) in dependency_map {
+        for dependency in &project_file.project_imports {
+            reverse_graph
+                .entry(dependency.as_str())
+                .or_default()
+                .push(importer.as_str());
+        }
+    }
+
+    let mut dependents = HashSet::new();
+
+    let mut stack = changed_file_paths;
+
+    while let Some(current_file) = stack.pop() {
+        if let Some(consumers) = reverse_graph.get(current_file.as_str()) {
+            for consumer in consumers {
+                if dependents.insert(consumer.to_string()) {
+                    stack.push(consumer.to_string());
+                }
+            }
+        }
+    }
+
+    dependents
+}
+
+/// Content hash -> sorted paths of the files with that content.
+pub fn hash_index(dependency_map: &HashMap<String, ProjectFile>) -> HashMap<String, Vec<String>> {
+    let mut index: HashMap<String, Vec<String>> = HashMap::with_capacity(dependency_map.len());
+    for (path, info) in dependency_map {
+        index.entry(info.hash.clone()).or_default().push(path.clone());
+    }
+    for paths in index.values_mut() {
+        paths.sort();
+    }
+    index
+}
+
+/// Sorted paths of the files whose content hash is `hash`.
+pub fn files_by_hash(dependency_map: &HashMap<String, ProjectFile>, hash: &str) -> Vec<String> {
+    let mut paths: Vec<String> = dependency_map
+        .iter()
+        .filter(|(_, info)| info.hash.eq_ignore_ascii_case(hash))
+        .map(|(path, _)| path.clone())
+        .collect();
+    paths.sort();
+    paths
+}
+
+pub fn memory_stats(dependency_map: &HashMap<String, ProjectFile>) -> MemoryStats {
+    fn strings_bytes(items: &[String]) -> usize {
+        items.iter().map(|s| s.capacity()).sum()
+    }
+    fn vec_bytes(items: &[String]) -> usize {
+        items.len() * std::mem::size_of::<String>()
+    }
+
+    let mut stats = MemoryStats { files: dependency_map.len(), ..Default::default() };
+    for (path, info) in dependency_map {
+        stats.edges += info.project_imports.len();
+        stats.string_bytes += path.capacity()
+            + info.hash.capacity()
+            + strings_bytes(&info.project_imports)
+            + strings_bytes(&info.stdlib_imports)
+            + strings_bytes(&info.third_party_imports)
+            + strings_bytes(&info.third_party_modules)
+            + strings_bytes(&info.unresolved_imports);
+        stats.struct_bytes += std::mem::size_of::<String>()
+            + std::mem::size_of::<ProjectFile>()
+            + vec_bytes(&info.project_imports)
+            + vec_bytes(&info.stdlib_imports)
+            + vec_bytes(&info.third_party_imports)
+            + vec_bytes(&info.third_party_modules)
+            + vec_bytes(&info.unresolved_imports);
+    }
+    stats.total_bytes = stats.string_bytes + stats.struct_bytes;
+    stats
+}
+
+/// Re-keys a dependency map by dotted module name relative to `source_root`.
+/// Files that do not map to a module name (outside the root, non-Python) are left out.
+pub fn to_module_map(
+    dependency_map: &HashMap<String, ProjectFile>,
+    source_root: &Path,
+) -> HashMap<String, ModuleFile> {
+    let module_name = |path: &str| helpers::module_name_for_path(Path::new(path), source_root);
+
+    let mut module_map = HashMap::with_capacity(dependency_map.len());
+    for (path, info) in dependency_map {
+        let Some(name) = module_name(path) else { continue };
+        let mut project_imports: Vec<String> = info
+            .project_imports
+            .iter()
+            .filter_map(|p| module_name(p))
+            .collect();
+        project_imports.sort();
+        project_imports.dedup();
+        module_map.insert(name, ModuleFile {
+            path: path.clone(),
+            hash: info.hash.clone(),
+            project_imports,
+            stdlib_imports: info.stdlib_imports.clone(),
+            third_party_imports: info.third_party_imports.clone(),
+        });
+    }
+    module_map
+}
+
+impl Scanner<'_> {
+    fn skip(&mut self, path: &Path, reason: SkipReason) {
+        self.skipped.push(skipped_file(path, reason));
+    }
+
+    /// Scans `files` (with their deep-mode hop counts) batch by batch, in order.
+    fn scan_files(&mut self, files: &[(PathBuf, u32)]) {
+        let mut rest = files;
+        while !rest.is_empty() {
+            let (batch, next) = rest.split_at(self.prefetch(rest));
+            for (path, depth) in batch {
+                self.parse_file_imports(path, *depth);
+            }
+            self.prefetched.clear();
+            rest = next;
+        }
+    }
+
+    fn stat(&self, path: &Path) -> Option<(u64, u64)> {
+        match &self.archive {
+            Some(archive) => archive.read(path).map(|content| (content.len() as u64, 0)),
+            None => fsio::stat(path, &self.config.io).ok(),
+        }
+    }
+
+    fn read_source(&self, path: &Path) -> io::Result<Vec<u8>> {
+        match &self.archive {
+            Some(archive) => archive.read(path).map(<[u8]>::to_vec).ok_or_else(|| io::ErrorKind::NotFound.into()),
+            None => fsio::read(path, &self.config.io),
+        }
+    }
+
+    /// Reads ahead and analyzes the next batch of `files`: up to `IoOptions::batch_size`
+    /// files and, at least one file aside, `max_buffered_bytes`, skipping files reusable
+    /// from `previous_map`. Returns the batch length; the caller resolves the batch
+    /// before asking for the next one, which bounds the bytes held in memory.
+    fn prefetch(&mut self, files: &[(PathBuf, u32)]) -> usize {
+        let io = &self.config.io;
+        let batch_size = io.batch_size.clamp(1, files.len().max(1));
+        let readers = if self.archive.is_some() { 1 } else { io.reader_threads() };
+        if readers <= 1 && self.config.max_workers == Some(1) {
+            return batch_size;
+        }
+        let _prefetch_span = debug_span!("prefetch").entered();
+        let mut to_read = Vec::new();
+        let mut buffered: u64 = 0;
+        let mut taken = 0;
+        for (path, _) in &files[..batch_size] {
+            let key = path.to_string_lossy();
+            let stat = self.stat(path);
+            let skipped = stat.is_some_and(|(size, mtime_ns)| {
+                self.config.exceeds_max_file_size(size) || self.config.reusable_entry(&key, size, mtime_ns).is_some()
+            });
+            if !skipped && !self.project_file_map.contains_key(key.as_ref()) {
+                let size = stat.map_or(0, |(size, _)| size);
+                if !to_read.is_empty() && io.max_buffered_bytes.is_some_and(|max| buffered + size > max) {
+                    break;
+                }
+                buffered += size;
+                to_read.push(path.clone());
+            }
+            taken += 1;
+        }
+        let contents = if readers > 1 {
+            fsio::read_all(&to_read, io)
+        } else {
+            to_read.iter().map(|path| self.read_source(path)).collect()
+        };
+        let config = self.config;
+        let analyze_all = || -> Vec<io::Result<Analysis>> {
+            contents.into_par_iter().map(|content| content.map(|content| analyze(config, &content))).collect()
+        };
+        let analyses = match &self.pool {
+            Some(pool) => pool.install(analyze_all),
+            None => analyze_all(),
+        };
+        self.prefetched.extend(to_read.into_iter().zip(analyses));
+        taken
+    }
+
+    fn is_package_dir(&self, dir: &Path) -> bool {
+        source_dir_exists(self.archive.as_ref(), dir)
+    }
+
+    fn matches_volatile_pattern(&self, path: &Path) -> bool {
+        if self.config.volatile_patterns.is_empty() {
+            return false;
+        }
+        let relative = helpers::relative_path_str(path, &self.config.source_root);
+        self.config.volatile_patterns.iter().any(|pattern| helpers::glob_match(pattern, &relative))
+    }
+
+    /// Adds the declared edges, scanning targets that were not part of the include paths.
+    fn merge_extra_edges(&mut self, extra_edges: &BTreeMap<String, Vec<String>>) -> Result<()> {
+        for (source, targets) in extra_edges {
+            let source_key =
+                helpers::normalize_lexically(&self.config.source_root.join(source)).to_string_lossy().into_owned();
+            if !self.project_file_map.contains_key(&source_key) {
+                return Err(Error::InvalidInput(format!(
+                    "extra_edges source '{}' is not part of the scanned files", source
+                )));
+            }
+            for target in targets {
+                let target_path = helpers::normalize_lexically(&self.config.source_root.join(target));
+                let archive = self.archive.as_ref();
+                if !source_exists(archive, &target_path) || source_dir_exists(archive, &target_path) {
+                    return Err(Error::InvalidInput(format!(
+                        "extra_edges target '{}' (from '{}') does not exist", target, source
+                    )));
+                }
+                self.parse_file_imports(&target_path, 0);
+                let target_key = target_path.to_string_lossy().into_owned();
+                if let Some(entry) = self.project_file_map.get_mut(&source_key) {
+                    if !entry.project_imports.contains(&target_key) {
+                        entry.project_imports.push(target_key);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn follows_deep(&self, base_module: &str, depth: u32) -> bool {
+        !self.config.site_packages_dirs.is_empty()
+            && self.config.deep_max_depth.is_none_or(|max| depth < max)
+            && (self.config.deep_packages.is_empty() || self.config.deep_packages.iter().any(|p| p == base_module))
+    }
+
+    /// Applies `resolution_scope` to a resolved project import: whether to keep the
+    /// edge, queueing the target for scanning under `ResolutionScope::Follow`.
+    fn admit_project_import(&mut self, target: &Path, depth: u32) -> bool {
+        match self.config.resolution_scope {
+            ResolutionScope::SourceRoot => true,
+            ResolutionScope::Scanned => self.config.is_in_include_paths(target),
+            ResolutionScope::Follow => {
+                if !self.config.is_in_include_paths(target) && !self.project_file_map.contains_key(target.to_string_lossy().as_ref()) {
+                    self.scan_queue.push_back((target.to_path_buf(), depth));
+                }
+                true
+            }
+        }
+    }
+
+    /// Script run through `subprocess` or `runpy.run_path`, looked up relative to
+    /// `source_root` and then to the directory of the file running it. A directory
+    /// stands for its `__main__.py`.
+    fn resolve_script(&self, script: &str, from: &Path) -> Option<PathBuf> {
+        let from_dir = from.parent().unwrap_or(&self.config.source_root);
+        [self.config.source_root.join(script), from_dir.join(script)]
+            .into_iter()
+            .map(|candidate| if candidate.is_dir() { candidate.join("__main__.py") } else { candidate })
+            .find(|candidate| source_exists(self.archive.as_ref(), candidate))
+    }
+
+    /// Adds a project data file read through `importlib.resources` to the map (hashed,
+    /// without imports) and returns its key. Resources of non-project packages are
+    /// ignored.
+    fn add_resource_file(&mut self, resource: &helpers::ResourceRef, from: &Path) -> Option<String> {
+        let package_dir = match &resource.package {
+            Some(package) => {
+                if !self.project_prefixes.iter().any(|prefix| helpers::matches_module_prefix(package, prefix)) {
+                    return None;
+                }
+                self.config.source_root.join(package.replace('.', "/"))
+            }
+            None => from.parent()?.to_path_buf(),
+        };
+        let path = package_dir.join(&resource.resource);
+        let key = path.to_string_lossy().into_owned();
+        if self.project_file_map.contains_key(&key) {
+            return Some(key);
+        }
+        let content = match &self.archive {
+            Some(archive) => archive.read(&path).map(<[u8]>::to_vec),
+            None => fsio::read(&path, &self.config.io).ok(),
+        }?;
+        let (size, mtime_ns) = match &self.archive {
+            Some(_) => (content.len() as u64, 0),
+            None => fsio::stat(&path, &self.config.io).ok()?,
+        };
+        self.project_file_map.insert(key.clone(), ProjectFile {
+            hash: self.config.content_hash(&content),
+            size,
+            mtime_ns,
+            volatile: self.matches_volatile_pattern(&path),
+            imports_container: self.config.imports_container,
+            ..Default::default()
+        });
+        Some(key)
+    }
+
+    /// Source root `module` resolves under; see `helpers::module_root`.
+    fn module_root(&mut self, module: &str) -> PathBuf {
+        if self.source_roots.len() == 1 {
+            return self.config.source_root.clone();
+        }
+        if let Some(root) = self.module_root_cache.get(module) {
+            return root.clone();
+        }
+        let archive = self.archive.as_ref();
+        let exists = |p: &Path| source_exists(archive, p);
+        let dir_exists = |p: &Path| source_dir_exists(archive, p);
+        let root = helpers::module_root(module, &self.source_roots, self.config.include_stubs, &exists, &dir_exists)
+            .to_path_buf();
+        self.module_root_cache.insert(module.to_string(), root.clone());
+        root
+    }
+
+    /// Innermost source root holding `path`.
+    fn file_root(&self, path: &Path) -> &Path {
+        self.source_roots
+            .iter()
+            .filter(|root| path.starts_with(root))
+            .max_by_key(|root| root.components().count())
+            .map_or(&self.config.source_root, PathBuf::as_path)
+    }
+
+    /// Files `module` resolves to in the first site-packages directory that has it.
+    fn resolve_deep_module(&mut self, module: &str) -> Vec<PathBuf> {
+        if let Some(cached) = self.deep_resolution_cache.get(module) {
+            return cached.clone();
+        }
+        let mut resolved = Vec::new();
+        for dir in &self.config.site_packages_dirs {
+            if let Some(p) = helpers::resolve_module_in_project_seq(module, dir, self.config.include_stubs, &mut HashMap::new()) {
+                resolved.extend(helpers::find_package_inits_in_path_seq(module, dir, &mut HashMap::new()));
+                resolved.push(p);
+                break;
+            }
+        }
+        self.deep_resolution_cache.insert(module.to_string(), resolved.clone());
+        resolved
+    }
+
+    /// `depth` is the number of deep-mode hops from a first-party file (0 for those).
+    fn parse_file_imports(&mut self, path: &Path, depth: u32) {
+        let path_str = path.to_string_lossy().into_owned();
+        if self.project_file_map.contains_key(&path_str) { return; }
+        let _file_span = debug_span!("file", path = %path_str).entered();
+
+        let (size, mtime_ns) = match self.stat(path) {
+            Some(stat) => stat,
+            None => {
+                self.skip(path, SkipReason::Unreadable);
+                return;
+            }
+        };
+        if self.config.exceeds_max_file_size(size) {
+            self.skip(path, SkipReason::TooLarge);
+            return;
+        }
+
+        let (hash, parsed) = match self.config.reusable_entry(&path_str, size, mtime_ns) {
+            Some((hash, parsed)) => {
+                let reused = (hash.to_string(), parsed.clone());
+                self.reused_files += 1;
+                reused
+            }
+            None => {
+                let analysis = match self.prefetched.remove(path) {
+                    Some(analysis) => analysis,
+                    None => debug_span!("read")
+                        .in_scope(|| self.read_source(path))
+                        .map(|content| analyze(self.config, &content)),
+                };
+                let Ok(Analysis { hash, parsed }) = analysis else {
+                    self.skip(path, SkipReason::Unreadable);
+                    return;
+                };
+                (hash, parsed)
+            }
+        };
+
+        let mut resolved_project_imports = HashSet::new();
+        let mut stdlib_imports = HashSet::new();
+        let mut third_party_imports = HashSet::new();
+        let mut third_party_modules = HashSet::new();
+        let mut unresolved_imports = HashSet::new();
+        let mut volatile = self.matches_volatile_pattern(path);
+        let mut import_aliases = HashMap::new();
+        let mut import_kinds: HashMap<String, ImportKind> = HashMap::new();
+        let mut degraded = false;
+        let mut dynamic_import_warnings = Vec::new();
+        let mut module_edges = Vec::new();
+        let mut statement_lines: HashMap<String, HashSet<usize>> = HashMap::new();
+
+        if let Some(source_imports) = &parsed.imports {
+            volatile |= parsed.volatile_pragma;
+            if source_imports.failed {
+                self.skip(path, SkipReason::ParseFailure);
+            }
+            degraded = source_imports.degraded;
+            dynamic_import_warnings = source_imports
+                .dynamic_imports
+                .iter()
+                .map(|site| DynamicImportWarning { path: path_str.clone(), line: site.line, expression: site.expression.clone() })
+                .collect();
+            let _resolve_span = debug_span!("resolve").entered();
+            let config = self.config;
+            let site_packages_dir = config.site_packages_dirs.iter().find(|dir| path.starts_with(dir));
+            for record in &source_imports.records {
+                if config.excluded_import_kinds.contains(&record.kind) {
+                    continue;
+                }
+                // Relative imports name modules below the directory holding the file:
+                // project modules, or in deep mode modules of its site-packages.
+                let relative = record.module.starts_with('.');
+                let module = if relative {
+                    let root = site_packages_dir.map_or_else(|| self.file_root(path), PathBuf::as_path);
+                    match helpers::absolute_module(&record.module, path, root) {
+                        Some(module) => module,
+                        None => {
+                            if !record.member {
+                                unresolved_imports.insert(record.module.clone());
+                            }
+                            continue;
+                        }
+                    }
+                } else {
+                    record.module.clone()
+                };
+                if let Some(alias) = &record.alias {
+                    import_aliases.insert(alias.clone(), module.clone());
+                }
+                let kind = import_kinds.entry(module.clone()).or_insert(record.kind);
+                *kind = (*kind).min(record.kind);
+                let base_module = module.split('.').next().unwrap_or(&module);
+
+                if (relative && site_packages_dir.is_none())
+                    || self.project_prefixes.iter().any(|prefix| helpers::matches_module_prefix(&module, prefix))
+                {
+                    let root = self.module_root(&module);
+                    let archive = self.archive.as_ref();
+                    let exists = |p: &Path| source_exists(archive, p);
+                    let mut targets = helpers::find_package_inits_with(&module, &root, &mut self.package_init_cache, &exists);
+                    let module_file = helpers::resolve_module_with(&module, &root, self.config.include_stubs, &mut self.module_resolution_cache, &exists);
+                    // A namespace package has a directory but no file of its own.
+                    if module_file.is_none() && !record.member && !self.is_package_dir(&root.join(module.replace('.', "/"))) {
+                        unresolved_imports.insert(module.clone());
+                    }
+                    targets.extend(module_file);
+                    let mut admitted = Vec::new();
+                    for p in targets {
+                        if self.admit_project_import(&p, depth) {
+                            admitted.push(p.to_string_lossy().into_owned());
+                        }
+                    }
+                    for target in &admitted {
+                        statement_lines.entry(target.clone()).or_default().insert(record.line);
+                    }
+                    resolved_project_imports.extend(admitted.iter().cloned());
+                    if !self.config.prefix_sets.is_empty() {
+                        // Relative imports keep their dots here; every view keeps them.
+                        module_edges.push((if relative { record.module.clone() } else { module.clone() }, admitted));
+                    }
+                } else if self.stdlib_modules.contains(base_module) {
+                    stdlib_imports.insert(base_module.to_string());
+                    statement_lines.entry(base_module.to_string()).or_default().insert(record.line);
+                } else {
+                    if self.follows_deep(base_module, depth) {
+                        for p in self.resolve_deep_module(&module) {
+                            let key = p.to_string_lossy().into_owned();
+                            if !self.project_file_map.contains_key(&key) {
+                                self.scan_queue.push_back((p, depth + 1));
+                            }
+                            statement_lines.entry(key.clone()).or_default().insert(record.line);
+                            resolved_project_imports.insert(key);
+                        }
+                    }
+                    statement_lines.entry(base_module.to_string()).or_default().insert(record.line);
+                    third_party_imports.insert(base_module.to_string());
+                    third_party_modules.insert(module.clone());
+                }
+            }
+            for resource in &source_imports.resources {
+                if let Some(target) = self.add_resource_file(resource, path) {
+                    resolved_project_imports.insert(target);
+                }
+            }
+            for script in &source_imports.script_paths {
+                if let Some(target) = self.resolve_script(script, path) {
+                    if self.admit_project_import(&target, depth) {
+                        resolved_project_imports.insert(target.to_string_lossy().into_owned());
+                    }
+                }
+            }
+        } else {
+            self.skip(path, SkipReason::NotUtf8);
+        }
+        self.project_file_map.insert(path_str, ProjectFile {
+            hash,
+            project_imports: resolved_project_imports.into_iter().collect(),
+            stdlib_imports: stdlib_imports.into_iter().collect(),
+            third_party_imports: third_party_imports.into_iter().collect(),
+            size,
+            mtime_ns,
+            volatile,
+            degraded,
+            dynamic_imports: Vec::new(),
+            observed_imports: Vec::new(),
+            import_aliases,
+            third_party_modules: third_party_modules.into_iter().collect(),
+            unresolved_imports: unresolved_imports.into_iter().collect(),
+            import_kinds,
+            import_statements: statement_lines.into_iter().map(|(target, lines)| (target, lines.len() as u32)).collect(),
+            dynamic_import_warnings,
+            last_commit: None,
+            imports_container: self.config.imports_container,
+            parsed: Some(parsed),
+        });
+        if !module_edges.is_empty() {
+            self.module_edges.insert(path.to_string_lossy().into_owned(), module_edges);
+        }
+    }
+
+    /// The scanned map as a build with `prefixes` as `project_module_prefixes` would
+    /// give it: edges added only by modules outside `prefixes` are dropped, and those
+    /// modules are listed as standard library or third-party imports instead.
+    fn prefix_view(&self, prefixes: &[String]) -> HashMap<String, ProjectFile> {
+        self.project_file_map
+            .iter()
+            .map(|(path, info)| {
+                let mut view = info.clone();
+                if let Some(modules) = self.module_edges.get(path) {
+                    let (kept, dropped): (Vec<_>, Vec<_>) = modules
+                        .iter()
+                        .partition(|(module, _)| {
+                            module.starts_with('.') || prefixes.iter().any(|prefix| helpers::matches_module_prefix(module, prefix))
+                        });
+                    let kept_targets: HashSet<&String> = kept.iter().flat_map(|(_, targets)| targets).collect();
+                    let dropped_targets: HashSet<&String> = dropped
+                        .iter()
+                        .flat_map(|(_, targets)| targets)
+                        .filter(|target| !kept_targets.contains(target))
+                        .collect();
+                    view.project_imports.retain(|target| !dropped_targets.contains(target));
+                    for (module, _) in dropped {
+                        let base_module = module.split('.').next().unwrap_or(module).to_string();
+                        if self.stdlib_modules.contains(&base_module) {
+                            push_unique(&mut view.stdlib_imports, base_module);
+                        } else {
+                            push_unique(&mut view.third_party_imports, base_module);
+                            push_unique(&mut view.third_party_modules, module.clone());
+                        }
+                    }
+                }
+                (path.clone(), view)
+            })
+            .collect()
+    }
+}
+
+fn push_unique(items: &mut Vec<String>, item: String) {
+    if !items.contains(&item) {
+        items.push(item);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn mock_file(imports: Vec<&str>) -> ProjectFile {
+        ProjectFile {
+            hash: "dummy".to_string(),
+            project_imports: imports.iter().map(|s| s.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_find_dependents_logic() {
+        let mut map = HashMap::new();
+
+        map.insert("file_a.py".to_string(), mock_file(vec!["file_b.py"]));
+        map.insert("file_b.py".to_string(), mock_file(vec![]));
+
+        map.insert("file_c.py".to_string(), mock_file(vec!["file_d.py"]));
+        map.insert("file_d.py".to_string(), mock_file(vec![]));
+
+        let result = find_dependents(&map, vec![
+            "file_b.py".to_string(),
+            "file_d.py".to_string()
+        ]);
+
+        assert!(result.contains("file_a.py"));
+        assert!(result.contains("file_c.py"));
+        assert_eq!(result.len(), 2);
+    }
+
+    #[test]
+    fn test_dependency_graph_reports_minimum_depth() {
+        let dir = tempdir().unwrap();
+        let entry = dir.path().join("main.py");
+        fs::write(&entry, "").unwrap();
+        let entry = fs::canonicalize(entry).unwrap().to_string_lossy().into_owned();
+
+        // main -> a -> b, and main -> b directly: b must be reported at depth 1.
+        let mut map = HashMap::new();
+        map.insert(entry.clone(), mock_file(vec!["a.py", "b.py"]));
+        map.insert("a.py".to_string(), mock_file(vec!["b.py", "c.py"]));
+        map.insert("b.py".to_string(), mock_file(vec![]));
+        map.insert("c.py".to_string(), mock_file(vec![]));
+
+        let graph = get_dependency_graph(&map, &entry, None, &GraphLimits::default(), false).unwrap();
+        assert_eq!(graph[&entry].depth, 0);
+        assert_eq!(graph["a.py"].depth, 1);
+        assert_eq!(graph["b.py"].depth, 1);
+        assert_eq!(graph["c.py"].depth, 2);
+
+        let limits = GraphLimits { max_nodes: Some(3), ..Default::default() };
+        assert!(matches!(
+            get_dependency_graph(&map, &entry, None, &limits, false),
+            Err(Error::LimitExceeded(_))
+        ));
+
+        let limits = GraphLimits { max_edges: Some(4), ..Default::default() };
+        assert!(get_dependency_graph(&map, &entry, None, &limits, false).is_ok());
+        let limits = GraphLimits { max_edges: Some(3), ..Default::default() };
+        assert!(get_dependency_graph(&map, &entry, None, &limits, false).is_err());
+    }
+
+    #[test]
+    fn test_dependency_graph_provenance() {
+        let dir = tempdir().unwrap();
+        let entry = dir.path().join("main.py");
+        fs::write(&entry, "").unwrap();
+        let entry = fs::canonicalize(entry).unwrap().to_string_lossy().into_owned();
+
+        let mut map = HashMap::new();
+        map.insert(entry.clone(), mock_file(vec!["a.py", "b.py"]));
+        map.insert("a.py".to_string(), mock_file(vec!["c.py"]));
+        map.insert("b.py".to_string(), mock_file(vec!["c.py", "d.py"]));
+        map.insert("c.py".to_string(), mock_file(vec![]));
+        map.insert("d.py".to_string(), mock_file(vec![]));
+
+        let graph = get_dependency_graph(&map, &entry, None, &GraphLimits::default(), true).unwrap();
+        assert_eq!(graph[&entry].parent, None);
+        assert!(graph[&entry].imported_by.is_empty());
+        assert_eq!(graph["c.py"].parent.as_deref(), Some("a.py"));
+        assert_eq!(graph["c.py"].imported_by, vec!["a.py".to_string(), "b.py".to_string()]);
+        assert_eq!(graph["d.py"].parent.as_deref(), Some("b.py"));
+        assert_eq!(graph["b.py"].parent, Some(entry.clone()));
+
+        let graph = get_dependency_graph(&map, &entry, None, &GraphLimits::default(), false).unwrap();
+        assert!(graph["c.py"].parent.is_none() && graph["c.py"].imported_by.is_empty());
+    }
+
+    #[test]
+    fn test_memory_stats_counts_strings_and_edges() {
+        let mut map = HashMap::new();
+        map.insert("a.py".to_string(), mock_file(vec!["b.py", "c.py"]));
+        map.insert("b.py".to_string(), mock_file(vec![]));
+
+        let stats = memory_stats(&map);
+        assert_eq!(stats.files, 2);
+        assert_eq!(stats.edges, 2);
+        // Two 4-byte keys, two 5-byte hashes and two 4-byte import paths.
+        assert!(stats.string_bytes >= 4 * 2 + 5 * 2 + 4 * 2);
+        assert_eq!(stats.total_bytes, stats.string_bytes + stats.struct_bytes);
+        assert_eq!(memory_stats(&HashMap::new()), MemoryStats::default());
+    }
+
+    #[test]
+    fn test_files_by_hash() {
+        let mut map = HashMap::new();
+        for (path, hash) in [("b.py", "abc"), ("a.py", "abc"), ("c.py", "def")] {
+            map.insert(path.to_string(), ProjectFile { hash: hash.to_string(), ..Default::default() });
+        }
+
+        assert_eq!(files_by_hash(&map, "abc"), vec!["a.py".to_string(), "b.py".to_string()]);
+        assert_eq!(files_by_hash(&map, "DEF"), vec!["c.py".to_string()]);
+        assert!(files_by_hash(&map, "missing").is_empty());
+
+        let index = hash_index(&map);
+        assert_eq!(index.len(), 2);
+        assert_eq!(index["abc"], files_by_hash(&map, "abc"));
+    }
+
+    #[test]
+    fn test_parse_target_version() {
+        assert_eq!(ParserOptions::parse_target_version("3.9").unwrap(), (3, 9));
+        assert_eq!(ParserOptions::parse_target_version("3.12").unwrap(), (3, 12));
+        assert!(ParserOptions::parse_target_version("3").is_err());
+        assert!(ParserOptions::parse_target_version("three.nine").is_err());
+    }
+
+    #[test]
+    fn test_to_module_map() {
+        let mut map = HashMap::new();
+        map.insert("/project/app/main.py".to_string(), mock_file(vec!["/project/app/__init__.py", "/project/app/utils.py"]));
+        map.insert("/project/app/utils.py".to_string(), mock_file(vec![]));
+        map.insert("/project/app/__init__.py".to_string(), mock_file(vec![]));
+
+        let modules = to_module_map(&map, Path::new("/project"));
+
+        assert_eq!(modules.len(), 3);
+        let main = &modules["app.main"];
+        assert_eq!(main.path, "/project/app/main.py");
+        assert_eq!(main.project_imports, vec!["app".to_string(), "app.utils".to_string()]);
+        assert!(modules.contains_key("app"));
+    }
+
+    #[test]
+    fn test_resolve_entry_point_from_module_name() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("app/handlers/checkout")).unwrap();
+        fs::write(root.join("app/handlers/checkout/__init__.py"), "").unwrap();
+        fs::write(root.join("app/handlers/orders.py"), "").unwrap();
+
+        let package = resolve_entry_point("app.handlers.checkout", Some(root)).unwrap();
+        assert!(package.ends_with("checkout/__init__.py"));
+
+        let module = resolve_entry_point("app.handlers.orders", Some(root)).unwrap();
+        assert!(module.ends_with("handlers/orders.py"));
+
+        assert!(resolve_entry_point("app.handlers.missing", Some(root)).is_err());
+        assert!(resolve_entry_point("app.handlers.orders", None).is_err());
+    }
+
+    #[test]
+    fn test_dotted_entry_points_resolve_like_imports() {
+        let dir = tempdir().unwrap();
+        let root = fs::canonicalize(dir.path()).unwrap();
+        fs::create_dir_all(root.join("src/app")).unwrap();
+        fs::create_dir_all(root.join("libs/corp/jobs")).unwrap();
+        fs::write(root.join("src/app/__init__.py"), "").unwrap();
+        fs::write(root.join("src/app/main.py"), "import corp.jobs.nightly\n").unwrap();
+        // `corp` is a namespace package, `corp.jobs.nightly` only has a stub.
+        fs::write(root.join("libs/corp/jobs/nightly.pyi"), "").unwrap();
+        let key = |rel: &str| root.join(rel).to_string_lossy().into_owned();
+        let config = BuildConfig {
+            source_root: root.clone(),
+            extra_source_roots: vec![PathBuf::from("src"), PathBuf::from("libs")],
+            project_module_prefixes: vec!["app".to_string(), "corp".to_string()],
+            include_paths: vec!["src/app".to_string()],
+            include_stubs: true,
+            resolution_scope: ResolutionScope::Follow,
+            ..Default::default()
+        };
+
+        assert_eq!(resolve_entry_point_in(&config, "app.main").unwrap(), key("src/app/main.py"));
+        assert_eq!(resolve_entry_point_in(&config, "corp.jobs.nightly").unwrap(), key("libs/corp/jobs/nightly.pyi"));
+        assert!(resolve_entry_point("app.main", Some(&root)).is_err());
+
+        let map = build_dependency_map(&config).unwrap().files;
+        let graph = get_dependency_graph(&map, "app.main", Some(&root), &GraphLimits::default(), false).unwrap();
+        assert!(graph.contains_key(&key("libs/corp/jobs/nightly.pyi")));
+        assert_eq!(entry_key(&map, "corp.jobs.nightly", Some(&root)).unwrap(), key("libs/corp/jobs/nightly.pyi"));
+        assert!(entry_key(&map, "corp.jobs.weekly", Some(&root)).is_err());
+        // Under `source_root`, the module is found without listing the map.
+        let contains = |path: &str| map.contains_key(path);
+        let unlisted = || -> Vec<String> { unreachable!("paths listed for a module under source_root") };
+        assert_eq!(
+            lookup_entry_point_with("app.main", Some(&root.join("src")), &contains, &unlisted).unwrap(),
+            key("src/app/main.py")
+        );
+    }
+
+    #[test]
+    fn test_build_dependency_map_collects_dynamic_import_warnings() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir(root.join("app")).unwrap();
+        fs::write(root.join("app/loader.py"), "import importlib\n\ndef load(name):\n    return importlib.import_module(name)\n").unwrap();
+        fs::write(root.join("app/main.py"), "import importlib\nimportlib.import_module('app.loader')\n").unwrap();
+
+        let config = BuildConfig {
+            source_root: root.to_path_buf(),
+            project_module_prefixes: vec!["app".to_string()],
+            include_paths: vec!["app".to_string()],
+            ..Default::default()
+        };
+        let result = build_dependency_map(&config).unwrap();
+        let loader = root.join("app/loader.py").to_string_lossy().into_owned();
+        assert_eq!(result.warnings, vec![DynamicImportWarning {
+            path: loader.clone(),
+            line: 4,
+            expression: "importlib.import_module(name)".to_string(),
+        }]);
+        assert_eq!(result.files[&loader].dynamic_import_warnings, result.warnings);
+        assert_eq!(dynamic_import_warnings(&result.files), result.warnings);
+    }
+
+    #[test]
+    fn test_build_dependency_map_reports_skipped_files() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir(root.join("app")).unwrap();
+        fs::write(root.join("app/main.py"), "import os\n").unwrap();
+        fs::write(root.join("app/notes.txt"), "").unwrap();
+        fs::write(root.join("app/types.pyi"), "").unwrap();
+        fs::write(root.join("app/latin1.py"), b"# caf\xe9\nimport os\n").unwrap();
+        fs::write(root.join("app/broken.py"), "import os\ndef broken(:\n").unwrap();
+
+        let config = BuildConfig {
+            source_root: root.to_path_buf(),
+            project_module_prefixes: vec!["app".to_string()],
+            include_paths: vec!["app".to_string()],
+            parser: ParserOptions { error_recovery: false, ..Default::default() },
+            ..Default::default()
+        };
+        let result = build_dependency_map(&config).unwrap();
+        let key = |rel: &str| root.join(rel).to_string_lossy().into_owned();
+        let skipped: Vec<(String, SkipReason)> = result.skipped.into_iter().map(|s| (s.path, s.reason)).collect();
+        assert_eq!(skipped, vec![
+            (key("app/broken.py"), SkipReason::ParseFailure),
+            (key("app/latin1.py"), SkipReason::NotUtf8),
+            (key("app/notes.txt"), SkipReason::WrongExtension),
+            (key("app/types.pyi"), SkipReason::WrongExtension),
+        ]);
+        assert!(result.files.contains_key(&key("app/latin1.py")));
+        assert!(!result.files.contains_key(&key("app/notes.txt")));
+    }
+
+    #[test]
+    fn test_build_dependency_map_skips_files_over_max_file_size() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir(root.join("app")).unwrap();
+        fs::write(root.join("app/main.py"), "import app.data\n").unwrap();
+        fs::write(root.join("app/data.py"), format!("import os\nDATA = '{}'\n", "x".repeat(100))).unwrap();
+
+        let config = BuildConfig {
+            source_root: root.to_path_buf(),
+            project_module_prefixes: vec!["app".to_string()],
+            include_paths: vec!["app".to_string()],
+            max_file_size: Some(64),
+            max_workers: Some(4),
+            io: IoOptions { threads: 2, ..Default::default() },
+            ..Default::default()
+        };
+        let result = build_dependency_map(&config).unwrap();
+        let key = |rel: &str| root.join(rel).to_string_lossy().into_owned();
+        assert_eq!(result.skipped, vec![SkippedFile { path: key("app/data.py"), reason: SkipReason::TooLarge }]);
+        assert!(result.files.contains_key(&key("app/main.py")));
+        assert!(!result.files.contains_key(&key("app/data.py")));
+    }
+
+    #[test]
+    fn test_build_dependency_map_resolution_scope() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("app/api")).unwrap();
+        fs::create_dir_all(root.join("app/core")).unwrap();
+        fs::write(root.join("app/api/views.py"), "from app.core import util\n").unwrap();
+        fs::write(root.join("app/core/util.py"), "from app.core import base\n").unwrap();
+        fs::write(root.join("app/core/base.py"), "").unwrap();
+        let key = |rel: &str| root.join(rel).to_string_lossy().into_owned();
+
+        let build = |resolution_scope| {
+            let config = BuildConfig {
+                source_root: root.to_path_buf(),
+                project_module_prefixes: vec!["app".to_string()],
+                include_paths: vec!["app/api".to_string()],
+                resolution_scope,
+                ..Default::default()
+            };
+            build_dependency_map(&config).unwrap().files
+        };
+
+        let default = build(ResolutionScope::SourceRoot);
+        assert_eq!(default[&key("app/api/views.py")].project_imports, vec![key("app/core/util.py")]);
+        assert!(!default.contains_key(&key("app/core/util.py")));
+
+        let scanned = build(ResolutionScope::Scanned);
+        assert!(scanned[&key("app/api/views.py")].project_imports.is_empty());
+
+        let follow = build(ResolutionScope::Follow);
+        assert_eq!(follow.len(), 3);
+        assert_eq!(follow[&key("app/core/util.py")].project_imports, vec![key("app/core/base.py")]);
+        assert_eq!("follow".parse::<ResolutionScope>().unwrap(), ResolutionScope::Follow);
+        assert!("everything".parse::<ResolutionScope>().is_err());
+    }
+
+    #[test]
+    fn test_build_dependency_map_links_subprocess_scripts() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("app/handlers")).unwrap();
+        fs::create_dir(root.join("scripts")).unwrap();
+        fs::write(
+            root.join("app/handlers/deploy.py"),
+            "import subprocess, sys\nsubprocess.run([sys.executable, 'scripts/migrate.py'])\nsubprocess.run(['python', 'helper.py'])\nsubprocess.run(['python', 'scripts/missing.py'])\n",
+        )
+        .unwrap();
+        fs::write(root.join("app/handlers/helper.py"), "").unwrap();
+        fs::write(root.join("scripts/migrate.py"), "").unwrap();
+
+        let config = BuildConfig {
+            source_root: root.to_path_buf(),
+            project_module_prefixes: vec!["app".to_string()],
+            include_paths: vec!["app".to_string()],
+            ..Default::default()
+        };
+        let result = build_dependency_map(&config).unwrap();
+        let key = |rel: &str| root.join(rel).to_string_lossy().into_owned();
+        let mut imports = result.files[&key("app/handlers/deploy.py")].project_imports.clone();
+        imports.sort();
+        assert_eq!(imports, vec![key("app/handlers/helper.py"), key("scripts/migrate.py")]);
+    }
+
+    #[test]
+    fn test_build_dependency_map_includes_resource_files() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("app/data")).unwrap();
+        fs::write(
+            root.join("app/report.py"),
+            "from importlib import resources\nSQL = resources.files('app.data').joinpath('report.sql').read_text()\nTPL = resources.read_text(__package__, 'report.tpl')\nX = resources.read_text('requests', 'cacert.pem')\n",
+        )
+        .unwrap();
+        fs::write(root.join("app/data/report.sql"), "SELECT 1").unwrap();
+        fs::write(root.join("app/report.tpl"), "{{ rows }}").unwrap();
+
+        let config = BuildConfig {
+            source_root: root.to_path_buf(),
+            project_module_prefixes: vec!["app".to_string()],
+            include_paths: vec!["app".to_string()],
+            ..Default::default()
+        };
+        let result = build_dependency_map(&config).unwrap();
+        let key = |rel: &str| root.join(rel).to_string_lossy().into_owned();
+        let mut imports = result.files[&key("app/report.py")].project_imports.clone();
+        imports.sort();
+        assert_eq!(imports, vec![key("app/data/report.sql"), key("app/report.tpl")]);
+
+        let data = &result.files[&key("app/data/report.sql")];
+        assert_eq!(data.hash, hex::encode(Sha256::digest(b"SELECT 1")));
+        assert!(data.project_imports.is_empty());
+        assert!(result.skipped.is_empty());
+    }
+
+    #[test]
+    fn test_build_dependency_map_normalizes_line_endings() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::write(root.join("unix.py"), "import os\nprint(os.name)\n").unwrap();
+        fs::write(root.join("windows.py"), "import os\r\nprint(os.name)\r\n\r\n").unwrap();
+        let key = |rel: &str| root.join(rel).to_string_lossy().into_owned();
+
+        let build = |normalize_line_endings, ignore_trailing_newlines| {
+            let config = BuildConfig {
+                source_root: root.to_path_buf(),
+                include_paths: vec!["unix.py".to_string(), "windows.py".to_string()],
+                normalize_line_endings,
+                ignore_trailing_newlines,
+                ..Default::default()
+            };
+            let files = build_dependency_map(&config).unwrap().files;
+            (files[&key("unix.py")].hash.clone(), files[&key("windows.py")].hash.clone())
+        };
+
+        let (unix, windows) = build(false, false);
+        assert_ne!(unix, windows);
+        let (unix, windows) = build(true, false);
+        assert_ne!(unix, windows);
+        let (unix, windows) = build(true, true);
+        assert_eq!(unix, windows);
+    }
+
+    #[test]
+    fn test_build_dependency_map_reads_ahead_in_batches() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir(root.join("app")).unwrap();
+        for i in 0..20 {
+            fs::write(root.join(format!("app/m{}.py", i)), format!("from app import m{}\n", (i + 1) % 20)).unwrap();
+        }
+
+        let config = |threads, max_open_files, max_buffered_bytes| BuildConfig {
+            source_root: root.to_path_buf(),
+            project_module_prefixes: vec!["app".to_string()],
+            include_paths: vec!["app".to_string()],
+            io: IoOptions { threads, batch_size: 7, max_open_files, max_buffered_bytes, ..Default::default() },
+            ..Default::default()
+        };
+        let sequential = build_dependency_map(&config(1, None, None)).unwrap().files;
+        for (max_open_files, max_buffered_bytes) in [(None, None), (Some(4), Some(40))] {
+            let concurrent = build_dependency_map(&config(4, max_open_files, max_buffered_bytes)).unwrap().files;
+            assert_eq!(concurrent.len(), 20);
+            for (path, info) in &sequential {
+                assert_eq!(concurrent[path].hash, info.hash);
+                assert_eq!(concurrent[path].project_imports, info.project_imports);
+            }
+        }
+    }
+
+    #[test]
+    fn test_find_source_files_matches_scan() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("app/pkg")).unwrap();
+        fs::create_dir(root.join("scripts")).unwrap();
+        fs::write(root.join("app/main.py"), "from app.pkg import mod\n").unwrap();
+        fs::write(root.join("app/pkg/mod.py"), "").unwrap();
+        fs::write(root.join("app/pkg/types.pyi"), "").unwrap();
+        fs::write(root.join("app/README.md"), "").unwrap();
+        fs::write(root.join("scripts/run"), "import app.main\n").unwrap();
+        let key = |rel: &str| root.join(rel).to_string_lossy().into_owned();
+
+        let config = BuildConfig {
+            source_root: root.to_path_buf(),
+            project_module_prefixes: vec!["app".to_string()],
+            include_paths: vec!["app".to_string(), "app/pkg".to_string(), "scripts/run".to_string()],
+            ..Default::default()
+        };
+        let files = find_source_files(&config).unwrap();
+        assert_eq!(files, vec![key("app/main.py"), key("app/pkg/mod.py"), key("scripts/run")]);
+        let mut scanned: Vec<String> = build_dependency_map(&config).unwrap().files.into_keys().collect();
+        scanned.sort();
+        assert_eq!(scanned, files);
+
+        let stubs = BuildConfig { include_stubs: true, ..config };
+        assert!(find_source_files(&stubs).unwrap().contains(&key("app/pkg/types.pyi")));
+    }
+
+    #[test]
+    fn test_walk_prunes_excluded_and_git_ignored_paths() {
+        let dir = tempdir().unwrap();
+        let root = fs::canonicalize(dir.path()).unwrap();
+        git2::Repository::init(&root).unwrap();
+        for sub_dir in ["app/venv/lib", "app/build", "app/tests"] {
+            fs::create_dir_all(root.join(sub_dir)).unwrap();
+        }
+        fs::write(root.join(".gitignore"), "build/\n*_pb2.py\n").unwrap();
+        fs::write(root.join("app/main.py"), "").unwrap();
+        fs::write(root.join("app/venv/lib/six.py"), "").unwrap();
+        fs::write(root.join("app/build/main.py"), "").unwrap();
+        fs::write(root.join("app/api_pb2.py"), "").unwrap();
+        fs::write(root.join("app/tests/test_main.py"), "").unwrap();
+        let key = |rel: &str| root.join(rel).to_string_lossy().into_owned();
+        let config = BuildConfig {
+            source_root: root.clone(),
+            include_paths: vec!["app".to_string()],
+            exclude: vec!["venv".to_string(), "app/tests/**".to_string()],
+            ..Default::default()
+        };
+
+        assert_eq!(find_source_files(&config).unwrap(), vec![
+            key("app/api_pb2.py"),
+            key("app/build/main.py"),
+            key("app/main.py"),
+        ]);
+        let gitignore = BuildConfig { respect_gitignore: true, ..config };
+        assert_eq!(find_source_files(&gitignore).unwrap(), vec![key("app/main.py")]);
+        let skipped: Vec<(String, SkipReason)> =
+            build_dependency_map(&gitignore).unwrap().skipped.into_iter().map(|s| (s.path, s.reason)).collect();
+        assert_eq!(skipped, vec![
+            (key("app/api_pb2.py"), SkipReason::Excluded),
+            (key("app/build"), SkipReason::Excluded),
+            (key("app/tests"), SkipReason::Excluded),
+            (key("app/venv"), SkipReason::Excluded),
+        ]);
+    }
+
+    #[test]
+    fn test_build_dependency_map_scans_include_paths_outside_root() {
+        let dir = tempdir().unwrap();
+        let base = dir.path();
+        fs::create_dir_all(base.join("src/app")).unwrap();
+        fs::create_dir_all(base.join("build/gen")).unwrap();
+        fs::create_dir_all(base.join("vendor_gen")).unwrap();
+        fs::write(base.join("src/app/models.py"), "").unwrap();
+        fs::write(base.join("build/gen/api_pb2.py"), "from app import models\n").unwrap();
+        fs::write(base.join("vendor_gen/client.py"), "import app.models\n").unwrap();
+        let key = |rel: &str| base.join(rel).to_string_lossy().into_owned();
+
+        let config = BuildConfig {
+            source_root: base.join("src"),
+            project_module_prefixes: vec!["app".to_string()],
+            include_paths: vec!["app".to_string(), "../build/gen".to_string(), key("vendor_gen")],
+            resolution_scope: ResolutionScope::Scanned,
+            ..Default::default()
+        };
+        let files = build_dependency_map(&config).unwrap().files;
+        assert_eq!(files.len(), 3);
+        assert_eq!(files[&key("build/gen/api_pb2.py")].project_imports, vec![key("src/app/models.py")]);
+        assert_eq!(files[&key("vendor_gen/client.py")].project_imports, vec![key("src/app/models.py")]);
+        assert!(find_source_files(&config).unwrap().contains(&key("build/gen/api_pb2.py")));
+    }
+
+    #[test]
+    fn test_build_dependency_map_prefix_set_views() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("app")).unwrap();
+        fs::create_dir_all(root.join("libs/common")).unwrap();
+        fs::write(root.join("app/main.py"), "import app.util\nimport libs.common.db\nimport requests\n").unwrap();
+        fs::write(root.join("app/util.py"), "").unwrap();
+        fs::write(root.join("libs/__init__.py"), "").unwrap();
+        fs::write(root.join("libs/common/db.py"), "").unwrap();
+        let key = |rel: &str| root.join(rel).to_string_lossy().into_owned();
+        let sorted = |mut items: Vec<String>| {
+            items.sort();
+            items
+        };
+
+        let base = BuildConfig {
+            source_root: root.to_path_buf(),
+            project_module_prefixes: vec!["app".to_string()],
+            include_paths: vec!["app".to_string(), "libs".to_string()],
+            ..Default::default()
+        };
+        let config = BuildConfig {
+            prefix_sets: BTreeMap::from([
+                ("app".to_string(), vec!["app.".to_string()]),
+                ("shared".to_string(), vec!["app".to_string(), "libs.".to_string()]),
+            ]),
+            ..base.clone()
+        };
+        let result = build_dependency_map(&config).unwrap();
+        let separate = build_dependency_map(&base).unwrap().files;
+        let main = &result.files[&key("app/main.py")];
+        assert_eq!(main.project_imports, separate[&key("app/main.py")].project_imports);
+        assert_eq!(sorted(main.third_party_imports.clone()), vec!["libs".to_string(), "requests".to_string()]);
+
+        let app_view = &result.views["app"][&key("app/main.py")];
+        assert_eq!(app_view.project_imports, vec![key("app/util.py")]);
+        let shared_view = &result.views["shared"][&key("app/main.py")];
+        assert_eq!(
+            sorted(shared_view.project_imports.clone()),
+            vec![key("app/util.py"), key("libs/__init__.py"), key("libs/common/db.py")]
+        );
+        assert_eq!(shared_view.third_party_modules, vec!["requests".to_string()]);
+        assert_eq!(result.views["shared"].len(), result.files.len());
+    }
+
+    #[test]
+    fn test_plan_dependency_map() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("app/pkg")).unwrap();
+        fs::write(root.join("app/main.py"), "import os\n").unwrap();
+        fs::write(root.join("app/pkg/mod.py"), "x = 1\n").unwrap();
+        fs::write(root.join("app/notes.txt"), "").unwrap();
+        let key = |rel: &str| root.join(rel).to_string_lossy().into_owned();
+
+        let mut config = BuildConfig {
+            source_root: root.to_path_buf(),
+            project_module_prefixes: vec!["app".to_string()],
+            include_paths: vec!["app".to_string(), "app/pkg".to_string()],
+            ..Default::default()
+        };
+        let plan = plan_dependency_map(&config).unwrap();
+        assert_eq!(plan.files, 2);
+        assert_eq!(plan.bytes_to_hash, 16);
+        assert_eq!(plan.include_paths[0], IncludePathPlan { include_path: "app".to_string(), files: 2, bytes: 16 });
+        assert_eq!(plan.include_paths[1].files, 1);
+        assert_eq!(plan.excluded, vec![SkippedFile { path: key("app/notes.txt"), reason: SkipReason::WrongExtension }]);
+
+        config.previous_map = build_dependency_map(&config).unwrap().files;
+        let plan = plan_dependency_map(&config).unwrap();
+        assert_eq!((plan.files, plan.reusable_files, plan.bytes_to_hash), (2, 2, 0));
+
+        config.max_file_size = Some(8);
+        let plan = plan_dependency_map(&config).unwrap();
+        assert_eq!(plan.files, 1);
+        assert!(plan.excluded.contains(&SkippedFile { path: key("app/main.py"), reason: SkipReason::TooLarge }));
+    }
+
+    #[test]
+    fn test_build_dependency_map_parallel_matches_sequential() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("app/pkg")).unwrap();
+        fs::write(root.join("app/__init__.py"), "").unwrap();
+        fs::write(root.join("app/pkg/__init__.py"), "").unwrap();
+        for i in 0..40 {
+            fs::write(root.join(format!("app/pkg/mod{}.py", i)), format!("import os\nimport app.pkg.mod{}\n", (i + 1) % 40)).unwrap();
+        }
+        fs::write(root.join("app/binary.py"), [0xff, 0xfe, 0x00]).unwrap();
+
+        let sequential = BuildConfig {
+            source_root: root.to_path_buf(),
+            project_module_prefixes: vec!["app".to_string()],
+            include_paths: vec!["app".to_string()],
+            max_workers: Some(1),
+            ..Default::default()
+        };
+        let expected = build_dependency_map(&sequential).unwrap();
+        let sorted = |mut items: Vec<String>| {
+            items.sort();
+            items
+        };
+        for max_workers in [None, Some(4)] {
+            let io = IoOptions { batch_size: 7, ..Default::default() };
+            let result = build_dependency_map(&BuildConfig { max_workers, io, ..sequential.clone() }).unwrap();
+            assert_eq!(result.files.len(), 43);
+            for (path, info) in &expected.files {
+                let parallel = &result.files[path];
+                assert_eq!(parallel.hash, info.hash);
+                assert_eq!(sorted(parallel.project_imports.clone()), sorted(info.project_imports.clone()));
+                assert_eq!(parallel.third_party_imports, info.third_party_imports);
+            }
+            assert_eq!(result.skipped, expected.skipped);
+        }
+    }
+
+    #[test]
+    fn test_build_dependency_map_resolves_relative_imports() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("app/core")).unwrap();
+        fs::create_dir_all(root.join("app/utils")).unwrap();
+        fs::write(root.join("app/__init__.py"), "").unwrap();
+        fs::write(root.join("app/core/__init__.py"), "from .models import Model\n").unwrap();
+        fs::write(root.join("app/core/models.py"), "").unwrap();
+        fs::write(root.join("app/core/views.py"), "from . import models as m\nfrom ..utils import text\nfrom .... import nowhere\n").unwrap();
+        fs::write(root.join("app/utils/__init__.py"), "").unwrap();
+        fs::write(root.join("app/utils/text.py"), "").unwrap();
+        let key = |rel: &str| root.join(rel).to_string_lossy().into_owned();
+
+        let config = BuildConfig {
+            source_root: root.to_path_buf(),
+            project_module_prefixes: vec!["app".to_string()],
+            include_paths: vec!["app".to_string()],
+            ..Default::default()
+        };
+        let result = build_dependency_map(&config).unwrap();
+        let mut views = result.files[&key("app/core/views.py")].project_imports.clone();
+        views.sort();
+        assert_eq!(views, vec![
+            key("app/__init__.py"),
+            key("app/core/__init__.py"),
+            key("app/core/models.py"),
+            key("app/utils/__init__.py"),
+            key("app/utils/text.py"),
+        ]);
+        let views_file = &result.files[&key("app/core/views.py")];
+        assert!(views_file.third_party_imports.is_empty());
+        assert_eq!(views_file.import_aliases.get("m"), Some(&"app.core.models".to_string()));
+        assert!(result.files[&key("app/core/__init__.py")].project_imports.contains(&key("app/core/models.py")));
+    }
+
+    #[test]
+    fn test_build_dependency_map_resolves_across_source_roots() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("src/app")).unwrap();
+        fs::create_dir_all(root.join("libs/shared/corp/billing")).unwrap();
+        fs::create_dir_all(root.join("src/corp/auth")).unwrap();
+        fs::write(root.join("src/app/__init__.py"), "").unwrap();
+        fs::write(root.join("src/app/main.py"), "import corp.billing.invoice\nimport corp.auth.tokens\nfrom . import helpers\n").unwrap();
+        fs::write(root.join("src/app/helpers.py"), "").unwrap();
+        // `corp` is a namespace package split across both roots.
+        fs::write(root.join("libs/shared/corp/billing/invoice.py"), "").unwrap();
+        fs::write(root.join("src/corp/auth/tokens.pyi"), "").unwrap();
+        let key = |rel: &str| root.join(rel).to_string_lossy().into_owned();
+
+        let config = BuildConfig {
+            source_root: root.to_path_buf(),
+            extra_source_roots: vec![PathBuf::from("src"), PathBuf::from("libs/shared")],
+            project_module_prefixes: vec!["app".to_string(), "corp".to_string()],
+            include_paths: vec!["src/app".to_string()],
+            include_stubs: true,
+            ..Default::default()
+        };
+        let result = build_dependency_map(&config).unwrap();
+        let mut imports = result.files[&key("src/app/main.py")].project_imports.clone();
+        imports.sort();
+        assert_eq!(imports, vec![
+            key("libs/shared/corp/billing/invoice.py"),
+            key("src/app/__init__.py"),
+            key("src/app/helpers.py"),
+            key("src/corp/auth/tokens.pyi"),
+        ]);
+    }
+
+    #[test]
+    fn test_build_dependency_map_excludes_import_kinds() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("app")).unwrap();
+        fs::write(root.join("app/__init__.py"), "").unwrap();
+        fs::write(
+            root.join("app/main.py"),
+            "import os\nfrom typing import TYPE_CHECKING\nif TYPE_CHECKING:\n    import app.models\n    import app.util\n\ndef run():\n    import app.util\n",
+        )
+        .unwrap();
+        fs::write(root.join("app/models.py"), "").unwrap();
+        fs::write(root.join("app/util.py"), "").unwrap();
+        let key = |rel: &str| root.join(rel).to_string_lossy().into_owned();
+        let config = BuildConfig {
+            source_root: root.to_path_buf(),
+            project_module_prefixes: vec!["app".to_string()],
+            include_paths: vec!["app".to_string()],
+            ..Default::default()
+        };
+
+        let result = build_dependency_map(&config).unwrap();
+        let main = &result.files[&key("app/main.py")];
+        assert_eq!(main.import_kinds["os"], ImportKind::TopLevel);
+        assert_eq!(main.import_kinds["app.models"], ImportKind::TypeChecking);
+        assert_eq!(main.import_kinds["app.util"], ImportKind::Deferred);
+        assert!(main.project_imports.contains(&key("app/models.py")));
+
+        let config = BuildConfig { excluded_import_kinds: vec![ImportKind::TypeChecking], ..config };
+        let result = build_dependency_map(&config).unwrap();
+        let main = &result.files[&key("app/main.py")];
+        let mut imports = main.project_imports.clone();
+        imports.sort();
+        assert_eq!(imports, vec![key("app/__init__.py"), key("app/util.py")]);
+        assert!(!main.import_kinds.contains_key("app.models"));
+        assert_eq!(main.import_kinds["app.util"], ImportKind::Deferred);
+    }
+
+    #[test]
+    fn test_build_dependency_map_reports_unresolved_imports() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("app/ns")).unwrap();
+        fs::write(root.join("app/__init__.py"), "").unwrap();
+        fs::write(
+            root.join("app/main.py"),
+            "import requests\nimport app.modles\nimport app.ns\nfrom app import helper\nfrom ... import up\nimport app.util\n",
+        )
+        .unwrap();
+        fs::write(root.join("app/util.py"), "import yaml\nimport requests.adapters\n").unwrap();
+        fs::write(root.join("app/other.py"), "import numpy\n").unwrap();
+        let key = |rel: &str| root.join(rel).to_string_lossy().into_owned();
+        let config = BuildConfig {
+            source_root: root.to_path_buf(),
+            project_module_prefixes: vec!["app".to_string()],
+            include_paths: vec!["app".to_string()],
+            ..Default::default()
+        };
+
+        let result = build_dependency_map(&config).unwrap();
+        let mut unresolved = result.files[&key("app/main.py")].unresolved_imports.clone();
+        unresolved.sort();
+        assert_eq!(unresolved, vec!["...".to_string(), "app.modles".to_string()]);
+        assert!(result.files[&key("app/util.py")].unresolved_imports.is_empty());
+        assert_eq!(
+            get_external_dependencies(&result.files, &key("app/main.py"), None).unwrap(),
+            vec!["requests".to_string(), "yaml".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_build_dependency_map_marks_volatile_files() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("app/generated")).unwrap();
+        fs::write(root.join("app/generated/build_info.py"), "BUILD = 1\n").unwrap();
+        fs::write(root.join("app/stamp.py"), "# depmap: volatile\nSTAMP = 2\n").unwrap();
+        fs::write(root.join("app/main.py"), "import os\n").unwrap();
+
+        let config = BuildConfig {
+            source_root: root.to_path_buf(),
+            project_module_prefixes: vec!["app".to_string()],
+            include_paths: vec!["app".to_string()],
+            volatile_patterns: vec!["app/generated/**".to_string()],
+            ..Default::default()
+        };
+        let files = build_dependency_map(&config).unwrap().files;
+        let volatile = |rel: &str| files[&root.join(rel).to_string_lossy().into_owned()].volatile;
+
+        assert!(volatile("app/generated/build_info.py"));
+        assert!(volatile("app/stamp.py"));
+        assert!(!volatile("app/main.py"));
+    }
+
+    #[test]
+    fn test_build_dependency_map_merges_extra_edges() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("app/handlers")).unwrap();
+        fs::create_dir_all(root.join("app/plugins")).unwrap();
+        fs::write(root.join("app/handlers/x.py"), "import os\n").unwrap();
+        fs::write(root.join("app/plugins/reflected.py"), "").unwrap();
+        let edges_path = root.join("edges.toml");
+        fs::write(&edges_path, "[extra_edges]\n\"app/handlers/x.py\" = [\"app/plugins/reflected.py\"]\n").unwrap();
+
+        let mut config = BuildConfig {
+            source_root: root.to_path_buf(),
+            project_module_prefixes: vec!["app".to_string()],
+            include_paths: vec!["app/handlers".to_string()],
+            extra_edges_path: Some(edges_path),
+            ..Default::default()
+        };
+        let files = build_dependency_map(&config).unwrap().files;
+        let handler_key = root.join("app/handlers/x.py").to_string_lossy().into_owned();
+        let plugin = root.join("app/plugins/reflected.py").to_string_lossy().into_owned();
+        assert!(files[&handler_key].project_imports.contains(&plugin));
+        assert!(files.contains_key(&plugin));
+
+        let unnormalized_edges = root.join("unnormalized.json");
+        fs::write(&unnormalized_edges, r#"{"extra_edges": {"./app/handlers/x.py": ["app//handlers/../plugins/reflected.py"]}}"#).unwrap();
+        config.extra_edges_path = Some(unnormalized_edges);
+        let files = build_dependency_map(&config).unwrap().files;
+        assert!(files[&handler_key].project_imports.contains(&plugin));
+
+        for target in ["app/missing.py", "app/plugins"] {
+            let bad_edges = root.join("bad.json");
+            fs::write(&bad_edges, format!(r#"{{"extra_edges": {{"app/handlers/x.py": ["{}"]}}}}"#, target)).unwrap();
+            config.extra_edges_path = Some(bad_edges);
+            assert!(build_dependency_map(&config).is_err());
+        }
+    }
+
+    #[test]
+    fn test_build_dependency_map_deep_mode_follows_site_packages() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("project");
+        let site = dir.path().join("site-packages");
+        fs::create_dir_all(root.join("app")).unwrap();
+        fs::create_dir_all(site.join("vendored")).unwrap();
+        fs::create_dir_all(site.join("other")).unwrap();
+        fs::write(root.join("app/main.py"), "import vendored.core\nimport other\n").unwrap();
+        fs::write(site.join("vendored/__init__.py"), "").unwrap();
+        fs::write(site.join("vendored/core.py"), "from vendored import util\nimport other\n").unwrap();
+        fs::write(site.join("vendored/util.py"), "import json\n").unwrap();
+        fs::write(site.join("other/__init__.py"), "").unwrap();
+        let key = |p: PathBuf| p.to_string_lossy().into_owned();
+
+        let mut config = BuildConfig {
+            source_root: root.clone(),
+            project_module_prefixes: vec!["app".to_string()],
+            include_paths: vec!["app".to_string()],
+            site_packages_dirs: vec![site.clone()],
+            deep_packages: vec!["vendored".to_string()],
+            ..Default::default()
+        };
+        let files = build_dependency_map(&config).unwrap().files;
+        let main = &files[&key(root.join("app/main.py"))];
+        assert!(main.project_imports.contains(&key(site.join("vendored/core.py"))));
+        assert!(main.third_party_imports.contains(&"vendored".to_string()));
+        assert!(files[&key(site.join("vendored/core.py"))].project_imports.contains(&key(site.join("vendored/util.py"))));
+        assert!(files.contains_key(&key(site.join("vendored/util.py"))));
+        assert!(!files.contains_key(&key(site.join("other/__init__.py"))));
+
+        config.deep_max_depth = Some(1);
+        let files = build_dependency_map(&config).unwrap().files;
+        assert!(files.contains_key(&key(site.join("vendored/core.py"))));
+        assert!(!files.contains_key(&key(site.join("vendored/util.py"))));
+
+        config.site_packages_dirs.clear();
+        let files = build_dependency_map(&config).unwrap().files;
+        assert_eq!(files.len(), 1);
+    }
+
+    #[test]
+    fn test_build_dependency_map_reads_zip_source_root() {
+        use std::io::Write;
+
+        let dir = tempdir().unwrap();
+        let archive_path = dir.path().join("bundle.zip");
+        let mut zip = zip::ZipWriter::new(fs::File::create(&archive_path).unwrap());
+        for (name, content) in [
+            ("app/__init__.py", ""),
+            ("app/main.py", "import os\nfrom app import util\n"),
+            ("app/util.py", "import requests\n"),
+        ] {
+            zip.start_file(name, zip::write::SimpleFileOptions::default()).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+
+        let config = BuildConfig {
+            source_root: archive_path.clone(),
+            project_module_prefixes: vec!["app".to_string()],
+            include_paths: vec!["app".to_string()],
+            ..Default::default()
+        };
+        let files = build_dependency_map(&config).unwrap().files;
+        let key = |rel: &str| archive_path.join(rel).to_string_lossy().into_owned();
+        assert_eq!(files.len(), 3);
+        let main = &files[&key("app/main.py")];
+        assert!(main.project_imports.contains(&key("app/util.py")));
+        assert!(main.project_imports.contains(&key("app/__init__.py")));
+        assert_eq!(files[&key("app/util.py")].third_party_imports, vec!["requests".to_string()]);
+        assert_eq!(files[&key("app/util.py")].third_party_modules, vec!["requests".to_string()]);
+
+        let graph = get_dependency_graph(&files, &key("app/main.py"), None, &GraphLimits::default(), false).unwrap();
+        assert_eq!(graph.len(), 3);
+    }
+
+    #[test]
+    fn test_build_dependency_map_reuses_unchanged_entries() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir(root.join("app")).unwrap();
+        fs::write(root.join("app/main.py"), "import os\n").unwrap();
+        let main_path = root.join("app/main.py").to_string_lossy().into_owned();
+
+        let mut config = BuildConfig {
+            source_root: root.to_path_buf(),
+            project_module_prefixes: vec!["app".to_string()],
+            include_paths: vec!["app".to_string()],
+            ..Default::default()
+        };
+        let first = build_dependency_map(&config).unwrap().files;
+
+        config.previous_map = first.clone();
+        config.previous_map.get_mut(&main_path).unwrap().hash = "from-previous-run".to_string();
+        let second = build_dependency_map(&config).unwrap();
+        assert_eq!(second.files[&main_path].hash, "from-previous-run");
+        assert_eq!(second.reused_files, 1);
+
+        config.previous_map.get_mut(&main_path).unwrap().size += 1;
+        let third = build_dependency_map(&config).unwrap();
+        assert_eq!(third.files[&main_path].hash, first[&main_path].hash);
+        assert_eq!(third.reused_files, 0);
+    }
+
+    #[test]
+    fn test_reused_entries_resolve_their_imports_again() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir(root.join("app")).unwrap();
+        fs::write(root.join("app/main.py"), "import app.util\n").unwrap();
+        fs::write(root.join("app/util.py"), "").unwrap();
+        let key = |rel: &str| root.join(rel).to_string_lossy().into_owned();
+
+        let mut config = BuildConfig {
+            source_root: root.to_path_buf(),
+            project_module_prefixes: vec!["app".to_string()],
+            include_paths: vec!["app".to_string()],
+            ..Default::default()
+        };
+        config.previous_map = build_dependency_map(&config).unwrap().files;
+        assert_eq!(config.previous_map[&key("app/main.py")].project_imports, vec![key("app/util.py")]);
+
+        fs::remove_file(root.join("app/util.py")).unwrap();
+        let result = build_dependency_map(&config).unwrap();
+        assert_eq!(result.reused_files, 1);
+        let main = &result.files[&key("app/main.py")];
+        assert!(main.project_imports.is_empty());
+        assert_eq!(main.unresolved_imports, vec!["app.util".to_string()]);
+
+        let other_prefixes = BuildConfig { project_module_prefixes: vec!["other".to_string()], ..config.clone() };
+        let result = build_dependency_map(&other_prefixes).unwrap();
+        assert_eq!(result.reused_files, 1);
+        assert_eq!(result.files[&key("app/main.py")].third_party_imports, vec!["app".to_string()]);
+
+        let normalized = BuildConfig { normalize_line_endings: true, ..config };
+        assert_eq!(build_dependency_map(&normalized).unwrap().reused_files, 0);
+    }
+}
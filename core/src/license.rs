@@ -0,0 +1,123 @@
+#[cfg(feature = "python")]
+use pyo3::prelude::*;
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+
+use crate::pip::packages_for_imports;
+use crate::{closure_paths, entry_key, PipMetadata, ProjectFile, Result};
+
+/// Third-party licenses pulled in by one entry point.
+#[cfg_attr(feature = "python", pyclass)]
+#[derive(Clone, Debug, Default)]
+pub struct LicenseReport {
+    /// Distribution -> license, for every distribution with license metadata.
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub licenses: HashMap<String, String>,
+    /// Distributions without license metadata, sorted.
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub unknown: Vec<String>,
+    /// Distributions whose license matches a disallowed license, sorted.
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub disallowed: Vec<String>,
+}
+
+/// Whether `license` is, or is an SPDX expression containing, one of `disallowed`
+/// (compared case-insensitively).
+fn is_disallowed(license: &str, disallowed: &[String]) -> bool {
+    let tokens: Vec<&str> = license
+        .split(|c: char| c.is_whitespace() || c == '(' || c == ')')
+        .filter(|t| !t.is_empty() && !matches!(*t, "AND" | "OR" | "WITH"))
+        .collect();
+    disallowed.iter().any(|d| {
+        license.eq_ignore_ascii_case(d) || tokens.iter().any(|t| t.eq_ignore_ascii_case(d))
+    })
+}
+
+/// Aggregates the licenses of the distributions (including transitive requirements)
+/// behind the third-party imports of `entry_point`'s closure.
+pub fn license_report(
+    dependency_map: &HashMap<String, ProjectFile>,
+    entry_point: &str,
+    source_root: Option<&Path>,
+    pip_metadata: &PipMetadata,
+    disallowed_licenses: &[String],
+) -> Result<LicenseReport> {
+    let entry_key = entry_key(dependency_map, entry_point, source_root)?;
+    let modules: BTreeSet<&String> = closure_paths(dependency_map, &entry_key)
+        .iter()
+        .flat_map(|path| dependency_map[path].third_party_module_names())
+        .collect();
+
+    let mut report = LicenseReport::default();
+    for (package, info) in packages_for_imports(modules, pip_metadata) {
+        match info.license {
+            Some(license) => {
+                if is_disallowed(&license, disallowed_licenses) {
+                    report.disallowed.push(package.clone());
+                }
+                report.licenses.insert(package, license);
+            }
+            None => report.unknown.push(package),
+        }
+    }
+    report.unknown.sort();
+    report.disallowed.sort();
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PipPackageInfo;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_is_disallowed_matches_expression_terms() {
+        let disallowed = vec!["GPL-3.0-only".to_string(), "GNU General Public License v3 (GPLv3)".to_string()];
+        assert!(is_disallowed("(MIT OR gpl-3.0-only)", &disallowed));
+        assert!(is_disallowed("GNU General Public License v3 (GPLv3)", &disallowed));
+        assert!(!is_disallowed("LGPL-3.0-only", &disallowed));
+    }
+
+    #[test]
+    fn test_license_report_for_entry_point() {
+        let dir = tempdir().unwrap();
+        let root = fs::canonicalize(dir.path()).unwrap();
+        fs::write(root.join("main.py"), "").unwrap();
+        fs::write(root.join("other.py"), "").unwrap();
+        let key = |rel: &str| root.join(rel).to_string_lossy().into_owned();
+
+        let mut map = HashMap::new();
+        map.insert(key("main.py"), ProjectFile { third_party_imports: vec!["requests".to_string()], ..Default::default() });
+        map.insert(key("other.py"), ProjectFile { third_party_imports: vec!["gpl_lib".to_string()], ..Default::default() });
+
+        let package = |license: Option<&str>, deps: &[&str]| PipPackageInfo {
+            version: "1.0".to_string(),
+            installed_paths: Vec::new(),
+            dependencies: deps.iter().map(|d| d.to_string()).collect(),
+            license: license.map(str::to_string),
+        };
+        let metadata = PipMetadata {
+            import_to_pip_map: HashMap::from([
+                ("requests".to_string(), "requests".to_string()),
+                ("gpl_lib".to_string(), "gpl-lib".to_string()),
+            ]),
+            pip_package_info_map: HashMap::from([
+                ("requests".to_string(), package(Some("Apache-2.0"), &["urllib3", "chardet"])),
+                ("urllib3".to_string(), package(Some("MIT"), &[])),
+                ("chardet".to_string(), package(None, &[])),
+                ("gpl-lib".to_string(), package(Some("GPL-3.0-only"), &[])),
+            ]),
+            extra_dependencies_map: HashMap::new(),
+            extra_paths_map: HashMap::new(),
+        };
+        let disallowed = vec!["MIT".to_string(), "GPL-3.0-only".to_string()];
+
+        let report = license_report(&map, &key("main.py"), None, &metadata, &disallowed).unwrap();
+        assert_eq!(report.licenses.len(), 2);
+        assert_eq!(report.licenses["requests"], "Apache-2.0");
+        assert_eq!(report.unknown, vec!["chardet".to_string()]);
+        assert_eq!(report.disallowed, vec!["urllib3".to_string()]);
+    }
+}
@@ -0,0 +1,133 @@
+#[cfg(feature = "python")]
+use pyo3::prelude::*;
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Build latencies kept for the percentiles.
+const LATENCY_WINDOW: usize = 1024;
+
+struct Recorder {
+    scans: u64,
+    files_tracked: usize,
+    files_seen: u64,
+    files_reused: u64,
+    latencies: VecDeque<Duration>,
+}
+
+static RECORDER: Mutex<Recorder> = Mutex::new(Recorder {
+    scans: 0,
+    files_tracked: 0,
+    files_seen: 0,
+    files_reused: 0,
+    latencies: VecDeque::new(),
+});
+
+/// Records one finished `build_dependency_map` call.
+pub(crate) fn record_build(duration: Duration, files: usize, reused_files: usize) {
+    let mut recorder = RECORDER.lock().unwrap_or_else(|e| e.into_inner());
+    recorder.scans += 1;
+    recorder.files_tracked = files;
+    recorder.files_seen += files as u64;
+    recorder.files_reused += reused_files as u64;
+    if recorder.latencies.len() == LATENCY_WINDOW {
+        recorder.latencies.pop_front();
+    }
+    recorder.latencies.push_back(duration);
+}
+
+/// Process-wide operational metrics of the builds run so far.
+#[cfg_attr(feature = "python", pyclass)]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Metrics {
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub scans: u64,
+    /// Files in the map of the latest build.
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub files_tracked: usize,
+    /// Share of files reused from `previous_map` across all builds (0 before any).
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub cache_hit_rate: f64,
+    /// Build latency percentiles in seconds over the last 1024 builds.
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub latency_p50: f64,
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub latency_p90: f64,
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub latency_p99: f64,
+}
+
+impl Metrics {
+    /// Prometheus text exposition format.
+    pub fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, samples: &[(&str, f64)]| {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} {}", name, kind);
+            for (labels, value) in samples {
+                let _ = writeln!(out, "{}{} {}", name, labels, value);
+            }
+        };
+        metric("depmap_scans_total", "counter", "Dependency map builds performed.", &[("", self.scans as f64)]);
+        metric("depmap_files_tracked", "gauge", "Files in the latest dependency map.", &[("", self.files_tracked as f64)]);
+        metric("depmap_cache_hit_ratio", "gauge", "Share of files reused from a previous map.", &[("", self.cache_hit_rate)]);
+        metric("depmap_build_duration_seconds", "summary", "Dependency map build latency.", &[
+            ("{quantile=\"0.5\"}", self.latency_p50),
+            ("{quantile=\"0.9\"}", self.latency_p90),
+            ("{quantile=\"0.99\"}", self.latency_p99),
+        ]);
+        out
+    }
+}
+
+/// Nearest-rank percentile of sorted `values`.
+fn percentile(sorted: &[f64], quantile: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = (quantile * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// Snapshot of the metrics recorded so far in this process.
+pub fn metrics() -> Metrics {
+    let recorder = RECORDER.lock().unwrap_or_else(|e| e.into_inner());
+    let mut latencies: Vec<f64> = recorder.latencies.iter().map(Duration::as_secs_f64).collect();
+    latencies.sort_by(f64::total_cmp);
+    Metrics {
+        scans: recorder.scans,
+        files_tracked: recorder.files_tracked,
+        cache_hit_rate: if recorder.files_seen == 0 {
+            0.0
+        } else {
+            recorder.files_reused as f64 / recorder.files_seen as f64
+        },
+        latency_p50: percentile(&latencies, 0.5),
+        latency_p90: percentile(&latencies, 0.9),
+        latency_p99: percentile(&latencies, 0.99),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentile_nearest_rank() {
+        let values: Vec<f64> = (1..=10).map(f64::from).collect();
+        assert_eq!(percentile(&values, 0.5), 5.0);
+        assert_eq!(percentile(&values, 0.9), 9.0);
+        assert_eq!(percentile(&values, 0.99), 10.0);
+        assert_eq!(percentile(&[], 0.5), 0.0);
+    }
+
+    #[test]
+    fn test_to_prometheus() {
+        let metrics = Metrics { scans: 3, files_tracked: 120, cache_hit_rate: 0.5, latency_p50: 0.25, ..Default::default() };
+        let text = metrics.to_prometheus();
+        assert!(text.contains("# TYPE depmap_scans_total counter\ndepmap_scans_total 3\n"));
+        assert!(text.contains("depmap_files_tracked 120\n"));
+        assert!(text.contains("depmap_build_duration_seconds{quantile=\"0.5\"} 0.25\n"));
+    }
+}
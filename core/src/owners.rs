@@ -0,0 +1,170 @@
+#[cfg(feature = "python")]
+use pyo3::prelude::*;
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::Path;
+
+use crate::{closure_paths, entry_key, helpers, ProjectFile, Result};
+
+/// One CODEOWNERS line, with the pattern rewritten for `helpers::glob_match`.
+struct Rule {
+    glob: String,
+    /// Pattern ended in `/`: matches directory contents only.
+    dir_only: bool,
+    owners: Vec<String>,
+}
+
+impl Rule {
+    fn parse(line: &str) -> Option<Rule> {
+        let line = line.split(" #").next().unwrap_or(line).trim();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let mut tokens = line.split_whitespace();
+        let pattern = tokens.next()?;
+        let dir_only = pattern.ends_with('/');
+        let trimmed = pattern.trim_end_matches('/');
+        // As in .gitignore, a pattern with a leading or inner `/` is relative to the
+        // root; otherwise it matches at any depth.
+        let anchored = trimmed.starts_with('/') || trimmed.contains('/');
+        let body = trimmed.trim_start_matches('/');
+        let glob = if anchored { body.to_string() } else { format!("**/{}", body) };
+        Some(Rule { glob, dir_only, owners: tokens.map(str::to_string).collect() })
+    }
+
+    fn matches(&self, path: &str) -> bool {
+        (!self.dir_only && helpers::glob_match(&self.glob, path))
+            || helpers::glob_match(&format!("{}/**", self.glob), path)
+    }
+}
+
+/// Parsed CODEOWNERS file; the last matching rule decides a file's owners.
+struct Codeowners {
+    rules: Vec<Rule>,
+}
+
+impl Codeowners {
+    fn parse(content: &str) -> Self {
+        Codeowners { rules: content.lines().filter_map(Rule::parse).collect() }
+    }
+
+    fn owners_of(&self, relative_path: &str) -> &[String] {
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| rule.matches(relative_path))
+            .map_or(&[], |rule| rule.owners.as_slice())
+    }
+}
+
+/// CODEOWNERS rollup of one entry point's closure.
+#[cfg_attr(feature = "python", pyclass)]
+#[derive(Clone, Debug, Default)]
+pub struct OwnershipReport {
+    /// Owner -> share of the closure's files they own. Co-owned files count for every
+    /// owner, so shares can add up to more than 1.
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub fractions: HashMap<String, f64>,
+    /// Owner -> owned files of the closure (relative to `source_root`), sorted.
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub files: HashMap<String, Vec<String>>,
+    /// Closure files no rule assigns an owner to, sorted.
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub unowned: Vec<String>,
+    /// Owners of the changed files that are part of the closure, sorted.
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub affected_owners: Vec<String>,
+}
+
+/// Rolls up the owners of `entry_point`'s closure from a CODEOWNERS file, whose
+/// patterns are matched against paths relative to `source_root`.
+///
+/// `changed_files` are paths relative to `source_root` (or absolute); their owners are
+/// reported in `affected_owners` when the files belong to the closure.
+pub fn codeowners_report(
+    dependency_map: &HashMap<String, ProjectFile>,
+    entry_point: &str,
+    source_root: &Path,
+    codeowners_path: &Path,
+    changed_files: &[String],
+) -> Result<OwnershipReport> {
+    let codeowners = Codeowners::parse(&fs::read_to_string(codeowners_path)?);
+    let entry_key = entry_key(dependency_map, entry_point, Some(source_root))?;
+    let mut closure: Vec<String> = closure_paths(dependency_map, &entry_key)
+        .iter()
+        .map(|path| helpers::relative_path_str(Path::new(path), source_root))
+        .collect();
+    closure.sort();
+
+    let mut report = OwnershipReport::default();
+    for path in &closure {
+        let owners = codeowners.owners_of(path);
+        if owners.is_empty() {
+            report.unowned.push(path.clone());
+        }
+        for owner in owners {
+            report.files.entry(owner.clone()).or_default().push(path.clone());
+        }
+    }
+    for (owner, files) in &report.files {
+        report.fractions.insert(owner.clone(), files.len() as f64 / closure.len() as f64);
+    }
+
+    let mut affected = BTreeSet::new();
+    for changed in changed_files {
+        let relative = helpers::relative_path_str(Path::new(changed), source_root);
+        if closure.binary_search(&relative).is_ok() {
+            affected.extend(codeowners.owners_of(&relative).iter().cloned());
+        }
+    }
+    report.affected_owners = affected.into_iter().collect();
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_codeowners_last_match_wins() {
+        let codeowners = Codeowners::parse(
+            "# Default owners\n* @org/platform\n*.md @org/docs\n/app/billing/ @org/payments @alice\napp/**/tests/ @org/qa # QA\n/app/generated.py\n",
+        );
+        assert_eq!(codeowners.owners_of("setup.py"), ["@org/platform"]);
+        assert_eq!(codeowners.owners_of("app/docs/README.md"), ["@org/docs"]);
+        assert_eq!(codeowners.owners_of("app/billing/invoice.py"), ["@org/payments", "@alice"]);
+        assert_eq!(codeowners.owners_of("lib/app/billing/invoice.py"), ["@org/platform"]);
+        assert_eq!(codeowners.owners_of("app/billing/tests/test_invoice.py"), ["@org/qa"]);
+        assert!(codeowners.owners_of("app/generated.py").is_empty());
+    }
+
+    #[test]
+    fn test_codeowners_report_for_closure() {
+        let dir = tempdir().unwrap();
+        let root = fs::canonicalize(dir.path()).unwrap();
+        fs::create_dir_all(root.join("app/billing")).unwrap();
+        for rel in ["app/main.py", "app/billing/invoice.py", "app/util.py", "app/unused.py"] {
+            fs::write(root.join(rel), "").unwrap();
+        }
+        fs::write(root.join("CODEOWNERS"), "/app/ @org/platform\n/app/billing/ @org/payments\n/app/util.py\n").unwrap();
+        let key = |rel: &str| root.join(rel).to_string_lossy().into_owned();
+
+        let mut map = HashMap::new();
+        map.insert(key("app/main.py"), ProjectFile {
+            project_imports: vec![key("app/billing/invoice.py"), key("app/util.py")],
+            ..Default::default()
+        });
+        map.insert(key("app/billing/invoice.py"), ProjectFile::default());
+        map.insert(key("app/util.py"), ProjectFile::default());
+        map.insert(key("app/unused.py"), ProjectFile::default());
+
+        let changed = vec!["app/billing/invoice.py".to_string(), key("app/unused.py")];
+        let report = codeowners_report(&map, "app.main", &root, &root.join("CODEOWNERS"), &changed).unwrap();
+        assert_eq!(report.files["@org/platform"], vec!["app/main.py".to_string()]);
+        assert_eq!(report.files["@org/payments"], vec!["app/billing/invoice.py".to_string()]);
+        assert!((report.fractions["@org/payments"] - 1.0 / 3.0).abs() < 1e-9);
+        assert_eq!(report.unowned, vec!["app/util.py".to_string()]);
+        assert_eq!(report.affected_owners, vec!["@org/payments".to_string()]);
+    }
+}
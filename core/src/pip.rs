@@ -0,0 +1,695 @@
+#[cfg(feature = "python")]
+use pyo3::prelude::*;
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::{Error, Result};
+use crate::helpers;
+
+#[cfg_attr(feature = "python", pyclass)]
+#[derive(Clone, Debug)]
+pub struct PipPackageInfo {
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub version: String,
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub installed_paths: Vec<String>,
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub dependencies: Vec<String>,
+    /// From the dist-info `METADATA`: `License-Expression`, else the license classifiers,
+    /// else a short `License` field.
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub license: Option<String>,
+}
+
+#[cfg_attr(feature = "python", pyclass)]
+#[derive(Clone, Debug)]
+pub struct PipMetadata {
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub import_to_pip_map: HashMap<String, String>,
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub pip_package_info_map: HashMap<String, PipPackageInfo>,
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub extra_dependencies_map: HashMap<String, Vec<String>>,
+    #[cfg_attr(feature = "python", pyo3(get))]
+    pub extra_paths_map: HashMap<String, Vec<String>>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct ManualMappings {
+    #[serde(default)]
+    import_mappings: HashMap<String, String>,
+    #[serde(default)]
+    extra_dependencies: HashMap<String, Vec<String>>,
+    #[serde(default)]
+    extra_package_paths: HashMap<String, Vec<String>>,
+}
+
+#[derive(Deserialize, Debug)]
+struct PackageDetails {
+    version: String,
+    dependencies: BTreeMap<String, PackageDetails>,
+}
+
+struct PipAnalyzer<'a> {
+    site_packages: &'a Path,
+    import_to_pip_map: HashMap<String, String>,
+    pip_package_info_map: HashMap<String, PipPackageInfo>,
+}
+
+impl<'a> PipAnalyzer<'a> {
+    fn new(site_packages: &'a Path) -> Self {
+        PipAnalyzer {
+            site_packages,
+            import_to_pip_map: HashMap::new(),
+            pip_package_info_map: HashMap::new(),
+        }
+    }
+
+    fn process_package(&mut self, package_name: &str, package_details: &PackageDetails) {
+        if let Some(existing_info) = self.pip_package_info_map.get(package_name) {
+            if !existing_info.dependencies.is_empty() && package_details.dependencies.is_empty() {
+                return;
+            }
+        }
+
+        let mut importables = HashSet::new();
+        let mut installed_artifact_paths = HashSet::new();
+        let mut license = None;
+        let dependencies: Vec<String> = package_details.dependencies.keys().cloned().collect();
+
+        if let Some(dist_dir) = find_dist_info_dir(package_name, &package_details.version, self.site_packages) {
+            if let Ok(metadata_content) = fs::read_to_string(dist_dir.join("METADATA")) {
+                license = license_from_metadata(&metadata_content);
+            }
+            if let Ok(record_content) = fs::read_to_string(dist_dir.join("RECORD")) {
+                for line in record_content.lines() {
+                    if let Some(path_str) = line.split(',').next() {
+                        if path_str.contains(".dist-info/") { continue; }
+                        if let Some(top_level) = path_str.split('/').next() {
+                            if top_level.is_empty() { continue; }
+
+                            if top_level == "bin" {
+                                installed_artifact_paths.insert(path_str.to_string());
+                            } else {
+                                installed_artifact_paths.insert(top_level.to_string());
+                            }
+                        }
+                    }
+                }
+            }
+
+            if let Ok(top_level_content) = fs::read_to_string(dist_dir.join("top_level.txt")) {
+                for name in top_level_content.lines() {
+                    if !name.trim().is_empty() { importables.insert(name.trim().to_string()); }
+                }
+            } else if !installed_artifact_paths.is_empty() {
+                for path in &installed_artifact_paths {
+                    let import_name = path.strip_suffix(".py").unwrap_or(path);
+                    if !import_name.contains('/') {
+                        importables.insert(import_name.to_string());
+                    }
+                }
+            }
+        }
+
+        for name in &importables {
+            self.import_to_pip_map.entry(name.clone()).or_insert_with(|| package_name.to_string());
+        }
+
+        let package_info = PipPackageInfo {
+            version: package_details.version.clone(),
+            installed_paths: installed_artifact_paths.into_iter().collect(),
+            dependencies,
+            license,
+        };
+        self.pip_package_info_map.insert(package_name.to_string(), package_info);
+
+        for (dep_name, dep_details) in &package_details.dependencies {
+            self.process_package(dep_name, dep_details);
+        }
+    }
+
+    fn finalize(self) -> (HashMap<String, String>, HashMap<String, PipPackageInfo>) {
+        (self.import_to_pip_map, self.pip_package_info_map)
+    }
+}
+
+
+pub fn build_pip_metadata(
+    dependency_tree_json_path: &Path,
+    site_packages: &Path,
+    manual_mapping_path: Option<&Path>,
+) -> Result<PipMetadata> {
+
+    let json_content = fs::read_to_string(dependency_tree_json_path)?;
+    let packages: BTreeMap<String, PackageDetails> = serde_json::from_str(&json_content)
+        .map_err(|e| Error::InvalidInput(e.to_string()))?;
+
+    let mut analyzer = PipAnalyzer::new(site_packages);
+
+    let mut manual_import_mappings = HashMap::new();
+    let mut manual_extra_deps = HashMap::new();
+    let mut manual_extra_paths = HashMap::new();
+
+    if let Some(path) = manual_mapping_path {
+        if path.is_file() {
+            let content = fs::read_to_string(path)?;
+            let mappings: ManualMappings = toml::from_str(&content)
+                .map_err(|e| Error::InvalidInput(e.to_string()))?;
+            manual_import_mappings = mappings.import_mappings;
+            manual_extra_deps = mappings.extra_dependencies;
+            manual_extra_paths = mappings.extra_package_paths;
+        }
+    }
+
+    analyzer.import_to_pip_map.extend(manual_import_mappings);
+
+    for (package_name, package_details) in &packages {
+        analyzer.process_package(package_name, package_details);
+    }
+
+    let (import_map, package_info_map) = analyzer.finalize();
+
+    Ok(PipMetadata {
+        import_to_pip_map: import_map,
+        pip_package_info_map: package_info_map,
+        extra_dependencies_map: manual_extra_deps,
+        extra_paths_map: manual_extra_paths,
+    })
+}
+
+
+pub fn resolve_package_set(
+    direct_packages: Vec<String>,
+    metadata: &PipMetadata,
+) -> HashMap<String, PipPackageInfo> {
+    let all_packages_info = &metadata.pip_package_info_map;
+    let extra_deps_map = &metadata.extra_dependencies_map;
+
+
+    let mut final_package_set = HashSet::new();
+    let mut processing_stack = direct_packages;
+
+    while let Some(package_name) = processing_stack.pop() {
+        if !final_package_set.insert(package_name.clone()) {
+            continue;
+        }
+
+        if let Some(package_info) = all_packages_info.get(&package_name) {
+            for dep in &package_info.dependencies {
+                processing_stack.push(dep.clone());
+            }
+        }
+
+        if let Some(extra_deps) = extra_deps_map.get(&package_name) {
+            for extra_dep in extra_deps {
+                processing_stack.push(extra_dep.clone());
+            }
+        }
+    }
+
+    final_package_set
+        .into_iter()
+        .filter_map(|name| all_packages_info.get(&name).map(|info| (name, info.clone())))
+        .collect()
+}
+
+/// `(key, value)` headers of a core metadata file. Headers end at the first blank
+/// line; the description body follows.
+fn metadata_headers(metadata: &str) -> impl Iterator<Item = (&str, &str)> {
+    metadata
+        .lines()
+        .take_while(|line| !line.trim().is_empty())
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, value)| (key, value.trim()))
+}
+
+/// License of a distribution from the headers of its core metadata file.
+fn license_from_metadata(metadata: &str) -> Option<String> {
+    let mut expression = None;
+    let mut license_field = None;
+    let mut classifiers = Vec::new();
+    for (key, value) in metadata_headers(metadata) {
+        match key {
+            "License-Expression" if !value.is_empty() => expression = Some(value.to_string()),
+            "License" if !value.is_empty() && value != "UNKNOWN" && value.len() <= 64 => {
+                license_field = Some(value.to_string())
+            }
+            "Classifier" => {
+                if let Some(name) = value.strip_prefix("License ::").and_then(|rest| rest.rsplit("::").next()) {
+                    let name = name.trim();
+                    if name != "OSI Approved" && !name.is_empty() {
+                        classifiers.push(name.to_string());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    expression.or_else(|| (!classifiers.is_empty()).then(|| classifiers.join(" OR "))).or(license_field)
+}
+
+/// Distributions (with their transitive requirements) providing `modules`.
+pub(crate) fn packages_for_imports<'a>(
+    modules: impl IntoIterator<Item = &'a String>,
+    metadata: &PipMetadata,
+) -> HashMap<String, PipPackageInfo> {
+    let direct_packages = modules
+        .into_iter()
+        .filter_map(|module| metadata.distribution_for_module(module).cloned())
+        .collect();
+    resolve_package_set(direct_packages, metadata)
+}
+
+/// PEP 503 normalized distribution name (`Foo_Bar.baz` -> `foo-bar-baz`).
+fn canonical_dist_name(name: &str) -> String {
+    let mut canonical = String::with_capacity(name.len());
+    for c in name.trim().chars() {
+        if matches!(c, '-' | '_' | '.') {
+            if !canonical.ends_with('-') {
+                canonical.push('-');
+            }
+        } else {
+            canonical.push(c.to_ascii_lowercase());
+        }
+    }
+    canonical
+}
+
+/// Distribution name of a `Requires-Dist` value, or `None` when it only applies to an extra.
+fn required_dist_name(requirement: &str) -> Option<String> {
+    let (spec, marker) = requirement.split_once(';').unwrap_or((requirement, ""));
+    if marker.contains("extra") {
+        return None;
+    }
+    let name: String = spec
+        .trim()
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        .collect();
+    (!name.is_empty()).then(|| canonical_dist_name(&name))
+}
+
+/// Importable names provided by the files listed in a `RECORD`.
+///
+/// Regular packages and top-level modules give their top-level name. Inside namespace
+/// packages (directories without `__init__.py`) the first regular package or module
+/// is used instead (`google.cloud.storage`), so distributions sharing a namespace are
+/// told apart.
+fn import_names_from_record(paths: &[&str]) -> BTreeSet<String> {
+    let files: HashSet<&str> = paths.iter().copied().collect();
+    let mut names = BTreeSet::new();
+    for path in paths {
+        let segments: Vec<&str> = path.split('/').collect();
+        let top = segments[0];
+        if top.is_empty() || top == ".." || top == "bin" || top == "__pycache__" || top.ends_with(".dist-info") || top.ends_with(".data") {
+            continue;
+        }
+        let package_depth = (1..segments.len()).find(|&depth| files.contains(format!("{}/__init__.py", segments[..depth].join("/")).as_str()));
+        match package_depth {
+            Some(depth) => {
+                names.insert(segments[..depth].join("."));
+            }
+            None => {
+                let file_name = segments[segments.len() - 1];
+                let stem = if let Some(stem) = file_name.strip_suffix(".py") {
+                    stem
+                } else if file_name.ends_with(".so") || file_name.ends_with(".pyd") {
+                    file_name.split('.').next().unwrap_or(file_name)
+                } else {
+                    continue;
+                };
+                let mut module: Vec<&str> = segments[..segments.len() - 1].to_vec();
+                module.push(stem);
+                if module.iter().all(|s| helpers::is_dotted_module_name(s)) {
+                    names.insert(module.join("."));
+                }
+            }
+        }
+    }
+    names
+}
+
+impl PipMetadata {
+    /// Distribution providing `module`, matched on the longest dotted prefix with a
+    /// known import name (`google.cloud.storage.blob` -> `google-cloud-storage`).
+    pub fn distribution_for_module(&self, module: &str) -> Option<&String> {
+        let mut candidate = module;
+        loop {
+            if let Some(dist) = self.import_to_pip_map.get(candidate) {
+                return Some(dist);
+            }
+            candidate = &candidate[..candidate.rfind('.')?];
+        }
+    }
+}
+
+/// Builds `PipMetadata` directly from the `*.dist-info` directories of an environment.
+///
+/// Distributions are keyed by their PEP 503 normalized name, with versions, licenses
+/// and non-extra `Requires-Dist` requirements read from `METADATA` and import names
+/// derived from `RECORD` (falling back to `top_level.txt`).
+pub fn build_environment_metadata(site_packages: &Path) -> Result<PipMetadata> {
+    let mut dist_dirs: Vec<PathBuf> = fs::read_dir(site_packages)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_dir() && path.extension().is_some_and(|ext| ext == "dist-info"))
+        .collect();
+    dist_dirs.sort();
+
+    let mut import_to_pip_map = HashMap::new();
+    let mut pip_package_info_map = HashMap::new();
+    for dist_dir in dist_dirs {
+        let Ok(metadata) = fs::read_to_string(dist_dir.join("METADATA")) else { continue };
+        let mut name = None;
+        let mut version = String::new();
+        let mut dependencies = BTreeSet::new();
+        for (key, value) in metadata_headers(&metadata) {
+            match key {
+                "Name" => name = Some(canonical_dist_name(value)),
+                "Version" => version = value.to_string(),
+                "Requires-Dist" => dependencies.extend(required_dist_name(value)),
+                _ => {}
+            }
+        }
+        let Some(name) = name else { continue };
+
+        let record = fs::read_to_string(dist_dir.join("RECORD")).unwrap_or_default();
+        let record_paths: Vec<&str> = record.lines().filter_map(|line| line.split(',').next()).filter(|p| !p.is_empty()).collect();
+        let mut import_names = import_names_from_record(&record_paths);
+        if import_names.is_empty() {
+            if let Ok(top_level) = fs::read_to_string(dist_dir.join("top_level.txt")) {
+                import_names.extend(top_level.lines().map(str::trim).filter(|n| !n.is_empty()).map(str::to_string));
+            }
+        }
+        for import_name in import_names {
+            import_to_pip_map.entry(import_name).or_insert_with(|| name.clone());
+        }
+
+        let installed_paths: BTreeSet<String> = record_paths
+            .iter()
+            .filter(|p| !p.contains(".dist-info/") && !p.starts_with(".."))
+            .map(|p| if p.starts_with("bin/") { p.to_string() } else { p.split('/').next().unwrap_or(p).to_string() })
+            .collect();
+        pip_package_info_map.insert(name, PipPackageInfo {
+            version,
+            installed_paths: installed_paths.into_iter().collect(),
+            dependencies: dependencies.into_iter().collect(),
+            license: license_from_metadata(&metadata),
+        });
+    }
+
+    Ok(PipMetadata {
+        import_to_pip_map,
+        pip_package_info_map,
+        extra_dependencies_map: HashMap::new(),
+        extra_paths_map: HashMap::new(),
+    })
+}
+
+fn normalize_pkg_name(name: &str) -> String {
+    name.to_lowercase().replace('-', "_")
+}
+
+fn find_dist_info_dir(
+    package_name: &str,
+    version: &str,
+    site_packages: &Path,
+) -> Option<PathBuf> {
+
+    let mut possible_names = HashSet::new();
+
+    possible_names.insert(package_name.to_string());
+    possible_names.insert(package_name.replace('-', "_"));
+    possible_names.insert(package_name.replace('-', "."));
+
+    let mut c = package_name.chars();
+    let capitalized_name = match c.next() {
+        None => package_name.to_string(),
+        Some(f) => f.to_uppercase().collect::<String>() + c.as_str(),
+    };
+    possible_names.insert(capitalized_name.clone());
+    possible_names.insert(capitalized_name.replace('-', "_"));
+    possible_names.insert(capitalized_name.replace('-', "."));
+
+    for name in possible_names {
+        let dir_name = format!("{}-{}.dist-info", name, version);
+        let path = site_packages.join(&dir_name);
+        if path.is_dir() {
+            return Some(path);
+        }
+    }
+
+    let normalized_input_name = normalize_pkg_name(package_name);
+    let version_suffix = format!("-{}.dist-info", version);
+
+    let entries = match fs::read_dir(site_packages) {
+        Ok(entries) => entries,
+        Err(_) => return None,
+    };
+
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if !path.is_dir() { continue; }
+
+        let dir_name_str = match entry.file_name().into_string() {
+            Ok(s) => s,
+            Err(_) => continue,
+        };
+
+        if dir_name_str.ends_with(&version_suffix) {
+
+            if let Some(actual_name) = dir_name_str.strip_suffix(&version_suffix) {
+
+                let normalized_actual_name = normalize_pkg_name(actual_name);
+
+                if normalized_actual_name == normalized_input_name {
+                    return Some(path);
+                }
+            }
+        }
+    }
+    None
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{self, File};
+    use std::io::Write;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_normalize_pkg_name() {
+        assert_eq!(normalize_pkg_name("CairoSVG"), "cairosvg");
+        assert_eq!(normalize_pkg_name("google-api-core"), "google_api_core");
+        assert_eq!(normalize_pkg_name("Babel"), "babel");
+    }
+
+    #[test]
+    fn test_license_from_metadata() {
+        let expression = "Metadata-Version: 2.4\nName: demo\nLicense-Expression: MIT OR Apache-2.0\nClassifier: License :: OSI Approved :: MIT License\n";
+        assert_eq!(license_from_metadata(expression).as_deref(), Some("MIT OR Apache-2.0"));
+
+        let classifier = "Name: demo\nLicense: UNKNOWN\nClassifier: License :: OSI Approved :: BSD License\n\nClassifier: License :: body text\n";
+        assert_eq!(license_from_metadata(classifier).as_deref(), Some("BSD License"));
+
+        assert_eq!(license_from_metadata("Name: demo\nLicense: LGPL-3.0-only\n").as_deref(), Some("LGPL-3.0-only"));
+        assert_eq!(license_from_metadata("Name: demo\n"), None);
+    }
+
+    #[test]
+    fn test_import_names_from_record_splits_namespace_packages() {
+        let storage = [
+            "google/cloud/storage/__init__.py",
+            "google/cloud/storage/blob.py",
+            "google_cloud_storage-2.16.0.dist-info/RECORD",
+        ];
+        assert_eq!(import_names_from_record(&storage), BTreeSet::from(["google.cloud.storage".to_string()]));
+
+        let regular = ["yaml/__init__.py", "yaml/loader.py", "_yaml.cpython-312-x86_64-linux-gnu.so", "six.py", "../../bin/tool", "bin/tool"];
+        assert_eq!(
+            import_names_from_record(&regular),
+            BTreeSet::from(["_yaml".to_string(), "six".to_string(), "yaml".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_build_environment_metadata_from_dist_info() {
+        let dir = tempdir().unwrap();
+        let site = dir.path();
+        let write_dist = |dir_name: &str, metadata: &str, record: &str| {
+            let dist = site.join(dir_name);
+            fs::create_dir(&dist).unwrap();
+            fs::write(dist.join("METADATA"), metadata).unwrap();
+            fs::write(dist.join("RECORD"), record).unwrap();
+        };
+        write_dist(
+            "google_cloud_storage-2.16.0.dist-info",
+            "Name: google-cloud-storage\nVersion: 2.16.0\nRequires-Dist: google-api-core (>=2.15)\nRequires-Dist: protobuf ; extra == \"protobuf\"\n",
+            "google/cloud/storage/__init__.py,sha256=x,1\n",
+        );
+        write_dist(
+            "google_api_core-2.19.0.dist-info",
+            "Name: google_api_core\nVersion: 2.19.0\nLicense: Apache 2.0\n",
+            "google/api_core/__init__.py,sha256=x,1\n",
+        );
+
+        let metadata = build_environment_metadata(site).unwrap();
+        assert_eq!(metadata.distribution_for_module("google.cloud.storage.blob").map(String::as_str), Some("google-cloud-storage"));
+        assert_eq!(metadata.distribution_for_module("google.api_core").map(String::as_str), Some("google-api-core"));
+        assert_eq!(metadata.distribution_for_module("google"), None);
+
+        let storage = &metadata.pip_package_info_map["google-cloud-storage"];
+        assert_eq!(storage.version, "2.16.0");
+        assert_eq!(storage.dependencies, vec!["google-api-core".to_string()]);
+        assert_eq!(metadata.pip_package_info_map["google-api-core"].license.as_deref(), Some("Apache 2.0"));
+        assert_eq!(
+            resolve_package_set(vec!["google-cloud-storage".to_string()], &metadata).len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_find_dist_info_dir_fast_path() {
+        let dir = tempdir().unwrap();
+        let site_packages = dir.path();
+
+        let pkg_dir = site_packages.join("requests-2.32.5.dist-info");
+        fs::create_dir(&pkg_dir).unwrap();
+
+        let found = find_dist_info_dir("requests", "2.32.5", site_packages);
+
+        let found_canon = found.map(|p| fs::canonicalize(p).unwrap());
+        let pkg_dir_canon = Some(fs::canonicalize(pkg_dir).unwrap());
+
+        assert_eq!(found_canon, pkg_dir_canon);
+    }
+
+    #[test]
+    fn test_find_dist_info_dir_slow_path_capitalized() {
+        let dir = tempdir().unwrap();
+        let site_packages = dir.path();
+
+        let pkg_dir = site_packages.join("CairoSVG-2.7.0.dist-info");
+        fs::create_dir(&pkg_dir).unwrap();
+
+        let found = find_dist_info_dir("cairosvg", "2.7.0", site_packages);
+
+        let found_canon = found.map(|p| fs::canonicalize(p).unwrap());
+        let pkg_dir_canon = Some(fs::canonicalize(pkg_dir).unwrap());
+
+        assert_eq!(found_canon, pkg_dir_canon);
+    }
+
+    #[test]
+    fn test_find_dist_info_dir_not_found() {
+        let dir = tempdir().unwrap();
+        let site_packages = dir.path();
+
+        let found = find_dist_info_dir("nonexistent", "1.0.0", site_packages);
+        assert_eq!(found, None);
+    }
+
+    #[test]
+    fn test_pip_analyzer_process_package_with_record() {
+        let dir = tempdir().unwrap();
+        let site_packages = dir.path();
+
+        let dist_info = site_packages.join("test_pkg-1.0.0.dist-info");
+        fs::create_dir(&dist_info).unwrap();
+
+        let record_path = dist_info.join("RECORD");
+        let mut record_file = File::create(record_path).unwrap();
+        writeln!(record_file, "test_pkg/__init__.py,sha256=...,100").unwrap();
+        writeln!(record_file, "bin/test-cli,sha256=...,200").unwrap();
+        writeln!(record_file, "test_pkg-1.0.0.dist-info/METADATA,sha256=...,300").unwrap();
+
+        let mut analyzer = PipAnalyzer::new(site_packages);
+        let details = PackageDetails {
+            version: "1.0.0".to_string(),
+            dependencies: BTreeMap::new(),
+        };
+
+        analyzer.process_package("test-pkg", &details);
+
+        let info = analyzer.pip_package_info_map.get("test-pkg").unwrap();
+        assert_eq!(info.version, "1.0.0");
+        assert!(info.installed_paths.contains(&"test_pkg".to_string()));
+        assert!(info.installed_paths.contains(&"bin/test-cli".to_string()));
+        assert!(!info.installed_paths.iter().any(|p| p.contains(".dist-info")));
+        assert_eq!(analyzer.import_to_pip_map.get("test_pkg"), Some(&"test-pkg".to_string()));
+    }
+
+    #[test]
+    fn test_recursive_dependency_analysis() {
+        let dir = tempdir().unwrap();
+        let site_packages = dir.path();
+
+        let setup_pkg = |name: &str, version: &str| {
+            let dist = site_packages.join(format!("{}-{}.dist-info", name, version));
+            fs::create_dir(&dist).unwrap();
+            let mut record = File::create(dist.join("RECORD")).unwrap();
+            writeln!(record, "{}/__init__.py,sha256=...,100", name).unwrap();
+        };
+
+        setup_pkg("pkg_a", "1.0");
+        setup_pkg("pkg_b", "2.0");
+        setup_pkg("pkg_c", "3.0");
+
+        let deps_c = BTreeMap::new();
+        let details_c = PackageDetails { version: "3.0".to_string(), dependencies: deps_c };
+
+        let mut deps_b = BTreeMap::new();
+        deps_b.insert("pkg_c".to_string(), details_c);
+        let details_b = PackageDetails { version: "2.0".to_string(), dependencies: deps_b };
+
+        let mut deps_a_real = BTreeMap::new();
+        deps_a_real.insert("pkg_b".to_string(), details_b);
+
+        let details_a = PackageDetails { version: "1.0".to_string(), dependencies: deps_a_real };
+
+        let mut analyzer = PipAnalyzer::new(site_packages);
+
+        analyzer.process_package("pkg_a", &details_a);
+        assert!(analyzer.pip_package_info_map.contains_key("pkg_a"));
+        assert!(analyzer.pip_package_info_map.contains_key("pkg_b"));
+        assert!(analyzer.pip_package_info_map.contains_key("pkg_c"));
+        assert_eq!(analyzer.pip_package_info_map.get("pkg_c").unwrap().version, "3.0");
+    }
+
+    #[test]
+    fn test_build_pip_metadata_with_manual_mappings() {
+        let dir = tempdir().unwrap();
+        let site_packages = dir.path();
+
+        let json_path = dir.path().join("tree.json");
+        fs::write(&json_path, "{}").unwrap();
+
+        let toml_path = dir.path().join("mappings.toml");
+        let toml_content = r#"
+            [import_mappings]
+            "slack" = "slackclient"
+
+            [extra_dependencies]
+            "pydantic" = ["email-validator"]
+
+            [extra_package_paths]
+            "gremlinpython" = ["bin", "lib"]
+        "#;
+        fs::write(&toml_path, toml_content).unwrap();
+
+        let metadata = build_pip_metadata(&json_path, site_packages, Some(&toml_path)).unwrap();
+
+        assert_eq!(metadata.import_to_pip_map.get("slack"), Some(&"slackclient".to_string()));
+
+        let extra_deps = metadata.extra_dependencies_map.get("pydantic").unwrap();
+        assert!(extra_deps.contains(&"email-validator".to_string()));
+
+        let extra_paths = metadata.extra_paths_map.get("gremlinpython").unwrap();
+        assert!(extra_paths.contains(&"bin".to_string()));
+        assert!(extra_paths.contains(&"lib".to_string()));
+    }
+}
@@ -0,0 +1,123 @@
+use pyo3::exceptions::PyKeyError;
+use pyo3::prelude::*;
+use pyo3::types::{PyFrozenSet, PyList, PyTuple};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::{FrozenMap, GraphFileResult, GraphLimits, ImportsContainer, ProjectFile, ReverseIndex, SkippedFile};
+
+impl ImportsContainer {
+    fn to_python<'py>(self, py: Python<'py>, items: &[String]) -> PyResult<Bound<'py, PyAny>> {
+        Ok(match self {
+            ImportsContainer::List => PyList::new(py, items)?.into_any(),
+            ImportsContainer::Tuple => PyTuple::new(py, items)?.into_any(),
+            ImportsContainer::FrozenSet => PyFrozenSet::new(py, items)?.into_any(),
+        })
+    }
+}
+
+#[pymethods]
+impl ProjectFile {
+    #[getter]
+    fn project_imports<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        self.imports_container.to_python(py, &self.project_imports)
+    }
+
+    #[getter]
+    fn stdlib_imports<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        self.imports_container.to_python(py, &self.stdlib_imports)
+    }
+
+    #[getter]
+    fn third_party_imports<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        self.imports_container.to_python(py, &self.third_party_imports)
+    }
+
+    #[getter]
+    fn import_kinds(&self) -> HashMap<String, &'static str> {
+        self.import_kinds.iter().map(|(module, kind)| (module.clone(), kind.as_str())).collect()
+    }
+}
+
+#[pymethods]
+impl GraphFileResult {
+    #[getter]
+    fn stdlib_imports<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        self.imports_container.to_python(py, &self.stdlib_imports)
+    }
+
+    #[getter]
+    fn third_party_imports<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        self.imports_container.to_python(py, &self.third_party_imports)
+    }
+}
+
+#[pymethods]
+impl SkippedFile {
+    #[getter]
+    fn reason(&self) -> &'static str {
+        self.reason.as_str()
+    }
+}
+
+#[pymethods]
+impl ReverseIndex {
+    #[pyo3(name = "affected_entry_points")]
+    fn py_affected_entry_points(&self, changed_files: Vec<String>) -> Vec<String> {
+        self.affected_entry_points(&changed_files)
+    }
+
+    #[pyo3(name = "direct_importers")]
+    fn py_direct_importers(&self, path: &str) -> Vec<String> {
+        self.direct_importers(path).to_vec()
+    }
+}
+
+/// Queries release the GIL, so threads can query one map concurrently.
+#[pymethods]
+impl FrozenMap {
+    fn __len__(&self) -> usize {
+        self.files().len()
+    }
+
+    fn __contains__(&self, path: &str) -> bool {
+        self.files().contains_key(path)
+    }
+
+    fn __getitem__(&self, path: &str) -> PyResult<ProjectFile> {
+        self.files().get(path).cloned().ok_or_else(|| PyKeyError::new_err(path.to_string()))
+    }
+
+    fn __copy__(&self) -> FrozenMap {
+        self.clone()
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.files().keys().cloned().collect()
+    }
+
+    fn to_dict(&self) -> HashMap<String, ProjectFile> {
+        self.files().clone()
+    }
+
+    #[pyo3(signature = (entry_point, source_root=None, max_nodes=None, max_edges=None, max_seconds=None, provenance=false))]
+    fn get_dependency_graph(
+        &self,
+        py: Python<'_>,
+        entry_point: &str,
+        source_root: Option<String>,
+        max_nodes: Option<usize>,
+        max_edges: Option<usize>,
+        max_seconds: Option<f64>,
+        provenance: bool,
+    ) -> PyResult<HashMap<String, GraphFileResult>> {
+        let limits = GraphLimits { max_nodes, max_edges, max_duration: max_seconds.map(Duration::from_secs_f64) };
+        let source_root = source_root.as_deref().map(Path::new);
+        Ok(py.allow_threads(|| crate::get_dependency_graph(self.files(), entry_point, source_root, &limits, provenance))?)
+    }
+
+    fn find_dependents(&self, py: Python<'_>, changed_file_paths: Vec<String>) -> HashSet<String> {
+        py.allow_threads(|| crate::find_dependents(self.files(), changed_file_paths))
+    }
+}
@@ -0,0 +1,156 @@
+#[cfg(feature = "python")]
+use pyo3::prelude::*;
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+
+use crate::{closure_paths, entry_key, ProjectFile, Result};
+
+/// Precomputed reverse adjacency and closure membership of a map, so change-set
+/// queries cost time proportional to the change set instead of a closure walk per
+/// entry point. Rebuild it whenever the map changes.
+#[cfg_attr(feature = "python", pyclass)]
+#[derive(Clone, Debug, Default)]
+pub struct ReverseIndex {
+    /// Entry points as given, indexing the membership bits.
+    entry_points: Vec<String>,
+    /// File -> files importing it directly, sorted.
+    importers: HashMap<String, Vec<String>>,
+    /// File -> bitmap of the entry points whose closure contains it.
+    membership: HashMap<String, Vec<u64>>,
+}
+
+impl ReverseIndex {
+    pub fn new(
+        dependency_map: &HashMap<String, ProjectFile>,
+        entry_points: &[String],
+        source_root: Option<&Path>,
+    ) -> Result<Self> {
+        let mut importers: HashMap<String, Vec<String>> = HashMap::new();
+        for (path, info) in dependency_map {
+            for target in &info.project_imports {
+                importers.entry(target.clone()).or_default().push(path.clone());
+            }
+        }
+        for sources in importers.values_mut() {
+            sources.sort();
+            sources.dedup();
+        }
+
+        let words = entry_points.len().div_ceil(64);
+        let mut membership: HashMap<String, Vec<u64>> = HashMap::new();
+        for (index, entry_point) in entry_points.iter().enumerate() {
+            let entry_key = entry_key(dependency_map, entry_point, source_root)?;
+            for path in closure_paths(dependency_map, &entry_key) {
+                membership.entry(path).or_insert_with(|| vec![0; words])[index / 64] |= 1 << (index % 64);
+            }
+        }
+        Ok(ReverseIndex { entry_points: entry_points.to_vec(), importers, membership })
+    }
+
+    /// Entry points whose closure contains any of `changed_files` (map keys), in the
+    /// order they were indexed. Unknown files are ignored.
+    pub fn affected_entry_points(&self, changed_files: &[String]) -> Vec<String> {
+        let mut affected = vec![0u64; self.entry_points.len().div_ceil(64)];
+        for bits in changed_files.iter().filter_map(|path| self.membership.get(path)) {
+            for (word, bit) in affected.iter_mut().zip(bits) {
+                *word |= bit;
+            }
+        }
+        self.entry_points
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| affected[index / 64] & (1 << (index % 64)) != 0)
+            .map(|(_, entry_point)| entry_point.clone())
+            .collect()
+    }
+
+    /// Files importing `path` directly, sorted.
+    pub fn direct_importers(&self, path: &str) -> &[String] {
+        self.importers.get(path).map_or(&[], Vec::as_slice)
+    }
+}
+
+/// Every file importing any of `changed_files` directly or transitively, mapped to the
+/// changed file it is the fewest imports away from (the earliest in `changed_files`
+/// on a tie). Changed files only appear when they import another changed file.
+pub fn get_reverse_dependencies(
+    dependency_map: &HashMap<String, ProjectFile>,
+    changed_files: &[String],
+) -> HashMap<String, String> {
+    let mut importers: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (path, info) in dependency_map {
+        for target in &info.project_imports {
+            importers.entry(target.as_str()).or_default().push(path.as_str());
+        }
+    }
+    // Breadth-first from all changed files at once: each level stays ordered by the
+    // changed file it started from, so the first to reach a file is the nearest one.
+    let mut affected = HashMap::new();
+    let mut queue: VecDeque<(&str, &str)> = changed_files.iter().map(|path| (path.as_str(), path.as_str())).collect();
+    while let Some((current, origin)) = queue.pop_front() {
+        for &importer in importers.get(current).into_iter().flatten() {
+            if !affected.contains_key(importer) {
+                affected.insert(importer.to_string(), origin.to_string());
+                queue.push_back((importer, origin));
+            }
+        }
+    }
+    affected
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(imports: &[&str]) -> ProjectFile {
+        ProjectFile { project_imports: imports.iter().map(|i| i.to_string()).collect(), ..Default::default() }
+    }
+
+    #[test]
+    fn test_reverse_index_matches_closures() {
+        let mut map = HashMap::new();
+        map.insert("api.py".to_string(), file(&["db.py", "util.py"]));
+        map.insert("worker.py".to_string(), file(&["util.py"]));
+        map.insert("db.py".to_string(), file(&["util.py"]));
+        map.insert("util.py".to_string(), file(&[]));
+        // Enough entry points to span two bitmap words.
+        let mut entry_points = vec!["api.py".to_string(), "worker.py".to_string()];
+        for i in 0..70 {
+            map.insert(format!("job{}.py", i), file(&[]));
+            entry_points.push(format!("job{}.py", i));
+        }
+        entry_points.push("db.py".to_string());
+
+        let index = ReverseIndex::new(&map, &entry_points, None).unwrap();
+        assert_eq!(index.direct_importers("util.py"), ["api.py", "db.py", "worker.py"]);
+        assert!(index.direct_importers("api.py").is_empty());
+        assert_eq!(index.affected_entry_points(&["db.py".to_string()]), vec!["api.py", "db.py"]);
+        assert_eq!(
+            index.affected_entry_points(&["job69.py".to_string(), "worker.py".to_string(), "gone.py".to_string()]),
+            vec!["worker.py", "job69.py"]
+        );
+        assert_eq!(index.affected_entry_points(&["util.py".to_string()]), vec!["api.py", "worker.py", "db.py"]);
+    }
+
+    #[test]
+    fn test_get_reverse_dependencies_maps_to_nearest_change() {
+        let mut map = HashMap::new();
+        map.insert("test_api.py".to_string(), file(&["api.py"]));
+        map.insert("api.py".to_string(), file(&["db.py", "util.py"]));
+        map.insert("db.py".to_string(), file(&["util.py"]));
+        map.insert("util.py".to_string(), file(&[]));
+        map.insert("other.py".to_string(), file(&[]));
+
+        let affected = get_reverse_dependencies(&map, &["util.py".to_string(), "db.py".to_string()]);
+        let mut expected = HashMap::new();
+        expected.insert("api.py".to_string(), "util.py".to_string());
+        expected.insert("db.py".to_string(), "util.py".to_string());
+        expected.insert("test_api.py".to_string(), "util.py".to_string());
+        assert_eq!(affected, expected);
+
+        let affected = get_reverse_dependencies(&map, &["db.py".to_string(), "gone.py".to_string()]);
+        assert_eq!(affected.len(), 2);
+        assert_eq!(affected["test_api.py"], "db.py");
+        assert!(get_reverse_dependencies(&map, &["test_api.py".to_string()]).is_empty());
+    }
+}
@@ -0,0 +1,145 @@
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use crate::{closure_paths, Error, ProjectFile, Result};
+
+#[derive(Deserialize)]
+struct CoverageReport {
+    files: BTreeMap<String, CoverageFile>,
+}
+
+#[derive(Deserialize)]
+struct CoverageFile {
+    #[serde(default)]
+    executed_lines: Vec<u64>,
+}
+
+/// Files with at least one executed line in a `coverage json` report. Relative paths
+/// are resolved against `base_dir` (the directory coverage ran from).
+pub fn load_coverage_json(report_path: &Path, base_dir: Option<&Path>) -> Result<Vec<String>> {
+    let content = fs::read_to_string(report_path)?;
+    let report: CoverageReport = serde_json::from_str(&content)
+        .map_err(|e| Error::InvalidInput(format!("invalid coverage JSON report: {}", e)))?;
+    Ok(report
+        .files
+        .into_iter()
+        .filter(|(_, file)| !file.executed_lines.is_empty())
+        .map(|(path, _)| match base_dir {
+            Some(base) if Path::new(&path).is_relative() => base.join(path).to_string_lossy().into_owned(),
+            _ => path,
+        })
+        .collect())
+}
+
+/// Key under which `path` is stored in the map, trying the canonical form as a fallback.
+fn map_key(dependency_map: &HashMap<String, ProjectFile>, path: &str) -> Option<String> {
+    if dependency_map.contains_key(path) {
+        return Some(path.to_string());
+    }
+    let canonical = fs::canonicalize(path).ok()?.to_string_lossy().into_owned();
+    dependency_map.contains_key(&canonical).then_some(canonical)
+}
+
+/// Merges runtime-observed files (per entry point) into a copy of the map.
+///
+/// Executed files outside the entry point's static closure become `dynamic_imports`
+/// of the entry point (and are added to its `project_imports`). Static edges whose
+/// both ends were executed are recorded in `observed_imports`.
+pub fn merge_runtime_dependencies(
+    dependency_map: &HashMap<String, ProjectFile>,
+    executed_files: &HashMap<String, Vec<String>>,
+) -> Result<HashMap<String, ProjectFile>> {
+    let mut merged = dependency_map.clone();
+
+    for (entry_point, files) in executed_files {
+        let entry_key = map_key(dependency_map, entry_point).ok_or_else(|| {
+            Error::InvalidInput(format!("entry point '{}' is not part of the dependency map", entry_point))
+        })?;
+        let closure = closure_paths(dependency_map, &entry_key);
+        let executed: HashSet<String> = files.iter().filter_map(|f| map_key(dependency_map, f)).collect();
+
+        for file in &closure {
+            if !executed.contains(file) {
+                continue;
+            }
+            let observed: Vec<String> = dependency_map[file]
+                .project_imports
+                .iter()
+                .filter(|dst| executed.contains(*dst))
+                .cloned()
+                .collect();
+            let entry = merged.get_mut(file).expect("closure paths come from the map");
+            for dst in observed {
+                if !entry.observed_imports.contains(&dst) {
+                    entry.observed_imports.push(dst);
+                }
+            }
+        }
+
+        let mut dynamic: Vec<&String> = executed.iter().filter(|f| !closure.contains(*f)).collect();
+        dynamic.sort();
+        let entry = merged.get_mut(&entry_key).expect("entry key comes from the map");
+        for file in dynamic {
+            if !entry.dynamic_imports.contains(file) {
+                entry.dynamic_imports.push(file.clone());
+            }
+            if !entry.project_imports.contains(file) {
+                entry.project_imports.push(file.clone());
+            }
+        }
+    }
+    Ok(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(imports: &[&str]) -> ProjectFile {
+        ProjectFile {
+            project_imports: imports.iter().map(|s| s.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_merge_runtime_dependencies_tags_edges() {
+        let mut map = HashMap::new();
+        map.insert("main.py".to_string(), file(&["a.py", "cold.py"]));
+        map.insert("a.py".to_string(), file(&[]));
+        map.insert("cold.py".to_string(), file(&[]));
+        map.insert("plugin.py".to_string(), file(&[]));
+
+        let mut executed = HashMap::new();
+        executed.insert(
+            "main.py".to_string(),
+            vec!["main.py".to_string(), "a.py".to_string(), "plugin.py".to_string(), "/not/in/map.py".to_string()],
+        );
+
+        let merged = merge_runtime_dependencies(&map, &executed).unwrap();
+        let main = &merged["main.py"];
+        assert_eq!(main.dynamic_imports, vec!["plugin.py".to_string()]);
+        assert!(main.project_imports.contains(&"plugin.py".to_string()));
+        assert_eq!(main.observed_imports, vec!["a.py".to_string()]);
+
+        let mut unknown = HashMap::new();
+        unknown.insert("missing.py".to_string(), vec![]);
+        assert!(merge_runtime_dependencies(&map, &unknown).is_err());
+    }
+
+    #[test]
+    fn test_load_coverage_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let report = dir.path().join("coverage.json");
+        fs::write(
+            &report,
+            r#"{"meta": {}, "files": {"app/main.py": {"executed_lines": [1, 2]}, "app/unused.py": {"executed_lines": []}}}"#,
+        )
+        .unwrap();
+
+        let files = load_coverage_json(&report, Some(Path::new("/repo"))).unwrap();
+        assert_eq!(files, vec!["/repo/app/main.py".to_string()]);
+    }
+}
@@ -0,0 +1,208 @@
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::Path;
+
+use crate::{closure_paths, entry_key, helpers, resolve_package_set, PipMetadata, ProjectFile, Result};
+
+/// Package URL of a PyPI distribution (`pkg:pypi/typing-extensions@4.12.2`).
+fn pypi_purl(name: &str, version: &str) -> String {
+    format!("pkg:pypi/{}@{}", name.to_lowercase().replace('_', "-"), version)
+}
+
+/// CycloneDX 1.5 JSON SBOM for the closure of `entry_point`.
+///
+/// First-party files become `file` components with their SHA-256 hash. Third-party
+/// imports are correlated with distributions through `pip_metadata` (including their
+/// transitive requirements); imports it cannot map, or all of them when no metadata is
+/// given, are listed as unversioned `library` components named after the import.
+pub fn cyclonedx_sbom(
+    dependency_map: &HashMap<String, ProjectFile>,
+    entry_point: &str,
+    source_root: Option<&Path>,
+    pip_metadata: Option<&PipMetadata>,
+) -> Result<String> {
+    let entry_key = entry_key(dependency_map, entry_point, source_root)?;
+    let closure: BTreeSet<String> = closure_paths(dependency_map, &entry_key).into_iter().collect();
+    let file_name = |path: &str| match source_root {
+        Some(root) => helpers::relative_path_str(Path::new(path), root),
+        None => path.to_string(),
+    };
+
+    // Third-party module -> bom-ref of the component it maps to.
+    let mut import_refs: BTreeMap<String, String> = BTreeMap::new();
+    let mut library_components: BTreeMap<String, Value> = BTreeMap::new();
+    let mut library_dependencies: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+    let modules: BTreeSet<&String> =
+        closure.iter().flat_map(|path| dependency_map[path].third_party_module_names()).collect();
+    let mut direct_packages = Vec::new();
+    for module in &modules {
+        match pip_metadata.and_then(|m| m.distribution_for_module(module)) {
+            Some(package) => direct_packages.push(package.clone()),
+            None => {
+                let name = module.split('.').next().unwrap_or(module.as_str());
+                let bom_ref = format!("import:{}", name);
+                library_components.entry(bom_ref.clone()).or_insert_with(|| json!({
+                    "type": "library",
+                    "bom-ref": bom_ref,
+                    "name": name,
+                }));
+                import_refs.insert(module.to_string(), bom_ref);
+            }
+        }
+    }
+    if let Some(metadata) = pip_metadata {
+        let packages = resolve_package_set(direct_packages, metadata);
+        for (package, info) in &packages {
+            let purl = pypi_purl(package, &info.version);
+            let mut component = json!({
+                "type": "library",
+                "bom-ref": purl,
+                "name": package,
+                "version": info.version,
+                "purl": purl,
+            });
+            if let Some(license) = &info.license {
+                component["licenses"] = json!([{"license": {"name": license}}]);
+            }
+            library_components.insert(purl.clone(), component);
+            let mut depends_on: Vec<String> = info
+                .dependencies
+                .iter()
+                .filter_map(|dep| packages.get(dep).map(|dep_info| pypi_purl(dep, &dep_info.version)))
+                .collect();
+            depends_on.sort();
+            library_dependencies.insert(purl, depends_on);
+        }
+        for module in &modules {
+            if let Some(package) = metadata.distribution_for_module(module) {
+                if let Some(info) = packages.get(package) {
+                    import_refs.insert(module.to_string(), pypi_purl(package, &info.version));
+                }
+            }
+        }
+    }
+
+    let mut components: Vec<Value> = Vec::new();
+    let mut dependencies: Vec<Value> = Vec::new();
+    for path in &closure {
+        let info = &dependency_map[path];
+        if path != &entry_key {
+            components.push(json!({
+                "type": "file",
+                "bom-ref": path,
+                "name": file_name(path),
+                "hashes": [{"alg": "SHA-256", "content": info.hash}],
+            }));
+        }
+        let mut depends_on: Vec<&String> = info.project_imports.iter().filter(|p| closure.contains(*p)).collect();
+        depends_on.extend(info.third_party_module_names().iter().filter_map(|module| import_refs.get(module)));
+        depends_on.sort();
+        depends_on.dedup();
+        dependencies.push(json!({"ref": path, "dependsOn": depends_on}));
+    }
+    components.extend(library_components.into_values());
+    for (bom_ref, depends_on) in library_dependencies {
+        dependencies.push(json!({"ref": bom_ref, "dependsOn": depends_on}));
+    }
+
+    let entry = &dependency_map[&entry_key];
+    let sbom = json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": "1.5",
+        "version": 1,
+        "metadata": {
+            "tools": {"components": [{"type": "application", "name": env!("CARGO_PKG_NAME"), "version": env!("CARGO_PKG_VERSION")}]},
+            "component": {
+                "type": "application",
+                "bom-ref": entry_key,
+                "name": file_name(&entry_key),
+                "hashes": [{"alg": "SHA-256", "content": entry.hash}],
+            },
+        },
+        "components": components,
+        "dependencies": dependencies,
+    });
+    Ok(sbom.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PipPackageInfo;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_cyclonedx_sbom_covers_closure_and_distributions() {
+        let dir = tempdir().unwrap();
+        let root = fs::canonicalize(dir.path()).unwrap();
+        fs::create_dir(root.join("app")).unwrap();
+        for name in ["main.py", "db.py", "unused.py"] {
+            fs::write(root.join("app").join(name), "").unwrap();
+        }
+        let key = |rel: &str| root.join(rel).to_string_lossy().into_owned();
+
+        let mut map = HashMap::new();
+        map.insert(key("app/main.py"), ProjectFile {
+            hash: "h-main".to_string(),
+            project_imports: vec![key("app/db.py")],
+            third_party_imports: vec!["yaml".to_string()],
+            ..Default::default()
+        });
+        map.insert(key("app/db.py"), ProjectFile {
+            hash: "h-db".to_string(),
+            third_party_imports: vec!["sqlalchemy".to_string(), "mystery".to_string()],
+            ..Default::default()
+        });
+        map.insert(key("app/unused.py"), ProjectFile { hash: "h-unused".to_string(), ..Default::default() });
+
+        let package = |version: &str, deps: &[&str]| PipPackageInfo {
+            version: version.to_string(),
+            installed_paths: Vec::new(),
+            dependencies: deps.iter().map(|d| d.to_string()).collect(),
+            license: Some("MIT".to_string()),
+        };
+        let metadata = PipMetadata {
+            import_to_pip_map: HashMap::from([
+                ("yaml".to_string(), "PyYAML".to_string()),
+                ("sqlalchemy".to_string(), "SQLAlchemy".to_string()),
+            ]),
+            pip_package_info_map: HashMap::from([
+                ("PyYAML".to_string(), package("6.0.1", &[])),
+                ("SQLAlchemy".to_string(), package("2.0.30", &["greenlet"])),
+                ("greenlet".to_string(), package("3.0.3", &[])),
+            ]),
+            extra_dependencies_map: HashMap::new(),
+            extra_paths_map: HashMap::new(),
+        };
+
+        let sbom: Value =
+            serde_json::from_str(&cyclonedx_sbom(&map, "app.main", Some(&root), Some(&metadata)).unwrap()).unwrap();
+        assert_eq!(sbom["bomFormat"], "CycloneDX");
+        assert_eq!(sbom["metadata"]["component"]["name"], "app/main.py");
+
+        let components = sbom["components"].as_array().unwrap();
+        let refs: Vec<String> = components.iter().map(|c| c["bom-ref"].as_str().unwrap().to_string()).collect();
+        assert_eq!(refs, vec![
+            key("app/db.py"),
+            "import:mystery".to_string(),
+            "pkg:pypi/greenlet@3.0.3".to_string(),
+            "pkg:pypi/pyyaml@6.0.1".to_string(),
+            "pkg:pypi/sqlalchemy@2.0.30".to_string(),
+        ]);
+        assert_eq!(components[0]["name"], "app/db.py");
+        assert_eq!(components[0]["hashes"][0]["content"], "h-db");
+        assert_eq!(components[3]["version"], "6.0.1");
+        assert_eq!(components[3]["licenses"][0]["license"]["name"], "MIT");
+
+        let db_deps = sbom["dependencies"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|d| d["ref"] == key("app/db.py"))
+            .unwrap()["dependsOn"]
+            .clone();
+        assert_eq!(db_deps, json!(["import:mystery", "pkg:pypi/sqlalchemy@2.0.30"]));
+    }
+}
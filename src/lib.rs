@@ -1,161 +1,60 @@
+use arrow::array::{ArrayRef, StringArray, UInt32Array};
+use arrow::datatypes::{DataType, Field, Schema};
+use arrow::pyarrow::ToPyArrow;
+use arrow::record_batch::RecordBatch;
 use pyo3::prelude::*;
 use pyo3::types::PyDict;
 use pyo3::Bound;
-use serde::Deserialize;
-use serde_json;
-use sha2::{Digest, Sha256};
+use py_dependency_mapper_core as engine;
+use py_dependency_mapper_core::{
+    BlastRadius, BuildConfig, BuildPlan, BuildResult, ChurnReport, DockerContext, DynamicImportWarning, FrozenMap, GitCommit,
+    GraphFileResult, GraphLimits, IncludePathPlan, IoOptions, LicenseReport, MemoryStats, Metrics, ModuleFile,
+    NewEdge, OwnershipReport, ParserOptions, PipMetadata, PipPackageInfo, ProjectFile, ResolutionCandidate,
+    ResolutionExplanation, ReverseIndex, RiskyImport, SkippedFile,
+};
 use std::collections::{BTreeMap, HashMap, HashSet};
-use std::fs;
 use std::path::{Path, PathBuf};
-use std::time::Instant;
-use walkdir::WalkDir;
-mod helpers;
-use helpers::imports_from_source;
-
-#[pyclass]
-#[derive(Clone, Debug)]
-struct ProjectFile {
-    #[pyo3(get)]
-    hash: String,
-    #[pyo3(get)]
-    project_imports: Vec<String>,
-    #[pyo3(get)]
-    stdlib_imports: Vec<String>,
-    #[pyo3(get)]
-    third_party_imports: Vec<String>,
-}
-
-#[pyclass]
-#[derive(Clone, Debug)]
-struct GraphFileResult {
-    #[pyo3(get)]
-    hash: String,
-    #[pyo3(get)]
-    stdlib_imports: Vec<String>,
-    #[pyo3(get)]
-    third_party_imports: Vec<String>,
-}
-
-#[pyclass]
-#[derive(Clone, Debug)]
-pub struct PipPackageInfo {
-    #[pyo3(get)]
-    pub version: String,
-    #[pyo3(get)]
-    pub installed_paths: Vec<String>,
-    #[pyo3(get)]
-    pub dependencies: Vec<String>,
-}
-
-#[pyclass]
-#[derive(Clone, Debug)]
-pub struct PipMetadata {
-    #[pyo3(get)]
-    pub import_to_pip_map: HashMap<String, String>,
-    #[pyo3(get)]
-    pub pip_package_info_map: HashMap<String, PipPackageInfo>,
-    #[pyo3(get)]
-    pub extra_dependencies_map: HashMap<String, Vec<String>>,
-    #[pyo3(get)]
-    pub extra_paths_map: HashMap<String, Vec<String>>,
-}
-
-#[derive(Deserialize, Debug, Default)]
-struct ManualMappings {
-    #[serde(default)]
-    import_mappings: HashMap<String, String>,
-    #[serde(default)]
-    extra_dependencies: HashMap<String, Vec<String>>,
-    #[serde(default)]
-    extra_package_paths: HashMap<String, Vec<String>>,
-}
-
-#[derive(Deserialize, Debug)]
-struct PackageDetails {
-    version: String,
-    dependencies: BTreeMap<String, PackageDetails>,
-}
-
-struct PipAnalyzer<'a> {
-    site_packages: &'a Path,
-    import_to_pip_map: HashMap<String, String>,
-    pip_package_info_map: HashMap<String, PipPackageInfo>,
-}
-
-impl<'a> PipAnalyzer<'a> {
-    fn new(site_packages: &'a Path) -> Self {
-        PipAnalyzer {
-            site_packages,
-            import_to_pip_map: HashMap::new(),
-            pip_package_info_map: HashMap::new(),
-        }
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing_chrome::ChromeLayerBuilder;
+use tracing_subscriber::prelude::*;
+
+fn extract_dependency_map(dependency_map: &Bound<'_, PyDict>) -> PyResult<HashMap<String, ProjectFile>> {
+    let mut map = HashMap::with_capacity(dependency_map.len());
+    for (path, value) in dependency_map {
+        map.insert(path.extract::<String>()?, value.extract::<ProjectFile>()?);
     }
+    Ok(map)
+}
 
-    fn process_package(&mut self, package_name: &str, package_details: &PackageDetails) {
-        if let Some(existing_info) = self.pip_package_info_map.get(package_name) {
-            if !existing_info.dependencies.is_empty() && package_details.dependencies.is_empty() {
-                return;
-            }
-        }
-
-        let mut importables = HashSet::new();
-        let mut installed_artifact_paths = HashSet::new();
-        let dependencies: Vec<String> = package_details.dependencies.keys().cloned().collect();
-
-        if let Some(dist_dir) = find_dist_info_dir(package_name, &package_details.version, self.site_packages) {
-            if let Ok(record_content) = fs::read_to_string(dist_dir.join("RECORD")) {
-                for line in record_content.lines() {
-                    if let Some(path_str) = line.split(',').next() {
-                        if path_str.contains(".dist-info/") { continue; }
-                        if let Some(top_level) = path_str.split('/').next() {
-                            if top_level.is_empty() { continue; }
-
-                            if top_level == "bin" {
-                                installed_artifact_paths.insert(path_str.to_string());
-                            } else {
-                                installed_artifact_paths.insert(top_level.to_string());
-                            }
-                        }
-                    }
-                }
-            }
-
-            if let Ok(top_level_content) = fs::read_to_string(dist_dir.join("top_level.txt")) {
-                for name in top_level_content.lines() {
-                    if !name.trim().is_empty() { importables.insert(name.trim().to_string()); }
-                }
-            } else if !installed_artifact_paths.is_empty() {
-                for path in &installed_artifact_paths {
-                    let import_name = path.strip_suffix(".py").unwrap_or(path);
-                    if !import_name.contains('/') {
-                        importables.insert(import_name.to_string());
-                    }
-                }
-            }
-        }
-
-        for name in &importables {
-            self.import_to_pip_map.entry(name.clone()).or_insert_with(|| package_name.to_string());
+/// Key of `entry_point` and the entries of its closure, read through `PyRef` borrows so
+/// closure queries copy the files they walk instead of the whole dict.
+fn extract_closure(
+    dependency_map: &Bound<'_, PyDict>,
+    entry_point: &str,
+    source_root: Option<&Path>,
+) -> PyResult<(String, HashMap<String, ProjectFile>)> {
+    let entry_key = engine::lookup_entry_point_with(
+        entry_point,
+        source_root,
+        &|key: &str| dependency_map.contains(key).unwrap_or(false),
+        &|| dependency_map.keys().iter().filter_map(|key| key.extract::<String>().ok()).collect(),
+    )?;
+    let mut closure = HashMap::new();
+    let mut stack = vec![entry_key.clone()];
+    while let Some(path) = stack.pop() {
+        if closure.contains_key(&path) {
+            continue;
         }
-
-        let package_info = PipPackageInfo {
-            version: package_details.version.clone(),
-            installed_paths: installed_artifact_paths.into_iter().collect(),
-            dependencies,
-        };
-        self.pip_package_info_map.insert(package_name.to_string(), package_info);
-
-        for (dep_name, dep_details) in &package_details.dependencies {
-            self.process_package(dep_name, dep_details);
+        if let Some(value) = dependency_map.get_item(&path)? {
+            let info = value.extract::<PyRef<ProjectFile>>()?;
+            stack.extend(info.project_imports.iter().filter(|target| !closure.contains_key(*target)).cloned());
+            closure.insert(path, ProjectFile::clone(&info));
         }
     }
-
-    fn finalize(self) -> (HashMap<String, String>, HashMap<String, PipPackageInfo>) {
-        (self.import_to_pip_map, self.pip_package_info_map)
-    }
+    Ok((entry_key, closure))
 }
 
-
 #[pyfunction]
 #[pyo3(signature = (dependency_tree_json_path, site_packages_path, manual_mapping_path=None))]
 pub fn build_pip_metadata(
@@ -163,168 +62,219 @@ pub fn build_pip_metadata(
     site_packages_path: &str,
     manual_mapping_path: Option<String>,
 ) -> PyResult<PipMetadata> {
+    Ok(engine::build_pip_metadata(
+        Path::new(dependency_tree_json_path),
+        Path::new(site_packages_path),
+        manual_mapping_path.as_deref().map(Path::new),
+    )?)
+}
 
-    let site_packages = PathBuf::from(site_packages_path);
-
-    let json_content = fs::read_to_string(dependency_tree_json_path)?;
-    let packages: BTreeMap<String, PackageDetails> = serde_json::from_str(&json_content)
-        .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))?;
-
-    let mut analyzer = PipAnalyzer::new(&site_packages);
-
-    let mut manual_import_mappings = HashMap::new();
-    let mut manual_extra_deps = HashMap::new();
-    let mut manual_extra_paths = HashMap::new();
-
-    if let Some(path_str) = manual_mapping_path {
-        let path = PathBuf::from(path_str);
-        if path.is_file() {
-            let content = fs::read_to_string(path)?;
-            let mappings: ManualMappings = toml::from_str(&content)
-                .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))?;
-            manual_import_mappings = mappings.import_mappings;
-            manual_extra_deps = mappings.extra_dependencies;
-            manual_extra_paths = mappings.extra_package_paths;
-        }
-    }
-
-    analyzer.import_to_pip_map.extend(manual_import_mappings);
-
-    for (package_name, package_details) in &packages {
-        analyzer.process_package(package_name, package_details);
-    }
-
-    let (import_map, package_info_map) = analyzer.finalize();
 
-    Ok(PipMetadata {
-        import_to_pip_map: import_map,
-        pip_package_info_map: package_info_map,
-        extra_dependencies_map: manual_extra_deps,
-        extra_paths_map: manual_extra_paths,
-    })
+#[pyfunction]
+pub fn build_environment_metadata(site_packages_path: &str) -> PyResult<PipMetadata> {
+    Ok(engine::build_environment_metadata(Path::new(site_packages_path))?)
 }
 
-
 #[pyfunction]
 pub fn resolve_package_set(
     direct_packages: Vec<String>,
     pip_metadata: &Bound<'_, PyAny>,
 ) -> PyResult<HashMap<String, PipPackageInfo>> {
     let metadata: PyRef<PipMetadata> = pip_metadata.extract()?;
-    let all_packages_info = &metadata.pip_package_info_map;
-    let extra_deps_map = &metadata.extra_dependencies_map;
-    
-
-    let mut final_package_set = HashSet::new();
-    let mut processing_stack = direct_packages;
-
-    while let Some(package_name) = processing_stack.pop() {
-        if !final_package_set.insert(package_name.clone()) {
-            continue; 
-        }
-
-        if let Some(package_info) = all_packages_info.get(&package_name) {
-            for dep in &package_info.dependencies {
-                processing_stack.push(dep.clone());
-            }
-        }
-        
-        if let Some(extra_deps) = extra_deps_map.get(&package_name) {
-            for extra_dep in extra_deps {
-                processing_stack.push(extra_dep.clone());
-            }
-        }
-    }
-
-    let resolved_map = final_package_set
-        .into_iter()
-        .filter_map(|name| all_packages_info.get(&name).map(|info| (name, info.clone())))
-        .collect();
-    Ok(resolved_map)
+    Ok(engine::resolve_package_set(direct_packages, &metadata))
 }
 
 #[pyfunction]
-#[pyo3(signature = (source_root, project_module_prefixes, include_paths, stdlib_list_path=None))]
+#[pyo3(signature = (source_root, project_module_prefixes, include_paths, stdlib_list_path=None, previous_map=None, imports_as="list", volatile_patterns=None, extra_edges_path=None, profile_path=None, include_stubs=false, target_version=None, reject_unsupported_syntax=false, error_recovery=true, site_packages_paths=None, deep_packages=None, deep_max_depth=None, resolution_scope="source_root", git_metadata=false, git_revision=None, normalize_line_endings=false, ignore_trailing_newlines=false, io_retries=3, io_backoff_ms=50, io_threads=1, io_batch_size=256, max_open_files=None, max_buffered_bytes=None, max_workers=None, extra_source_roots=None, exclude_import_kinds=None, exclude=None, respect_gitignore=false, prefix_sets=None, max_file_size=None, detailed=false, plan=false))]
 fn build_dependency_map(
+    py: Python<'_>,
     source_root: &str,
     project_module_prefixes: Vec<String>,
     include_paths: Vec<String>,
     stdlib_list_path: Option<String>,
-) -> PyResult<HashMap<String, ProjectFile>> {
+    previous_map: Option<HashMap<String, ProjectFile>>,
+    imports_as: &str,
+    volatile_patterns: Option<Vec<String>>,
+    extra_edges_path: Option<String>,
+    profile_path: Option<String>,
+    include_stubs: bool,
+    target_version: Option<String>,
+    reject_unsupported_syntax: bool,
+    error_recovery: bool,
+    site_packages_paths: Option<Vec<String>>,
+    deep_packages: Option<Vec<String>>,
+    deep_max_depth: Option<u32>,
+    resolution_scope: &str,
+    git_metadata: bool,
+    git_revision: Option<String>,
+    normalize_line_endings: bool,
+    ignore_trailing_newlines: bool,
+    io_retries: u32,
+    io_backoff_ms: u64,
+    io_threads: usize,
+    io_batch_size: usize,
+    max_open_files: Option<usize>,
+    max_buffered_bytes: Option<u64>,
+    max_workers: Option<usize>,
+    extra_source_roots: Option<Vec<String>>,
+    exclude_import_kinds: Option<Vec<String>>,
+    exclude: Option<Vec<String>>,
+    respect_gitignore: bool,
+    prefix_sets: Option<BTreeMap<String, Vec<String>>>,
+    max_file_size: Option<u64>,
+    detailed: bool,
+    plan: bool,
+) -> PyResult<Py<PyAny>> {
     let start_time = Instant::now();
-    let source_root_path = PathBuf::from(source_root);
-    
-    let mut project_file_map = HashMap::with_capacity(4096);
-    let mut module_resolution_cache: HashMap<String, Option<PathBuf>> = HashMap::with_capacity(1024);
-    let mut package_init_cache: HashMap<String, Vec<PathBuf>> = HashMap::with_capacity(1024);
-
-    let stdlib_modules = if let Some(path) = stdlib_list_path {
-        helpers::load_stdlib_from_file(&path)?
-    } else {
-        HashSet::new()
+    let config = BuildConfig {
+        source_root: PathBuf::from(source_root),
+        extra_source_roots: extra_source_roots.unwrap_or_default().into_iter().map(PathBuf::from).collect(),
+        project_module_prefixes,
+        prefix_sets: prefix_sets.unwrap_or_default(),
+        include_paths,
+        exclude: exclude.unwrap_or_default(),
+        respect_gitignore,
+        stdlib_list_path: stdlib_list_path.map(PathBuf::from),
+        previous_map: previous_map.unwrap_or_default(),
+        imports_container: imports_as.parse()?,
+        volatile_patterns: volatile_patterns.unwrap_or_default(),
+        extra_edges_path: extra_edges_path.map(PathBuf::from),
+        include_stubs,
+        parser: ParserOptions {
+            target_version: target_version.as_deref().map(ParserOptions::parse_target_version).transpose()?,
+            reject_unsupported_syntax,
+            error_recovery,
+        },
+        site_packages_dirs: site_packages_paths.unwrap_or_default().into_iter().map(PathBuf::from).collect(),
+        deep_packages: deep_packages.unwrap_or_default(),
+        deep_max_depth,
+        resolution_scope: resolution_scope.parse()?,
+        git_metadata,
+        git_revision,
+        normalize_line_endings,
+        ignore_trailing_newlines,
+        io: IoOptions {
+            retries: io_retries,
+            backoff: Duration::from_millis(io_backoff_ms),
+            threads: io_threads,
+            batch_size: io_batch_size,
+            max_open_files,
+            max_buffered_bytes,
+        },
+        max_workers,
+        excluded_import_kinds: exclude_import_kinds
+            .unwrap_or_default()
+            .iter()
+            .map(|kind| kind.parse())
+            .collect::<Result<_, _>>()?,
+        max_file_size,
     };
 
-    for path_str in &include_paths {
-        let full_path = source_root_path.join(path_str);
-        if full_path.is_dir() {
-            for entry in WalkDir::new(full_path).into_iter().filter_map(|e| e.ok()) {
-                let path = entry.path();
-                if path.is_file() && path.extension().map_or(false, |ext| ext == "py") {
-                    parse_file_imports(
-                        path, &source_root_path, &project_module_prefixes, &stdlib_modules,
-                        &mut project_file_map, &mut module_resolution_cache, &mut package_init_cache,
-                    );
-                }
-            }
-        } else if full_path.is_file() {
-            parse_file_imports(
-                &full_path, &source_root_path, &project_module_prefixes, &stdlib_modules,
-                &mut project_file_map, &mut module_resolution_cache, &mut package_init_cache,
-            );
-        }
+    if plan {
+        return Ok(engine::plan_dependency_map(&config)?.into_pyobject(py)?.into_any().unbind());
     }
 
+    let result = match profile_path {
+        Some(path) => {
+            let (chrome_layer, flush_guard) = ChromeLayerBuilder::new().file(path).include_args(true).build();
+            let subscriber = tracing_subscriber::registry().with(chrome_layer);
+            let result = tracing::subscriber::with_default(subscriber, || engine::build_dependency_map(&config));
+            drop(flush_guard);
+            result
+        }
+        None => engine::build_dependency_map(&config),
+    }?;
+
     let duration = start_time.elapsed();
     println!(
-        "✅ Dependency tree built: {} files in {:.4}s | Include Paths: {:?} | Filter for: {:?}",
-        project_file_map.len(),
+        "✅ Dependency tree built: {} files ({} reused) in {:.4}s | Include Paths: {:?} | Filter for: {:?}",
+        result.files.len(),
+        result.reused_files,
         duration.as_secs_f64(),
-        include_paths,
-        project_module_prefixes,
+        config.include_paths,
+        config.project_module_prefixes,
     );
-    
-    Ok(project_file_map)
+    if detailed {
+        return Ok(result.into_pyobject(py)?.into_any().unbind());
+    }
+    Ok(result.files.into_pyobject(py)?.into_any().unbind())
+}
+
+#[pyfunction]
+#[pyo3(signature = (source_root, project_module_prefixes, include_paths, cache_path, stdlib_list_path=None, imports_as="list", include_stubs=false, max_workers=None, extra_source_roots=None))]
+fn build_dependency_map_incremental(
+    source_root: &str,
+    project_module_prefixes: Vec<String>,
+    include_paths: Vec<String>,
+    cache_path: &str,
+    stdlib_list_path: Option<String>,
+    imports_as: &str,
+    include_stubs: bool,
+    max_workers: Option<usize>,
+    extra_source_roots: Option<Vec<String>>,
+) -> PyResult<HashMap<String, ProjectFile>> {
+    let config = BuildConfig {
+        source_root: PathBuf::from(source_root),
+        extra_source_roots: extra_source_roots.unwrap_or_default().into_iter().map(PathBuf::from).collect(),
+        project_module_prefixes,
+        include_paths,
+        stdlib_list_path: stdlib_list_path.map(PathBuf::from),
+        imports_container: imports_as.parse()?,
+        include_stubs,
+        max_workers,
+        ..Default::default()
+    };
+    Ok(engine::build_dependency_map_incremental(&config, Path::new(cache_path))?.files)
 }
 
 #[pyfunction]
+#[pyo3(signature = (dependency_map, entry_point, source_root=None, max_nodes=None, max_edges=None, max_seconds=None, provenance=false))]
 fn get_dependency_graph(
     dependency_map: &Bound<'_, PyDict>,
     entry_point: &str,
+    source_root: Option<String>,
+    max_nodes: Option<usize>,
+    max_edges: Option<usize>,
+    max_seconds: Option<f64>,
+    provenance: bool,
 ) -> PyResult<HashMap<String, GraphFileResult>> {
-    let entry_point_path = fs::canonicalize(entry_point)?.to_string_lossy().into_owned();
-    
-    let mut resolved_file_map = HashMap::with_capacity(64);
-    let mut stack: Vec<String> = vec![entry_point_path];
-    let mut seen: HashSet<String> = HashSet::with_capacity(128);
-
-    while let Some(current_path) = stack.pop() {
-        if !seen.insert(current_path.clone()) {
-            continue;
-        }
-        if let Some(info_obj) = dependency_map.get_item(&current_path)? {
-            let info = info_obj.extract::<PyRef<ProjectFile>>()?;
-            let result = GraphFileResult {
-                hash: info.hash.clone(),
-                stdlib_imports: info.stdlib_imports.clone(),
-                third_party_imports: info.third_party_imports.clone(),
-            };
-            resolved_file_map.insert(current_path, result);
-            for import_path in &info.project_imports {
-                stack.push(import_path.clone());
-            }
-        }
-    }
-    Ok(resolved_file_map)
+    let source_root = source_root.as_deref().map(Path::new);
+    let (entry_key, map) = extract_closure(dependency_map, entry_point, source_root)?;
+    let limits = GraphLimits {
+        max_nodes,
+        max_edges,
+        max_duration: max_seconds.map(Duration::from_secs_f64),
+    };
+    Ok(engine::get_dependency_graph(&map, &entry_key, source_root, &limits, provenance)?)
+}
+
+#[pyfunction]
+#[pyo3(signature = (source_root, include_paths, include_stubs=false, git_revision=None, max_open_files=None, exclude=None, respect_gitignore=false))]
+fn find_source_files(
+    source_root: &str,
+    include_paths: Vec<String>,
+    include_stubs: bool,
+    git_revision: Option<String>,
+    max_open_files: Option<usize>,
+    exclude: Option<Vec<String>>,
+    respect_gitignore: bool,
+) -> PyResult<Vec<String>> {
+    let config = BuildConfig {
+        source_root: PathBuf::from(source_root),
+        include_paths,
+        exclude: exclude.unwrap_or_default(),
+        respect_gitignore,
+        include_stubs,
+        git_revision,
+        io: IoOptions { max_open_files, ..Default::default() },
+        ..Default::default()
+    };
+    Ok(engine::find_source_files(&config)?)
+}
+
+#[pyfunction]
+fn freeze(dependency_map: &Bound<'_, PyDict>) -> PyResult<FrozenMap> {
+    Ok(FrozenMap::new(extract_dependency_map(dependency_map)?))
 }
 
 #[pyfunction]
@@ -332,208 +282,464 @@ fn find_dependents(
     dependency_map: &Bound<'_, PyDict>,
     changed_file_paths: Vec<String>,
 ) -> PyResult<HashSet<String>> {
-    
-    let mut reverse_graph: HashMap<String, Vec<String>> = HashMap::new();
-    
-    for (importer_path, value) in dependency_map {
-        let importer = importer_path.extract::<String>()?;
-        let project_file: PyRef<ProjectFile> = value.extract()?;
-        
-        for dependency in &project_file.project_imports {
-            reverse_graph
-                .entry(dependency.clone())
-                .or_default()
-                .push(importer.clone());
-        }
-    }
+    let map = extract_dependency_map(dependency_map)?;
+    Ok(engine::find_dependents(&map, changed_file_paths))
+}
 
-    let mut dependents = HashSet::new();
-    
-    let mut stack = changed_file_paths; 
-
-    while let Some(current_file) = stack.pop() {
-        if let Some(consumers) = reverse_graph.get(&current_file) {
-            for consumer in consumers {
-                if dependents.insert(consumer.clone()) {
-                    stack.push(consumer.clone());
-                }
-            }
-        }
-    }
+#[pyfunction]
+fn get_reverse_dependencies(
+    dependency_map: &Bound<'_, PyDict>,
+    changed_files: Vec<String>,
+) -> PyResult<HashMap<String, String>> {
+    let map = extract_dependency_map(dependency_map)?;
+    Ok(engine::get_reverse_dependencies(&map, &changed_files))
+}
 
-    Ok(dependents)
+#[pyfunction]
+#[pyo3(signature = (dependency_map, entry_point, source_root=None))]
+fn get_external_dependencies(
+    dependency_map: &Bound<'_, PyDict>,
+    entry_point: &str,
+    source_root: Option<String>,
+) -> PyResult<Vec<String>> {
+    let source_root = source_root.as_deref().map(Path::new);
+    let (entry_key, map) = extract_closure(dependency_map, entry_point, source_root)?;
+    Ok(engine::get_external_dependencies(&map, &entry_key, source_root)?)
 }
 
-#[pymodule]
-fn py_dependency_mapper<'py>(_py: Python<'py>, m: &Bound<'_, PyModule>) -> PyResult<()> {
-    m.add_class::<ProjectFile>()?;
-    m.add_class::<GraphFileResult>()?;
-    m.add_class::<PipMetadata>()?;
-    m.add_class::<PipPackageInfo>()?;
-    m.add_function(wrap_pyfunction!(build_dependency_map, m)?)?;
-    m.add_function(wrap_pyfunction!(get_dependency_graph, m)?)?;
-    m.add_function(wrap_pyfunction!(build_pip_metadata, m)?)?;
-    m.add_function(wrap_pyfunction!(resolve_package_set, m)?)?;
-    m.add_function(wrap_pyfunction!(find_dependents, m)?)?;
-    Ok(())
+#[pyfunction]
+fn find_import_cycles(dependency_map: &Bound<'_, PyDict>) -> PyResult<Vec<Vec<String>>> {
+    let map = extract_dependency_map(dependency_map)?;
+    Ok(engine::find_import_cycles(&map))
 }
 
-fn normalize_pkg_name(name: &str) -> String {
-    name.to_lowercase().replace('-', "_")
+#[pyfunction]
+#[pyo3(signature = (dependency_map, entry_point, source_root=None))]
+fn topological_order(
+    dependency_map: &Bound<'_, PyDict>,
+    entry_point: &str,
+    source_root: Option<String>,
+) -> PyResult<Vec<String>> {
+    let source_root = source_root.as_deref().map(Path::new);
+    let (entry_key, map) = extract_closure(dependency_map, entry_point, source_root)?;
+    Ok(engine::topological_order(&map, &entry_key, source_root)?)
 }
 
-fn find_dist_info_dir(
-    package_name: &str,
-    version: &str,
-    site_packages: &Path,
-) -> Option<PathBuf> {
-    
-    let mut possible_names = HashSet::new();
-    
-    possible_names.insert(package_name.to_string());
-    possible_names.insert(package_name.replace('-', "_"));
-    possible_names.insert(package_name.replace('-', "."));
+#[pyfunction]
+fn to_module_map(
+    dependency_map: &Bound<'_, PyDict>,
+    source_root: &str,
+) -> PyResult<HashMap<String, ModuleFile>> {
+    let map = extract_dependency_map(dependency_map)?;
+    Ok(engine::to_module_map(&map, Path::new(source_root)))
+}
 
-    let mut c = package_name.chars();
-    let capitalized_name = match c.next() {
-        None => package_name.to_string(),
-        Some(f) => f.to_uppercase().collect::<String>() + c.as_str(),
-    };
-    possible_names.insert(capitalized_name.clone());
-    possible_names.insert(capitalized_name.replace('-', "_"));
-    possible_names.insert(capitalized_name.replace('-', "."));
-
-    for name in possible_names {
-        let dir_name = format!("{}-{}.dist-info", name, version);
-        let path = site_packages.join(&dir_name);
-        if path.is_dir() {
-            return Some(path);
-        }
-    }
+#[pyfunction]
+fn files_by_hash(dependency_map: &Bound<'_, PyDict>, hash: &str) -> PyResult<Vec<String>> {
+    let map = extract_dependency_map(dependency_map)?;
+    Ok(engine::files_by_hash(&map, hash))
+}
 
-    let normalized_input_name = normalize_pkg_name(package_name);
-    let version_suffix = format!("-{}.dist-info", version); 
+#[pyfunction]
+fn hash_index(dependency_map: &Bound<'_, PyDict>) -> PyResult<HashMap<String, Vec<String>>> {
+    let map = extract_dependency_map(dependency_map)?;
+    Ok(engine::hash_index(&map))
+}
 
-    let entries = match fs::read_dir(site_packages) {
-        Ok(entries) => entries,
-        Err(_) => return None, 
-    };
-    
-    for entry in entries.filter_map(|e| e.ok()) {
-        let path = entry.path();
-        if !path.is_dir() { continue; }
-
-        let dir_name_str = match entry.file_name().into_string() {
-            Ok(s) => s,
-            Err(_) => continue,
-        };
+#[pyfunction]
+fn dynamic_import_warnings(dependency_map: &Bound<'_, PyDict>) -> PyResult<Vec<DynamicImportWarning>> {
+    let map = extract_dependency_map(dependency_map)?;
+    Ok(engine::dynamic_import_warnings(&map))
+}
+
+#[pyfunction]
+fn anonymize_map(dependency_map: &Bound<'_, PyDict>, key: &str) -> PyResult<HashMap<String, ProjectFile>> {
+    let map = extract_dependency_map(dependency_map)?;
+    Ok(engine::anonymize_map(&map, key))
+}
+
+#[pyfunction]
+fn get_metrics() -> Metrics {
+    engine::metrics()
+}
+
+#[pyfunction]
+fn metrics_prometheus() -> String {
+    engine::metrics().to_prometheus()
+}
 
-        if dir_name_str.ends_with(&version_suffix) {
-            
-            if let Some(actual_name) = dir_name_str.strip_suffix(&version_suffix) {
-                
-                let normalized_actual_name = normalize_pkg_name(actual_name);
+#[pyfunction]
+fn memory_stats(dependency_map: &Bound<'_, PyDict>) -> PyResult<MemoryStats> {
+    let map = extract_dependency_map(dependency_map)?;
+    Ok(engine::memory_stats(&map))
+}
 
-                if normalized_actual_name == normalized_input_name {
-                    return Some(path);
-                }
-            }
+/// Columnar `src`/`dst`/`edge_kind`/`weight` edge data as a `pyarrow.RecordBatch`,
+/// handed over through the Arrow C data interface, or as a DataFrame over the same
+/// buffers when `library` is `"pandas"` or `"polars"`.
+#[pyfunction]
+#[pyo3(signature = (dependency_map, library=None))]
+fn to_dataframe<'py>(
+    py: Python<'py>,
+    dependency_map: &Bound<'py, PyDict>,
+    library: Option<&str>,
+) -> PyResult<Bound<'py, PyAny>> {
+    let map = extract_dependency_map(dependency_map)?;
+    let table = engine::edge_table(&map);
+
+    let schema = Schema::new(vec![
+        Field::new("src", DataType::Utf8, false),
+        Field::new("dst", DataType::Utf8, false),
+        Field::new("edge_kind", DataType::Utf8, false),
+        Field::new("weight", DataType::UInt32, false),
+    ]);
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from(table.src)),
+        Arc::new(StringArray::from(table.dst)),
+        Arc::new(StringArray::from(table.edge_kind)),
+        Arc::new(UInt32Array::from(table.weight)),
+    ];
+    let batch = RecordBatch::try_new(Arc::new(schema), columns)
+        .map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))?
+        .to_pyarrow(py)?
+        .into_bound(py);
+
+    match library {
+        None => Ok(batch),
+        // Arrow-backed columns, so pandas keeps the buffers instead of boxing each cell.
+        Some("pandas") => {
+            let kwargs = PyDict::new(py);
+            kwargs.set_item("types_mapper", py.import("pandas")?.getattr("ArrowDtype")?)?;
+            batch.call_method("to_pandas", (), Some(&kwargs))
         }
+        Some("polars") => py.import("polars")?.call_method1("from_arrow", (batch,)),
+        Some(other) => Err(pyo3::exceptions::PyValueError::new_err(format!(
+            "unsupported dataframe library '{}', expected 'pandas' or 'polars'",
+            other
+        ))),
     }
-    None
-}
-
-fn parse_file_imports(
-    path: &Path,
-    source_root_path: &Path,
-    project_module_prefixes: &[String],
-    stdlib_modules: &HashSet<String>,
-    project_file_map: &mut HashMap<String, ProjectFile>,
-    module_resolution_cache: &mut HashMap<String, Option<PathBuf>>,
-    package_init_cache: &mut HashMap<String, Vec<PathBuf>>,
-) {
-    let path_str = path.to_string_lossy().into_owned();
-    if project_file_map.contains_key(&path_str) { return; }
-
-    if let Ok(content_bytes) = fs::read(path) {
-        let mut hasher = Sha256::new();
-        hasher.update(&content_bytes);
-        let hash = hex::encode(hasher.finalize());
-
-        let mut resolved_project_imports = HashSet::new();
-        let mut stdlib_imports = HashSet::new();
-        let mut third_party_imports = HashSet::new();
-
-        if let Ok(content_str) = std::str::from_utf8(&content_bytes) {
-            let import_strings = imports_from_source(content_str);
-            for module in import_strings {
-                let base_module = module.split('.').next().unwrap_or(&module);
-
-                if project_module_prefixes.iter().any(|prefix| module.starts_with(prefix)) {
-                    for p in helpers::find_package_inits_in_path_seq(&module, source_root_path, package_init_cache) {
-                        resolved_project_imports.insert(p.to_string_lossy().into_owned());
-                    }
-                    if let Some(p) = helpers::resolve_module_in_project_seq(&module, source_root_path, module_resolution_cache) {
-                        resolved_project_imports.insert(p.to_string_lossy().into_owned());
-                    }
-                } else if stdlib_modules.contains(base_module) {
-                    stdlib_imports.insert(base_module.to_string());
-                } else {
-                    third_party_imports.insert(base_module.to_string());
-                }
-            }
+}
+
+#[pyfunction]
+#[pyo3(signature = (dependency_map, source_root=None))]
+fn export_cytoscape(dependency_map: &Bound<'_, PyDict>, source_root: Option<String>) -> PyResult<String> {
+    let map = extract_dependency_map(dependency_map)?;
+    Ok(engine::cytoscape_json(&map, source_root.as_deref().map(Path::new)))
+}
+
+#[pyfunction]
+#[pyo3(signature = (dependency_map, format="dot", entry_point=None, source_root=None))]
+fn export_graph(
+    dependency_map: &Bound<'_, PyDict>,
+    format: &str,
+    entry_point: Option<&str>,
+    source_root: Option<String>,
+) -> PyResult<String> {
+    let source_root = source_root.as_deref().map(Path::new);
+    match entry_point {
+        Some(entry_point) => {
+            let (entry_key, map) = extract_closure(dependency_map, entry_point, source_root)?;
+            Ok(engine::export_graph(&map, format.parse()?, Some(&entry_key), source_root)?)
         }
-        project_file_map.insert(path_str, ProjectFile {
-            hash,
-            project_imports: resolved_project_imports.into_iter().collect(),
-            stdlib_imports: stdlib_imports.into_iter().collect(),
-            third_party_imports: third_party_imports.into_iter().collect(),
-        });
+        None => Ok(engine::export_graph(&extract_dependency_map(dependency_map)?, format.parse()?, None, source_root)?),
     }
 }
 
+#[pyfunction]
+#[pyo3(signature = (dependency_map, entry_points, path, source_root=None))]
+fn export_html_report(
+    dependency_map: &Bound<'_, PyDict>,
+    entry_points: Vec<String>,
+    path: &str,
+    source_root: Option<String>,
+) -> PyResult<()> {
+    let map = extract_dependency_map(dependency_map)?;
+    Ok(engine::export_html_report(&map, &entry_points, source_root.as_deref().map(Path::new), Path::new(path))?)
+}
 
+#[pyfunction]
+#[pyo3(signature = (report_path, base_dir=None))]
+fn load_coverage_json(report_path: &str, base_dir: Option<String>) -> PyResult<Vec<String>> {
+    Ok(engine::load_coverage_json(Path::new(report_path), base_dir.as_deref().map(Path::new))?)
+}
+
+#[pyfunction]
+fn merge_runtime_dependencies(
+    dependency_map: &Bound<'_, PyDict>,
+    executed_files: HashMap<String, Vec<String>>,
+) -> PyResult<HashMap<String, ProjectFile>> {
+    let map = extract_dependency_map(dependency_map)?;
+    Ok(engine::merge_runtime_dependencies(&map, &executed_files)?)
+}
 
+#[pyfunction]
+#[pyo3(signature = (dependency_map, entry_point, source_root=None, risky_modules=None))]
+fn audit_risky_imports(
+    dependency_map: &Bound<'_, PyDict>,
+    entry_point: &str,
+    source_root: Option<String>,
+    risky_modules: Option<Vec<String>>,
+) -> PyResult<Vec<RiskyImport>> {
+    let source_root = source_root.as_deref().map(Path::new);
+    let (entry_key, map) = extract_closure(dependency_map, entry_point, source_root)?;
+    let risky_modules = risky_modules
+        .unwrap_or_else(|| engine::DEFAULT_RISKY_MODULES.iter().map(|m| m.to_string()).collect());
+    Ok(engine::audit_risky_imports(&map, &entry_key, source_root, &risky_modules)?)
+}
+
+#[pyfunction]
+#[pyo3(signature = (dependency_map, entry_point, source_root=None, pip_metadata=None))]
+fn export_sbom(
+    dependency_map: &Bound<'_, PyDict>,
+    entry_point: &str,
+    source_root: Option<String>,
+    pip_metadata: Option<PyRef<'_, PipMetadata>>,
+) -> PyResult<String> {
+    let source_root = source_root.as_deref().map(Path::new);
+    let (entry_key, map) = extract_closure(dependency_map, entry_point, source_root)?;
+    Ok(engine::cyclonedx_sbom(&map, &entry_key, source_root, pip_metadata.as_deref())?)
+}
+
+#[pyfunction]
+#[pyo3(signature = (dependency_map, entry_point, pip_metadata, source_root=None, disallowed_licenses=None))]
+fn license_report(
+    dependency_map: &Bound<'_, PyDict>,
+    entry_point: &str,
+    pip_metadata: PyRef<'_, PipMetadata>,
+    source_root: Option<String>,
+    disallowed_licenses: Option<Vec<String>>,
+) -> PyResult<LicenseReport> {
+    let source_root = source_root.as_deref().map(Path::new);
+    let (entry_key, map) = extract_closure(dependency_map, entry_point, source_root)?;
+    Ok(engine::license_report(
+        &map,
+        &entry_key,
+        source_root,
+        &pip_metadata,
+        &disallowed_licenses.unwrap_or_default(),
+    )?)
+}
+
+#[pyfunction]
+fn asset_digest(dependency_map: &Bound<'_, PyDict>, entry_point: &str, source_root: &str) -> PyResult<String> {
+    let (entry_key, map) = extract_closure(dependency_map, entry_point, Some(Path::new(source_root)))?;
+    Ok(engine::asset_digest(&map, &entry_key, Path::new(source_root))?)
+}
+
+#[pyfunction]
+fn get_closure_hash(dependency_map: &Bound<'_, PyDict>, entry_point: &str, source_root: &str) -> PyResult<String> {
+    let (entry_key, map) = extract_closure(dependency_map, entry_point, Some(Path::new(source_root)))?;
+    Ok(engine::closure_hash(&map, &entry_key, Path::new(source_root))?)
+}
+
+#[pyfunction]
+fn directory_digests(dependency_map: &Bound<'_, PyDict>, source_root: &str) -> PyResult<BTreeMap<String, String>> {
+    let map = extract_dependency_map(dependency_map)?;
+    Ok(engine::directory_digests(&map, Path::new(source_root)))
+}
+
+#[pyfunction]
+#[pyo3(signature = (dependency_map, entry_points, source_root=None))]
+fn blast_radius(
+    dependency_map: &Bound<'_, PyDict>,
+    entry_points: Vec<String>,
+    source_root: Option<String>,
+) -> PyResult<Vec<BlastRadius>> {
+    let map = extract_dependency_map(dependency_map)?;
+    Ok(engine::blast_radius(&map, &entry_points, source_root.as_deref().map(Path::new))?)
+}
+
+#[pyfunction]
+#[pyo3(signature = (source_root, project_module_prefixes, module_name, importing_file=None, include_paths=None, stdlib_list_path=None, include_stubs=false, site_packages_paths=None, deep_packages=None, deep_max_depth=None, resolution_scope="source_root", git_revision=None, extra_source_roots=None))]
+fn explain_resolution(
+    source_root: &str,
+    project_module_prefixes: Vec<String>,
+    module_name: &str,
+    importing_file: Option<String>,
+    include_paths: Option<Vec<String>>,
+    stdlib_list_path: Option<String>,
+    include_stubs: bool,
+    site_packages_paths: Option<Vec<String>>,
+    deep_packages: Option<Vec<String>>,
+    deep_max_depth: Option<u32>,
+    resolution_scope: &str,
+    git_revision: Option<String>,
+    extra_source_roots: Option<Vec<String>>,
+) -> PyResult<ResolutionExplanation> {
+    let config = BuildConfig {
+        source_root: PathBuf::from(source_root),
+        extra_source_roots: extra_source_roots.unwrap_or_default().into_iter().map(PathBuf::from).collect(),
+        project_module_prefixes,
+        include_paths: include_paths.unwrap_or_default(),
+        stdlib_list_path: stdlib_list_path.map(PathBuf::from),
+        include_stubs,
+        site_packages_dirs: site_packages_paths.unwrap_or_default().into_iter().map(PathBuf::from).collect(),
+        deep_packages: deep_packages.unwrap_or_default(),
+        deep_max_depth,
+        resolution_scope: resolution_scope.parse()?,
+        git_revision,
+        ..Default::default()
+    };
+    Ok(engine::explain_resolution(&config, module_name, importing_file.as_deref().map(Path::new))?)
+}
+
+#[pyfunction]
+#[pyo3(signature = (dependency_map, entry_points, source_root=None))]
+fn build_reverse_index(
+    dependency_map: &Bound<'_, PyDict>,
+    entry_points: Vec<String>,
+    source_root: Option<String>,
+) -> PyResult<ReverseIndex> {
+    let map = extract_dependency_map(dependency_map)?;
+    Ok(ReverseIndex::new(&map, &entry_points, source_root.as_deref().map(Path::new))?)
+}
 
+#[pyfunction]
+fn churn_report(snapshots: Vec<Bound<'_, PyDict>>, entry_points: Vec<String>) -> PyResult<ChurnReport> {
+    let maps = snapshots.iter().map(extract_dependency_map).collect::<PyResult<Vec<_>>>()?;
+    Ok(engine::churn_report(&maps, &entry_points))
+}
 
+#[pyfunction]
+#[pyo3(signature = (dependency_map, entry_point, source_root, codeowners_path, changed_files=None))]
+fn codeowners_report(
+    dependency_map: &Bound<'_, PyDict>,
+    entry_point: &str,
+    source_root: &str,
+    codeowners_path: &str,
+    changed_files: Option<Vec<String>>,
+) -> PyResult<OwnershipReport> {
+    let (entry_key, map) = extract_closure(dependency_map, entry_point, Some(Path::new(source_root)))?;
+    Ok(engine::codeowners_report(
+        &map,
+        &entry_key,
+        Path::new(source_root),
+        Path::new(codeowners_path),
+        &changed_files.unwrap_or_default(),
+    )?)
+}
 
+#[pyfunction]
+fn export_docker_context(
+    dependency_map: &Bound<'_, PyDict>,
+    entry_point: &str,
+    source_root: &str,
+) -> PyResult<DockerContext> {
+    let (entry_key, map) = extract_closure(dependency_map, entry_point, Some(Path::new(source_root)))?;
+    Ok(engine::docker_context(&map, &entry_key, Path::new(source_root))?)
+}
 
+#[pyfunction]
+fn build_zip(
+    dependency_map: &Bound<'_, PyDict>,
+    entry_point: &str,
+    source_root: &str,
+    output_path: &str,
+) -> PyResult<String> {
+    let (entry_key, map) = extract_closure(dependency_map, entry_point, Some(Path::new(source_root)))?;
+    Ok(engine::build_zip(&map, &entry_key, Path::new(source_root), Path::new(output_path))?)
+}
 
+#[pymodule]
+fn py_dependency_mapper<'py>(_py: Python<'py>, m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<ProjectFile>()?;
+    m.add_class::<GraphFileResult>()?;
+    m.add_class::<PipMetadata>()?;
+    m.add_class::<PipPackageInfo>()?;
+    m.add_class::<ModuleFile>()?;
+    m.add_class::<MemoryStats>()?;
+    m.add_class::<RiskyImport>()?;
+    m.add_class::<LicenseReport>()?;
+    m.add_class::<DockerContext>()?;
+    m.add_class::<DynamicImportWarning>()?;
+    m.add_class::<SkippedFile>()?;
+    m.add_class::<BuildPlan>()?;
+    m.add_class::<BuildResult>()?;
+    m.add_class::<IncludePathPlan>()?;
+    m.add_class::<BlastRadius>()?;
+    m.add_class::<ChurnReport>()?;
+    m.add_class::<NewEdge>()?;
+    m.add_class::<GitCommit>()?;
+    m.add_class::<OwnershipReport>()?;
+    m.add_class::<Metrics>()?;
+    m.add_class::<ReverseIndex>()?;
+    m.add_class::<FrozenMap>()?;
+    m.add_class::<ResolutionExplanation>()?;
+    m.add_class::<ResolutionCandidate>()?;
+    m.add_function(wrap_pyfunction!(build_dependency_map, m)?)?;
+    m.add_function(wrap_pyfunction!(build_dependency_map_incremental, m)?)?;
+    m.add_function(wrap_pyfunction!(get_dependency_graph, m)?)?;
+    m.add_function(wrap_pyfunction!(build_pip_metadata, m)?)?;
+    m.add_function(wrap_pyfunction!(build_environment_metadata, m)?)?;
+    m.add_function(wrap_pyfunction!(resolve_package_set, m)?)?;
+    m.add_function(wrap_pyfunction!(find_dependents, m)?)?;
+    m.add_function(wrap_pyfunction!(get_reverse_dependencies, m)?)?;
+    m.add_function(wrap_pyfunction!(get_external_dependencies, m)?)?;
+    m.add_function(wrap_pyfunction!(find_import_cycles, m)?)?;
+    m.add_function(wrap_pyfunction!(topological_order, m)?)?;
+    m.add_function(wrap_pyfunction!(find_source_files, m)?)?;
+    m.add_function(wrap_pyfunction!(freeze, m)?)?;
+    m.add_function(wrap_pyfunction!(to_module_map, m)?)?;
+    m.add_function(wrap_pyfunction!(memory_stats, m)?)?;
+    m.add_function(wrap_pyfunction!(to_dataframe, m)?)?;
+    m.add_function(wrap_pyfunction!(export_cytoscape, m)?)?;
+    m.add_function(wrap_pyfunction!(export_graph, m)?)?;
+    m.add_function(wrap_pyfunction!(export_html_report, m)?)?;
+    m.add_function(wrap_pyfunction!(load_coverage_json, m)?)?;
+    m.add_function(wrap_pyfunction!(merge_runtime_dependencies, m)?)?;
+    m.add_function(wrap_pyfunction!(audit_risky_imports, m)?)?;
+    m.add_function(wrap_pyfunction!(export_sbom, m)?)?;
+    m.add_function(wrap_pyfunction!(license_report, m)?)?;
+    m.add_function(wrap_pyfunction!(asset_digest, m)?)?;
+    m.add_function(wrap_pyfunction!(get_closure_hash, m)?)?;
+    m.add_function(wrap_pyfunction!(blast_radius, m)?)?;
+    m.add_function(wrap_pyfunction!(directory_digests, m)?)?;
+    m.add_function(wrap_pyfunction!(build_reverse_index, m)?)?;
+    m.add_function(wrap_pyfunction!(explain_resolution, m)?)?;
+    m.add_function(wrap_pyfunction!(churn_report, m)?)?;
+    m.add_function(wrap_pyfunction!(codeowners_report, m)?)?;
+    m.add_function(wrap_pyfunction!(export_docker_context, m)?)?;
+    m.add_function(wrap_pyfunction!(build_zip, m)?)?;
+    m.add_function(wrap_pyfunction!(files_by_hash, m)?)?;
+    m.add_function(wrap_pyfunction!(hash_index, m)?)?;
+    m.add_function(wrap_pyfunction!(dynamic_import_warnings, m)?)?;
+    m.add_function(wrap_pyfunction!(anonymize_map, m)?)?;
+    m.add_function(wrap_pyfunction!(get_metrics, m)?)?;
+    m.add_function(wrap_pyfunction!(metrics_prometheus, m)?)?;
+    Ok(())
+}
 
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::fs::{self, File};
-    use std::io::Write;
-    use tempfile::tempdir;
+    use py_dependency_mapper_core::ImportsContainer;
     use pyo3::types::PyDict;
-   
+
     fn mock_file(py: Python, imports: Vec<&str>) -> PyObject {
         let file = ProjectFile {
             hash: "dummy".to_string(),
             project_imports: imports.iter().map(|s| s.to_string()).collect(),
-            stdlib_imports: vec![],
-            third_party_imports: vec![],
+            ..Default::default()
         };
         Py::new(py, file).unwrap().into_any()
     }
 
     #[test]
-    fn test_find_dependents_logic() {
+    fn test_find_dependents_from_python_dict() {
         pyo3::prepare_freethreaded_python();
-        
+
         Python::with_gil(|py| {
             let map = PyDict::new(py);
 
             map.set_item("file_a.py", mock_file(py, vec!["file_b.py"])).unwrap();
             map.set_item("file_b.py", mock_file(py, vec![])).unwrap();
-            
+
             map.set_item("file_c.py", mock_file(py, vec!["file_d.py"])).unwrap();
             map.set_item("file_d.py", mock_file(py, vec![])).unwrap();
 
             let result = find_dependents(&map, vec![
-                "file_b.py".to_string(), 
+                "file_b.py".to_string(),
                 "file_d.py".to_string()
             ]).unwrap();
 
@@ -544,154 +750,25 @@ mod tests {
     }
 
     #[test]
-    fn test_normalize_pkg_name() {
-        assert_eq!(normalize_pkg_name("CairoSVG"), "cairosvg");
-        assert_eq!(normalize_pkg_name("google-api-core"), "google_api_core");
-        assert_eq!(normalize_pkg_name("Babel"), "babel");
-    }
-
-    #[test]
-    fn test_find_dist_info_dir_fast_path() {
-        let dir = tempdir().unwrap();
-        let site_packages = dir.path();
-        
-        let pkg_dir = site_packages.join("requests-2.32.5.dist-info");
-        fs::create_dir(&pkg_dir).unwrap();
-
-        let found = find_dist_info_dir("requests", "2.32.5", site_packages);
-        
-        let found_canon = found.map(|p| fs::canonicalize(p).unwrap());
-        let pkg_dir_canon = Some(fs::canonicalize(pkg_dir).unwrap());
-
-        assert_eq!(found_canon, pkg_dir_canon);
-    }
-    
-    #[test]
-    fn test_find_dist_info_dir_slow_path_capitalized() {
-        let dir = tempdir().unwrap();
-        let site_packages = dir.path();
-
-        let pkg_dir = site_packages.join("CairoSVG-2.7.0.dist-info");
-        fs::create_dir(&pkg_dir).unwrap();
-
-        let found = find_dist_info_dir("cairosvg", "2.7.0", site_packages);
-        
-        let found_canon = found.map(|p| fs::canonicalize(p).unwrap());
-        let pkg_dir_canon = Some(fs::canonicalize(pkg_dir).unwrap());
-
-        assert_eq!(found_canon, pkg_dir_canon);
-    }
-
-    #[test]
-    fn test_find_dist_info_dir_not_found() {
-        let dir = tempdir().unwrap();
-        let site_packages = dir.path();
-        
-        let found = find_dist_info_dir("nonexistent", "1.0.0", site_packages);
-        assert_eq!(found, None);
-    }
-
-    #[test]
-    fn test_pip_analyzer_process_package_with_record() {
-        let dir = tempdir().unwrap();
-        let site_packages = dir.path();
-
-        let dist_info = site_packages.join("test_pkg-1.0.0.dist-info");
-        fs::create_dir(&dist_info).unwrap();
-
-        let record_path = dist_info.join("RECORD");
-        let mut record_file = File::create(record_path).unwrap();
-        writeln!(record_file, "test_pkg/__init__.py,sha256=...,100").unwrap();
-        writeln!(record_file, "bin/test-cli,sha256=...,200").unwrap();
-        writeln!(record_file, "test_pkg-1.0.0.dist-info/METADATA,sha256=...,300").unwrap();
-
-        let mut analyzer = PipAnalyzer::new(site_packages);
-        let details = PackageDetails {
-            version: "1.0.0".to_string(),
-            dependencies: BTreeMap::new(),
-        };
-
-        analyzer.process_package("test-pkg", &details);
-
-        let info = analyzer.pip_package_info_map.get("test-pkg").unwrap();
-        assert_eq!(info.version, "1.0.0");
-        assert!(info.installed_paths.contains(&"test_pkg".to_string()));
-        assert!(info.installed_paths.contains(&"bin/test-cli".to_string()));
-        assert!(!info.installed_paths.iter().any(|p| p.contains(".dist-info")));
-        assert_eq!(analyzer.import_to_pip_map.get("test_pkg"), Some(&"test-pkg".to_string()));
-    }
-
-    #[test]
-    fn test_recursive_dependency_analysis() {
-        let dir = tempdir().unwrap();
-        let site_packages = dir.path();
-
-        let setup_pkg = |name: &str, version: &str| {
-            let dist = site_packages.join(format!("{}-{}.dist-info", name, version));
-            fs::create_dir(&dist).unwrap();
-            let mut record = File::create(dist.join("RECORD")).unwrap();
-            writeln!(record, "{}/__init__.py,sha256=...,100", name).unwrap();
-        };
+    fn test_imports_exposed_as_frozenset() {
+        pyo3::prepare_freethreaded_python();
 
-        setup_pkg("pkg_a", "1.0");
-        setup_pkg("pkg_b", "2.0");
-        setup_pkg("pkg_c", "3.0");
-
-        let deps_c = BTreeMap::new(); 
-        let details_c = PackageDetails { version: "3.0".to_string(), dependencies: deps_c };
-
-        let mut deps_b = BTreeMap::new();
-        deps_b.insert("pkg_c".to_string(), details_c); 
-        let details_b = PackageDetails { version: "2.0".to_string(), dependencies: deps_b };
-
-        let mut deps_a_real = BTreeMap::new();
-        deps_a_real.insert("pkg_b".to_string(), details_b);
-        
-        let details_a = PackageDetails { version: "1.0".to_string(), dependencies: deps_a_real };
-
-        let mut analyzer = PipAnalyzer::new(site_packages);
-    
-        analyzer.process_package("pkg_a", &details_a);
-        assert!(analyzer.pip_package_info_map.contains_key("pkg_a"));
-        assert!(analyzer.pip_package_info_map.contains_key("pkg_b"));
-        assert!(analyzer.pip_package_info_map.contains_key("pkg_c"));
-        assert_eq!(analyzer.pip_package_info_map.get("pkg_c").unwrap().version, "3.0");
+        Python::with_gil(|py| {
+            let mut file = ProjectFile {
+                hash: "dummy".to_string(),
+                project_imports: vec!["a.py".to_string()],
+                stdlib_imports: vec!["os".to_string()],
+                imports_container: ImportsContainer::FrozenSet,
+                ..Default::default()
+            };
+            let obj = Py::new(py, file.clone()).unwrap().into_bound(py);
+            let imports = obj.getattr("stdlib_imports").unwrap();
+            assert!(imports.is_instance_of::<pyo3::types::PyFrozenSet>());
+            assert!(imports.hash().is_ok());
+
+            file.imports_container = ImportsContainer::Tuple;
+            let obj = Py::new(py, file).unwrap().into_bound(py);
+            assert!(obj.getattr("project_imports").unwrap().is_instance_of::<pyo3::types::PyTuple>());
+        });
     }
-
-    #[test]
-    fn test_build_pip_metadata_with_manual_mappings() {
-        let dir = tempdir().unwrap();
-        let site_packages = dir.path();
-        
-        let json_path = dir.path().join("tree.json");
-        fs::write(&json_path, "{}").unwrap(); 
-
-        let toml_path = dir.path().join("mappings.toml");
-        let toml_content = r#"
-            [import_mappings]
-            "slack" = "slackclient"
-
-            [extra_dependencies]
-            "pydantic" = ["email-validator"]
-
-            [extra_package_paths]
-            "gremlinpython" = ["bin", "lib"]
-        "#;
-        fs::write(&toml_path, toml_content).unwrap();
-
-        let metadata = build_pip_metadata(
-            json_path.to_str().unwrap(),
-            site_packages.to_str().unwrap(),
-            Some(toml_path.to_str().unwrap().to_string())
-        ).unwrap();
-
-        assert_eq!(metadata.import_to_pip_map.get("slack"), Some(&"slackclient".to_string()));
-
-        let extra_deps = metadata.extra_dependencies_map.get("pydantic").unwrap();
-        assert!(extra_deps.contains(&"email-validator".to_string()));
-
-        let extra_paths = metadata.extra_paths_map.get("gremlinpython").unwrap();
-        assert!(extra_paths.contains(&"bin".to_string()));
-        assert!(extra_paths.contains(&"lib".to_string()));
-    }    
-}
\ No newline at end of file
+}